@@ -0,0 +1,389 @@
+//! Departure boards: the next departures from a stop or a station.
+
+use std::{cmp::Ordering, collections::HashMap};
+
+use chrono::{DateTime, Duration, NaiveDate, TimeZone};
+
+use crate::structures::{
+    calendar::{self, service_day_start, Calendar},
+    calendar_dates::CalendarDate,
+    frequencies::Frequency,
+    routes::Route,
+    stop_times::PickupDropOffType,
+    stops::Stop,
+    trips::Trip,
+};
+
+/// Number of days after the requested time within which departures are looked for.
+const MAX_DAYS_AHEAD: i64 = 7;
+
+/// A departure of a trip from a stop.
+#[derive(Debug, Clone)]
+pub struct Departure<'t> {
+    pub trip: &'t Trip,
+    pub route: Option<&'t Route>,
+    pub stop: &'t Stop,
+    pub stop_sequence: u16,
+    /// Service date of the trip, which is the day before for trips running past midnight.
+    pub service_date: NaiveDate,
+    /// Start time of frequency-based trips, in seconds since the start of the service day.
+    pub start_time: Option<u64>,
+    /// POSIX time of the departure.
+    pub time: i64,
+    /// Headsign of the stop time, or else of the trip.
+    pub headsign: Option<&'t str>,
+    pub platform_code: Option<&'t str>,
+    pub pickup_type: PickupDropOffType,
+}
+
+/// Finds the next departures from stops, across the service days of the schedule.
+///
+/// Departures are those of the stop times with a departure time, except the last stop of
+/// each trip and those where no pickup is available. Querying a station returns the
+/// departures from all its stops.
+///
+/// ```
+/// use chrono::{TimeZone, Utc};
+/// use gtfs_parser::{departures::DepartureBoard, GtfsReader};
+///
+/// let mut gtfs = GtfsReader::from_path("./resources/zips/gtfs.zip").unwrap();
+/// let (trips, routes, stops) = (gtfs.trips().unwrap(), gtfs.routes().unwrap(), gtfs.stops().unwrap());
+/// let (calendar, calendar_dates) = (gtfs.calendar().unwrap(), gtfs.calendar_dates().unwrap());
+/// let board = DepartureBoard::new(&trips, &routes, &stops, &calendar, &calendar_dates, &[]);
+///
+/// let after = Utc.with_ymd_and_hms(2007, 1, 1, 0, 0, 0).unwrap();
+/// let departures = board.next_departures("stop1", &after, 3);
+/// assert!(departures.len() <= 3);
+/// assert!(departures.iter().all(|departure| departure.time >= after.timestamp()));
+/// ```
+pub struct DepartureBoard<'t> {
+    routes: HashMap<&'t str, &'t Route>,
+    /// Stops of each station
+    children: HashMap<&'t str, Vec<&'t str>>,
+    calendar: &'t [Calendar],
+    calendar_dates: &'t [CalendarDate],
+    frequencies: HashMap<&'t str, Vec<&'t Frequency>>,
+    /// Trips calling at each stop, with the index of the stop time
+    calls: HashMap<&'t str, Vec<(&'t Trip, usize)>>,
+    /// Bounds of the departure times, in seconds since the start of the service day
+    earliest: i64,
+    latest: i64,
+}
+
+impl<'t> DepartureBoard<'t> {
+    pub fn new(
+        trips: &'t HashMap<String, Trip>,
+        routes: &'t [Route],
+        stops: &'t [Stop],
+        calendar: &'t [Calendar],
+        calendar_dates: &'t [CalendarDate],
+        frequencies: &'t [Frequency],
+    ) -> Self {
+        let mut children: HashMap<&str, Vec<&str>> = HashMap::new();
+        for stop in stops {
+            if let Some(parent) = &stop.parent_station {
+                children.entry(parent).or_default().push(&stop.id);
+            }
+        }
+
+        let mut by_trip: HashMap<&str, Vec<&Frequency>> = HashMap::new();
+        for frequency in frequencies {
+            by_trip
+                .entry(&frequency.trip_id)
+                .or_default()
+                .push(frequency);
+        }
+
+        let mut calls: HashMap<&str, Vec<(&Trip, usize)>> = HashMap::new();
+        let (mut earliest, mut latest) = (i64::MAX, i64::MIN);
+        for trip in trips.values() {
+            let last = trip.stop_times.len().saturating_sub(1);
+            for (index, stop_time) in trip.stop_times.iter().enumerate().take(last) {
+                let time = match stop_time.departure_time {
+                    Some(time) => time as i64,
+                    None => continue,
+                };
+                if stop_time.pickup_type == PickupDropOffType::NotAvailable {
+                    continue;
+                }
+                calls
+                    .entry(&stop_time.stop.id)
+                    .or_default()
+                    .push((trip, index));
+
+                let (first, last) = match by_trip.get(trip.id.as_str()) {
+                    Some(frequencies) => {
+                        let offset = time - first_departure(trip).unwrap_or(time);
+                        let starts = frequencies.iter().map(|f| f.start_time as i64);
+                        let ends = frequencies.iter().map(|f| f.end_time as i64);
+                        (
+                            starts.min().unwrap_or(time) + offset,
+                            ends.max().unwrap_or(time) + offset,
+                        )
+                    }
+                    None => (time, time),
+                };
+                earliest = earliest.min(first);
+                latest = latest.max(last);
+            }
+        }
+
+        Self {
+            routes: routes
+                .iter()
+                .map(|route| (route.id.as_str(), route))
+                .collect(),
+            children,
+            calendar,
+            calendar_dates,
+            frequencies: by_trip,
+            calls,
+            earliest,
+            latest,
+        }
+    }
+
+    /// The next `count` departures from a stop or a station at or after a time, sorted by
+    /// time. The time zone of `after` must be the one of the agency.
+    pub fn next_departures<Tz: TimeZone>(
+        &self,
+        stop_id: &str,
+        after: &DateTime<Tz>,
+        count: usize,
+    ) -> Vec<Departure<'t>> {
+        let mut stop_ids = vec![stop_id];
+        if let Some(children) = self.children.get(stop_id) {
+            stop_ids.extend(children);
+        }
+        let calls: Vec<(&Trip, usize)> = stop_ids
+            .iter()
+            .filter_map(|stop_id| self.calls.get(stop_id))
+            .flatten()
+            .copied()
+            .collect();
+        if calls.is_empty() || count == 0 {
+            return vec![];
+        }
+
+        let timezone = after.timezone();
+        let local_date = after.naive_local().date();
+        let after = after.timestamp();
+        // Trips of previous service days may still be running after midnight
+        let days_back = self.latest.div_euclid(24 * 3600);
+
+        let mut departures = Vec::new();
+        let mut date = local_date - Duration::days(days_back);
+        while date <= local_date + Duration::days(MAX_DAYS_AHEAD) {
+            let day_start = service_day_start(date, &timezone);
+            if count <= departures.len() {
+                departures.sort_by(chronological);
+                departures.truncate(count);
+                if departures[count - 1].time < day_start + self.earliest {
+                    break;
+                }
+            }
+
+            let services = calendar::services_on(self.calendar, self.calendar_dates, date);
+            for (trip, index) in calls
+                .iter()
+                .filter(|(trip, _)| services.contains(&trip.service_id))
+            {
+                self.push_departures(&mut departures, trip, *index, date, day_start, after);
+            }
+            date = date.succ_opt().expect("dates of schedules are in range");
+        }
+
+        departures.sort_by(chronological);
+        departures.truncate(count);
+        departures
+    }
+
+    /// Pushes the departures of a trip from a stop on a service date, one per start time for
+    /// frequency-based trips.
+    fn push_departures(
+        &self,
+        departures: &mut Vec<Departure<'t>>,
+        trip: &'t Trip,
+        index: usize,
+        service_date: NaiveDate,
+        day_start: i64,
+        after: i64,
+    ) {
+        let stop_time = &trip.stop_times[index];
+        let time = match stop_time.departure_time {
+            Some(time) => time as i64,
+            None => return,
+        };
+        let runs: Vec<(Option<u64>, i64)> = match self.frequencies.get(trip.id.as_str()) {
+            Some(frequencies) => {
+                let first = first_departure(trip).unwrap_or(time);
+                frequencies
+                    .iter()
+                    .flat_map(|frequency| frequency.start_times())
+                    .map(|start| (Some(start), time - first + start as i64))
+                    .collect()
+            }
+            None => vec![(None, time)],
+        };
+
+        for (start_time, time) in runs {
+            let time = day_start + time;
+            if time < after {
+                continue;
+            }
+            departures.push(Departure {
+                trip,
+                route: self.routes.get(trip.route_id.as_str()).copied(),
+                stop: &stop_time.stop,
+                stop_sequence: stop_time.stop_sequence,
+                service_date,
+                start_time,
+                time,
+                headsign: stop_time
+                    .stop_headsign
+                    .as_deref()
+                    .or(trip.headsign.as_deref()),
+                platform_code: stop_time.stop.platform_code.as_deref(),
+                pickup_type: stop_time.pickup_type,
+            });
+        }
+    }
+}
+
+fn chronological(a: &Departure, b: &Departure) -> Ordering {
+    a.time
+        .cmp(&b.time)
+        .then_with(|| a.trip.route_id.cmp(&b.trip.route_id))
+        .then_with(|| a.trip.id.cmp(&b.trip.id))
+        .then_with(|| a.start_time.cmp(&b.start_time))
+}
+
+fn first_departure(trip: &Trip) -> Option<i64> {
+    trip.stop_times
+        .iter()
+        .find_map(|stop_time| stop_time.departure_time.or(stop_time.arrival_time))
+        .map(|time| time as i64)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::reader_from_files;
+    use chrono::Utc;
+
+    struct Static {
+        trips: HashMap<String, Trip>,
+        routes: Vec<Route>,
+        stops: Vec<Stop>,
+        calendar: Vec<Calendar>,
+        frequencies: Vec<Frequency>,
+    }
+
+    /// Station ST has platforms P1 and P2. T3 runs after midnight and F1 every 20 minutes.
+    fn feed() -> Static {
+        let mut gtfs = reader_from_files(&[
+            (
+                "calendar.txt",
+                "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\n\
+                 WD,1,1,1,1,1,0,0,20240101,20241231\n",
+            ),
+            (
+                "routes.txt",
+                "route_id,route_short_name,route_long_name,route_type\nR1,1,,3\n",
+            ),
+            (
+                "stops.txt",
+                "stop_id,stop_name,location_type,parent_station,platform_code\n\
+                 ST,Station,1,,\nP1,Station,0,ST,1\nP2,Station,0,ST,2\nX,Elsewhere,0,,\n",
+            ),
+            (
+                "trips.txt",
+                "route_id,service_id,trip_id,trip_headsign\n\
+                 R1,WD,T1,Elsewhere\nR1,WD,T2,Elsewhere\nR1,WD,T3,Night\nR1,WD,F1,Shuttle\n",
+            ),
+            (
+                "stop_times.txt",
+                "trip_id,arrival_time,departure_time,stop_id,stop_sequence,stop_headsign,pickup_type\n\
+                 T1,08:00:00,08:00:00,P1,1,Express,0\nT1,08:10:00,08:10:00,X,2,,0\n\
+                 T2,08:05:00,08:05:00,P2,1,,0\nT2,08:15:00,08:15:00,X,2,,0\n\
+                 T3,25:10:00,25:10:00,P1,1,,0\nT3,25:20:00,25:20:00,X,2,,0\n\
+                 F1,00:00:00,00:00:00,X,1,,1\nF1,00:05:00,00:05:00,P2,2,,0\nF1,00:10:00,00:10:00,X,3,,0\n",
+            ),
+            (
+                "frequencies.txt",
+                "trip_id,start_time,end_time,headway_secs\nF1,06:00:00,07:00:00,1200\n",
+            ),
+        ]);
+        Static {
+            trips: gtfs.trips().unwrap(),
+            routes: gtfs.routes().unwrap(),
+            stops: gtfs.stops().unwrap(),
+            calendar: gtfs.calendar().unwrap(),
+            frequencies: gtfs.frequencies().unwrap(),
+        }
+    }
+
+    fn summary(departures: &[Departure]) -> Vec<(String, String)> {
+        departures
+            .iter()
+            .map(|departure| {
+                let time = Utc.timestamp_opt(departure.time, 0).unwrap();
+                (
+                    departure.trip.id.clone(),
+                    time.format("%a %H:%M").to_string(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn next_departures() {
+        let gtfs = feed();
+        let board = DepartureBoard::new(
+            &gtfs.trips,
+            &gtfs.routes,
+            &gtfs.stops,
+            &gtfs.calendar,
+            &[],
+            &gtfs.frequencies,
+        );
+        let monday = Utc.with_ymd_and_hms(2024, 3, 4, 6, 10, 0).unwrap();
+
+        let departures = board.next_departures("ST", &monday, 4);
+        assert_eq!(
+            summary(&departures),
+            [
+                ("F1".to_owned(), "Mon 06:25".to_owned()),
+                ("F1".to_owned(), "Mon 06:45".to_owned()),
+                ("T1".to_owned(), "Mon 08:00".to_owned()),
+                ("T2".to_owned(), "Mon 08:05".to_owned()),
+            ]
+        );
+        assert_eq!(departures[0].start_time, Some(6 * 3600 + 20 * 60));
+        assert_eq!(departures[2].headsign, Some("Express"));
+        assert_eq!(departures[3].headsign, Some("Elsewhere"));
+        assert_eq!(departures[3].platform_code, Some("2"));
+        assert_eq!(departures[3].route.unwrap().short_name, "1");
+
+        // Only P1 is served by T1 and T3, F1 does not pick up passengers at X
+        let departures = board.next_departures("P1", &monday, 10);
+        assert_eq!(departures.len(), 10);
+        assert!(departures.iter().all(|departure| departure.stop.id == "P1"));
+        assert!(board.next_departures("X", &monday, 10).is_empty());
+
+        // T3 of Friday runs after midnight, and the next service day is Monday
+        let saturday = Utc.with_ymd_and_hms(2024, 3, 9, 0, 0, 0).unwrap();
+        let departures = board.next_departures("P1", &saturday, 2);
+        assert_eq!(
+            summary(&departures),
+            [
+                ("T3".to_owned(), "Sat 01:10".to_owned()),
+                ("T1".to_owned(), "Mon 08:00".to_owned()),
+            ]
+        );
+        assert_eq!(
+            departures[0].service_date,
+            NaiveDate::from_ymd_opt(2024, 3, 8).unwrap()
+        );
+    }
+}
@@ -1,11 +1,112 @@
+use std::fmt;
+
+use serde::de::DeserializeOwned;
 use thiserror::Error;
 
+/// Context of an error affecting a single record of a file.
 #[derive(Debug)]
 pub struct LineError {
+    /// 1-based line of the record in the file, the header being the first line.
+    pub line: u64,
+    /// Name of the column which failed, when it could be located.
+    pub column: Option<String>,
     pub headers: Vec<String>,
     pub values: Vec<String>,
 }
 
+impl LineError {
+    /// Value of the column which failed.
+    pub fn value(&self) -> Option<&str> {
+        let column = self.column.as_ref()?;
+        let index = self.headers.iter().position(|header| header == column)?;
+        self.values.get(index).map(String::as_str)
+    }
+
+    /// Locates the column a csv error, raised while deserializing the record as `D`,
+    /// relates to.
+    pub(crate) fn locate<D: DeserializeOwned>(mut self, err: &csv::Error) -> Self {
+        if let csv::ErrorKind::Deserialize { err, .. } = err.kind() {
+            self.column = match err.kind() {
+                csv::DeserializeErrorKind::Message(message)
+                    if message.starts_with("missing field") =>
+                {
+                    Some(
+                        message
+                            .trim_start_matches("missing field ")
+                            .trim_matches('`')
+                            .to_owned(),
+                    )
+                }
+                _ => self
+                    .column_index::<D>(err)
+                    .and_then(|index| self.headers.get(index))
+                    .cloned(),
+            };
+        }
+        self
+    }
+
+    /// Index of the column a deserialization error relates to.
+    ///
+    /// Errors raised by custom deserializers are not located by csv. As the fields of a
+    /// record are deserialized in the order of its columns, the column is then the last one
+    /// of the shortest part of the record which fails with the same error.
+    fn column_index<D: DeserializeOwned>(&self, err: &csv::DeserializeError) -> Option<usize> {
+        if let Some(field) = err.field() {
+            return Some(field as usize);
+        }
+
+        (0..self.values.len().min(self.headers.len())).find(|end| {
+            let headers = csv::StringRecord::from(self.headers[..=*end].to_vec());
+            let values = csv::StringRecord::from(self.values[..=*end].to_vec());
+            match values.deserialize::<D>(Some(&headers)) {
+                Err(prefix_err) => match prefix_err.kind() {
+                    csv::ErrorKind::Deserialize {
+                        err: prefix_err, ..
+                    } => prefix_err.kind().to_string() == err.kind().to_string(),
+                    _ => false,
+                },
+                Ok(_) => false,
+            }
+        })
+    }
+}
+
+impl fmt::Display for LineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}", self.line)?;
+        if let Some(column) = &self.column {
+            write!(f, ", column '{}'", column)?;
+            if let Some(value) = self.value() {
+                write!(f, " (value '{}')", value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn at_line(line: &Option<u64>) -> String {
+    match line {
+        Some(line) => format!(" at line {}", line),
+        None => String::new(),
+    }
+}
+
+fn at(line_in_error: &Option<LineError>) -> String {
+    match line_in_error {
+        Some(line_in_error) => format!(" at {}", line_in_error),
+        None => String::new(),
+    }
+}
+
+/// Why a csv record failed, without the position already given by the [LineError].
+fn reason(err: &csv::Error) -> String {
+    match err.kind() {
+        csv::ErrorKind::Deserialize { err, .. } => err.kind().to_string(),
+        _ => err.to_string(),
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("File not found {0}")]
@@ -17,13 +118,18 @@ pub enum Error {
         #[source]
         source: std::io::Error,
     },
-    #[error("Failed to read csv file '{filename}'")]
+    #[error("Failed to read csv file '{filename}'{}: {}", at(.line_in_error), reason(.source))]
     CSVError {
         filename: String,
         #[source]
         source: csv::Error,
         line_in_error: Option<LineError>,
     },
+    #[error("Invalid UTF-8 in '{filename}' at {line_in_error}")]
+    InvalidUtf8 {
+        filename: String,
+        line_in_error: LineError,
+    },
     #[error(transparent)]
     Zip(#[from] zip::result::ZipError),
     #[error("Failed to read file")]
@@ -34,9 +140,50 @@ pub enum Error {
     InvalidColor(String),
     #[error("'{0}' is not a valid time")]
     InvalidTime(String),
-    #[error("The id {0} is not known")]
-    ReferenceError(String),
+    #[error("'{0}' is not a valid date")]
+    InvalidDate(String),
+    #[error("'{0}' is not a valid code")]
+    InvalidCode(String),
+    #[error("Unknown {field} value {value} in '{filename}' at line {line}")]
+    UnknownCode {
+        filename: String,
+        line: u64,
+        field: String,
+        value: u16,
+    },
+    #[error("Unknown {field} '{id}' in '{filename}'{}, it is not defined in '{parent_filename}'", at_line(.line))]
+    ReferenceError {
+        filename: String,
+        /// Line of the record, when known
+        line: Option<u64>,
+        field: String,
+        id: String,
+        parent_filename: String,
+    },
+    #[error("Failed to write csv")]
+    CSVWrite(#[from] csv::Error),
+    #[error("Only structs can be written as csv records")]
+    NotARecord,
+    #[error("Failed to serialize to JSON")]
+    Json(#[from] serde_json::Error),
     #[cfg(feature = "read-url")]
     #[error("Failed to download file")]
     Fetch(#[from] reqwest::Error),
+    #[cfg(feature = "read-url")]
+    #[error("'{0}' is not modified, but is not in the cache")]
+    NotCached(String),
+    #[cfg(feature = "realtime")]
+    #[error("Failed to decode GTFS-Realtime feed")]
+    Decode(#[from] prost::DecodeError),
+    #[cfg(feature = "realtime")]
+    #[error("Unknown {field} '{id}' in realtime entity '{entity}', it is not defined in '{parent_filename}'")]
+    UnknownRealtimeReference {
+        entity: String,
+        field: String,
+        id: String,
+        parent_filename: String,
+    },
+    #[cfg(feature = "realtime")]
+    #[error("Realtime entity id '{0}' is used more than once")]
+    DuplicateEntity(String),
 }
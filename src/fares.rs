@@ -0,0 +1,440 @@
+//! Fare calculation with the fare classes of fare_attributes.txt and fare_rules.txt
+//! (GTFS Fares v1).
+
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    ops::Range,
+};
+
+use crate::{
+    routing::{Journey, Leg},
+    structures::{
+        fare_attributes::{FareAttribute, Transfers},
+        fare_rules::FareRule,
+        routes::{route_agency, Route},
+        stops::Stop,
+    },
+};
+
+/// A ride of an itinerary to price. Times are POSIX.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FareLeg<'a> {
+    pub route_id: &'a str,
+    pub from_stop_id: &'a str,
+    pub to_stop_id: &'a str,
+    pub departure: i64,
+    pub arrival: i64,
+}
+
+impl<'s> FareLeg<'s> {
+    /// The rides of a journey planned over the timetable.
+    pub fn from_journey(journey: &Journey<'s>) -> Vec<FareLeg<'s>> {
+        journey
+            .legs
+            .iter()
+            .filter_map(|leg| match leg {
+                Leg::Ride {
+                    trip,
+                    from,
+                    to,
+                    departure,
+                    arrival,
+                    ..
+                } => Some(FareLeg {
+                    route_id: &trip.route_id,
+                    from_stop_id: &from.id,
+                    to_stop_id: &to.id,
+                    departure: *departure,
+                    arrival: *arrival,
+                }),
+                Leg::Walk { .. } => None,
+            })
+            .collect()
+    }
+}
+
+/// A fare paid for consecutive legs of an itinerary.
+#[derive(Debug, Clone)]
+pub struct FareSegment<'f> {
+    pub fare: &'f FareAttribute,
+    /// Positions of the legs in the itinerary
+    pub legs: Range<usize>,
+}
+
+/// The fares to pay for an itinerary.
+#[derive(Debug, Clone)]
+pub struct FareTotal<'f> {
+    pub price: f64,
+    pub currency: String,
+    pub segments: Vec<FareSegment<'f>>,
+}
+
+/// The rules of a fare class.
+#[derive(Default)]
+struct FareClass<'f> {
+    route_ids: HashSet<&'f str>,
+    /// Origin and destination zones, either of which may be any
+    zones: Vec<(Option<&'f str>, Option<&'f str>)>,
+    contains: BTreeSet<&'f str>,
+}
+
+/// Finds the cheapest fares for itineraries.
+///
+/// As in most trip planners, the rules of a fare class are combined: the fare applies to
+/// legs whose routes are all among those of its rules, whose origin and destination zones
+/// match one of its rules, and which go through exactly the zones its rules contain. Fares
+/// without rules apply to every leg. Legs are priced together only if the transfers between
+/// them are allowed by the fare, the last one departing within its `transfer_duration`.
+/// Fares of an agency apply to the routes without `agency_id`, which belong to the only
+/// agency of the feed.
+///
+/// ```
+/// use gtfs_parser::{fares::{FareCalculator, FareLeg}, GtfsReader};
+///
+/// let mut gtfs = GtfsReader::from_path("./resources/zips/gtfs.zip").unwrap();
+/// let fare_attributes = gtfs.fare_attributes().unwrap();
+/// let fare_rules = gtfs.fare_rules().unwrap();
+/// let routes = gtfs.routes().unwrap();
+/// let stops = gtfs.stops().unwrap();
+/// let calculator = FareCalculator::new(&fare_attributes, &fare_rules, &routes, &stops);
+///
+/// let legs = [FareLeg {
+///     route_id: "A",
+///     from_stop_id: "stop1",
+///     to_stop_id: "stop2",
+///     departure: 1152000000,
+///     arrival: 1152000600,
+/// }];
+/// assert_eq!(calculator.currencies(), ["USD"]);
+/// let total = calculator.cheapest(&legs, "USD").unwrap();
+/// assert_eq!(total.price, 0.1);
+/// assert_eq!(total.segments[0].fare.id, "1");
+/// ```
+pub struct FareCalculator<'f> {
+    fares: Vec<(&'f FareAttribute, FareClass<'f>)>,
+    /// Agency of each route, if given
+    agencies: HashMap<&'f str, Option<&'f str>>,
+    zones: HashMap<&'f str, &'f str>,
+}
+
+impl<'f> FareCalculator<'f> {
+    pub fn new(
+        fare_attributes: &'f [FareAttribute],
+        fare_rules: &'f [FareRule],
+        routes: &'f [Route],
+        stops: &'f [Stop],
+    ) -> Self {
+        let mut classes: HashMap<&str, FareClass> = HashMap::new();
+        for rule in fare_rules {
+            let class = classes.entry(&rule.id).or_default();
+            if let Some(route_id) = &rule.route_id {
+                class.route_ids.insert(route_id);
+            }
+            if rule.origin_id.is_some() || rule.destination_id.is_some() {
+                class
+                    .zones
+                    .push((rule.origin_id.as_deref(), rule.destination_id.as_deref()));
+            }
+            if let Some(contains_id) = &rule.contains_id {
+                class.contains.insert(contains_id);
+            }
+        }
+
+        let agency_ids = routes
+            .iter()
+            .filter_map(|route| route.agency_id.as_deref())
+            .chain(
+                fare_attributes
+                    .iter()
+                    .filter_map(|fare| fare.agency_id.as_deref()),
+            )
+            .collect();
+        Self {
+            fares: fare_attributes
+                .iter()
+                .map(|fare| (fare, classes.remove(fare.id.as_str()).unwrap_or_default()))
+                .collect(),
+            agencies: routes
+                .iter()
+                .map(|route| (route.id.as_str(), route_agency(route, &agency_ids)))
+                .collect(),
+            zones: stops
+                .iter()
+                .filter_map(|stop| Some((stop.id.as_str(), stop.zone_id.as_deref()?)))
+                .collect(),
+        }
+    }
+
+    /// Currencies of the fares, in alphabetical order.
+    pub fn currencies(&self) -> Vec<&'f str> {
+        let currencies: BTreeSet<&str> = self
+            .fares
+            .iter()
+            .map(|(fare, _)| fare.currency.as_str())
+            .collect();
+        currencies.into_iter().collect()
+    }
+
+    /// Cheapest fares of a currency covering every leg of an itinerary, none if some cannot
+    /// be priced in this currency.
+    pub fn cheapest(&self, legs: &[FareLeg], currency: &str) -> Option<FareTotal<'f>> {
+        // Cheapest price of the first legs, with the last segment
+        let mut best: Vec<Option<(f64, FareSegment)>> = vec![None; legs.len() + 1];
+        for end in 1..=legs.len() {
+            for start in 0..end {
+                let before = match start {
+                    0 => 0.0,
+                    _ => match &best[start] {
+                        Some((price, _)) => *price,
+                        None => continue,
+                    },
+                };
+                for (fare, class) in &self.fares {
+                    if fare.currency != currency || !self.applies(fare, class, &legs[start..end]) {
+                        continue;
+                    }
+                    let price = before + fare.price;
+                    let cheaper = match &best[end] {
+                        Some((best, _)) => price < *best,
+                        None => true,
+                    };
+                    if cheaper {
+                        let segment = FareSegment {
+                            fare,
+                            legs: start..end,
+                        };
+                        best[end] = Some((price, segment));
+                    }
+                }
+            }
+        }
+
+        let (price, _) = best[legs.len()].clone()?;
+        let mut segments = Vec::new();
+        let mut end = legs.len();
+        while end > 0 {
+            let (_, segment) = best[end].clone()?;
+            end = segment.legs.start;
+            segments.push(segment);
+        }
+        segments.reverse();
+
+        Some(FareTotal {
+            price,
+            currency: currency.to_owned(),
+            segments,
+        })
+    }
+
+    /// Whether a fare covers consecutive legs.
+    fn applies(&self, fare: &FareAttribute, class: &FareClass, legs: &[FareLeg]) -> bool {
+        let (first, last) = match (legs.first(), legs.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return false,
+        };
+
+        let allowed_transfers = match fare.transfers {
+            Transfers::Unlimited => usize::MAX,
+            Transfers::NoTransfer => 0,
+            Transfers::UniqueTransfer => 1,
+            Transfers::TwoTransfers => 2,
+            Transfers::Other(transfers) => transfers as usize,
+        };
+        if legs.len() - 1 > allowed_transfers {
+            return false;
+        }
+        if let Some(duration) = fare.transfer_duration {
+            if legs.len() > 1 && last.departure - first.departure > duration as i64 {
+                return false;
+            }
+        }
+
+        if let Some(agency_id) = &fare.agency_id {
+            let agencies = legs.iter().all(|leg| {
+                self.agencies.get(leg.route_id).copied().flatten() == Some(agency_id.as_str())
+            });
+            if !agencies {
+                return false;
+            }
+        }
+        if !class.route_ids.is_empty()
+            && !legs
+                .iter()
+                .all(|leg| class.route_ids.contains(leg.route_id))
+        {
+            return false;
+        }
+
+        let origin = self.zones.get(first.from_stop_id).copied();
+        let destination = self.zones.get(last.to_stop_id).copied();
+        let zone_matches = |zone: Option<&str>, actual: Option<&str>| match zone {
+            Some(zone) => actual == Some(zone),
+            None => true,
+        };
+        if !class.zones.is_empty()
+            && !class
+                .zones
+                .iter()
+                .any(|(from, to)| zone_matches(*from, origin) && zone_matches(*to, destination))
+        {
+            return false;
+        }
+
+        if !class.contains.is_empty() {
+            let zones: BTreeSet<&str> = legs
+                .iter()
+                .flat_map(|leg| [leg.from_stop_id, leg.to_stop_id])
+                .filter_map(|stop| self.zones.get(stop).copied())
+                .collect();
+            if zones != class.contains {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::reader_from_files;
+
+    fn leg<'a>(route_id: &'a str, from: &'a str, to: &'a str, minutes: i64) -> FareLeg<'a> {
+        FareLeg {
+            route_id,
+            from_stop_id: from,
+            to_stop_id: to,
+            departure: 1709539200 + minutes * 60,
+            arrival: 1709539200 + (minutes + 10) * 60,
+        }
+    }
+
+    #[test]
+    fn cheapest() {
+        let mut gtfs = reader_from_files(&[
+            (
+                "stops.txt",
+                "stop_id,stop_name,zone_id\nA,A,z1\nB,B,z1\nC,C,z2\nD,D,z2\n",
+            ),
+            (
+                "routes.txt",
+                "route_id,agency_id,route_short_name,route_long_name,route_type\n\
+                 R1,X,1,,3\nR2,X,2,,3\nR3,Y,3,,3\n",
+            ),
+            (
+                "fare_attributes.txt",
+                "fare_id,price,currency_type,payment_method,transfers,agency_id,transfer_duration\n\
+                 bus,2.0,EUR,0,1,X,3600\n\
+                 od,3.0,EUR,1,0,,\n\
+                 across,3.5,EUR,1,,,\n\
+                 dollar,1.0,USD,0,,,\n",
+            ),
+            (
+                "fare_rules.txt",
+                "fare_id,route_id,origin_id,destination_id,contains_id\n\
+                 bus,R1,,,\nbus,R2,,,\n\
+                 od,,z1,z2,\n\
+                 across,,,,z1\nacross,,,,z2\n",
+            ),
+        ]);
+        let fare_attributes = gtfs.fare_attributes().unwrap();
+        let fare_rules = gtfs.fare_rules().unwrap();
+        let routes = gtfs.routes().unwrap();
+        let stops = gtfs.stops().unwrap();
+        let calculator = FareCalculator::new(&fare_attributes, &fare_rules, &routes, &stops);
+        let price = |legs: &[FareLeg]| calculator.cheapest(legs, "EUR").map(|total| total.price);
+
+        // One transfer is allowed within the hour
+        let total = calculator
+            .cheapest(&[leg("R1", "A", "B", 0), leg("R2", "B", "A", 30)], "EUR")
+            .unwrap();
+        assert_eq!(total.price, 2.0);
+        assert_eq!(total.currency, "EUR");
+        assert_eq!(total.segments.len(), 1);
+        assert_eq!(total.segments[0].fare.id, "bus");
+        assert_eq!(total.segments[0].legs, 0..2);
+        assert_eq!(
+            price(&[
+                leg("R1", "A", "B", 0),
+                leg("R2", "B", "A", 30),
+                leg("R1", "A", "B", 45)
+            ]),
+            Some(4.0)
+        );
+        assert_eq!(
+            price(&[leg("R1", "A", "B", 0), leg("R2", "B", "A", 90)]),
+            Some(4.0)
+        );
+
+        // By zones
+        assert_eq!(price(&[leg("R3", "A", "C", 0)]), Some(3.0));
+        assert_eq!(price(&[leg("R3", "C", "A", 0)]), Some(3.5));
+        assert_eq!(price(&[leg("R3", "C", "D", 0)]), None);
+        // Cheaper than the bus and the zonal fare
+        assert_eq!(
+            price(&[leg("R1", "A", "B", 0), leg("R3", "B", "C", 20)]),
+            Some(3.5)
+        );
+
+        // Fares of different currencies are priced apart
+        assert_eq!(calculator.currencies(), ["EUR", "USD"]);
+        let total = calculator
+            .cheapest(&[leg("R3", "C", "D", 0)], "USD")
+            .unwrap();
+        assert_eq!(total.price, 1.0);
+        assert_eq!(total.currency, "USD");
+        assert!(calculator
+            .cheapest(&[leg("R3", "C", "D", 0)], "GBP")
+            .is_none());
+    }
+
+    #[test]
+    fn route_without_agency() {
+        let mut gtfs = reader_from_files(&[
+            ("stops.txt", "stop_id,stop_name\nA,A\nB,B\n"),
+            (
+                "routes.txt",
+                "route_id,route_short_name,route_long_name,route_type\nR1,1,,3\n",
+            ),
+            (
+                "fare_attributes.txt",
+                "fare_id,price,currency_type,payment_method,transfers,agency_id\n\
+                 bus,2.0,EUR,0,0,X\n",
+            ),
+        ]);
+        let fare_attributes = gtfs.fare_attributes().unwrap();
+        let routes = gtfs.routes().unwrap();
+        let stops = gtfs.stops().unwrap();
+        let calculator = FareCalculator::new(&fare_attributes, &[], &routes, &stops);
+
+        let total = calculator.cheapest(&[leg("R1", "A", "B", 0)], "EUR");
+        assert_eq!(total.map(|total| total.price), Some(2.0));
+        // Unknown routes
+        assert!(calculator
+            .cheapest(&[leg("R2", "A", "B", 0)], "EUR")
+            .is_none());
+
+        // With several agencies, a route without agency_id belongs to none of them
+        let mut gtfs = reader_from_files(&[
+            ("stops.txt", "stop_id,stop_name\nA,A\nB,B\n"),
+            (
+                "routes.txt",
+                "route_id,agency_id,route_short_name,route_long_name,route_type\n\
+                 R1,,1,,3\nR2,Y,2,,3\n",
+            ),
+            (
+                "fare_attributes.txt",
+                "fare_id,price,currency_type,payment_method,transfers,agency_id\n\
+                 bus,2.0,EUR,0,0,X\n",
+            ),
+        ]);
+        let fare_attributes = gtfs.fare_attributes().unwrap();
+        let routes = gtfs.routes().unwrap();
+        let stops = gtfs.stops().unwrap();
+        let calculator = FareCalculator::new(&fare_attributes, &[], &routes, &stops);
+        assert!(calculator
+            .cheapest(&[leg("R1", "A", "B", 0)], "EUR")
+            .is_none());
+    }
+}
@@ -0,0 +1,418 @@
+//! Downloads of feeds kept in a local cache, refreshed with conditional requests.
+
+use std::{
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+use reqwest::{
+    blocking::{Client, Response},
+    header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+    StatusCode,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::Error,
+    hashing::{self, HashingReader},
+    GtfsReader,
+};
+
+/// What is known of a cached feed, stored next to it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    /// Hexadecimal SHA-256 digest of the cached zip file
+    sha256: String,
+}
+
+/// A feed fetched by a [FeedFetcher].
+#[derive(Debug, Clone)]
+pub struct FetchedFeed {
+    /// Path of the zip file in the cache
+    pub path: PathBuf,
+    /// Whether the feed differs from the one previously cached
+    pub changed: bool,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// Hexadecimal SHA-256 digest of the zip file
+    pub sha256: String,
+}
+
+impl FetchedFeed {
+    pub fn reader(&self) -> Result<GtfsReader, Error> {
+        GtfsReader::from_path(&self.path)
+    }
+}
+
+/// Downloads feeds into a cache directory, along with their `ETag` and `Last-Modified`
+/// headers. Feeds already in the cache are requested conditionally, so that they are only
+/// downloaded again when they have changed.
+///
+/// Requests failing to connect, timing out or answered with a server error or a
+/// `429 Too Many Requests` are retried, as are downloads interrupted before the end of the
+/// feed, waiting twice as long before each new attempt.
+///
+/// ```no_run
+/// use gtfs_parser::fetch::FeedFetcher;
+/// use std::time::Duration;
+///
+/// let fetcher = FeedFetcher::new("./cache")
+///     .header("x-api-key", "secret")
+///     .timeout(Duration::from_secs(300))
+///     .retries(3);
+///
+/// let feed = fetcher.fetch("https://example.com/gtfs.zip").unwrap();
+/// if feed.changed {
+///     let mut gtfs = feed.reader().unwrap();
+///     println!("{} stops", gtfs.stops().unwrap().len());
+/// }
+/// ```
+pub struct FeedFetcher {
+    cache_dir: PathBuf,
+    headers: Vec<(String, String)>,
+    timeout: Option<Duration>,
+    retries: u32,
+    retry_delay: Duration,
+}
+
+impl FeedFetcher {
+    /// Fetcher caching feeds in a directory, created if it does not exist.
+    pub fn new<P: AsRef<Path>>(cache_dir: P) -> Self {
+        Self {
+            cache_dir: cache_dir.as_ref().to_owned(),
+            headers: Vec::new(),
+            timeout: None,
+            retries: 2,
+            retry_delay: Duration::from_secs(1),
+        }
+    }
+
+    /// Header sent with every request, e.g. an API key.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_owned(), value.to_owned()));
+        self
+    }
+
+    /// Longest time a request may take, including the download of the feed. Defaults to
+    /// no limit.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Number of times a failed request is retried. Defaults to 2.
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Time to wait before retrying a request the first time. Defaults to 1 second.
+    pub fn retry_delay(mut self, retry_delay: Duration) -> Self {
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    /// Path of the zip file of a feed in the cache.
+    pub fn cache_path(&self, url: &str) -> PathBuf {
+        self.cache_dir.join(format!("{}.zip", cache_key(url)))
+    }
+
+    /// Fetches a feed, unless the cached one is still current.
+    pub fn fetch(&self, url: &str) -> Result<FetchedFeed, Error> {
+        let path = self.cache_path(url);
+        let entry_path = path.with_extension("json");
+        let cached = if path.exists() {
+            read_entry(&entry_path)
+        } else {
+            None
+        };
+
+        // Downloaded next to the cached feed, which is only replaced once complete
+        fs::create_dir_all(&self.cache_dir)?;
+        let download_path = path.with_extension("download");
+        let (etag, last_modified, sha256) =
+            match self.download(url, cached.as_ref(), &download_path)? {
+                Download::NotModified => {
+                    // Only cached feeds are requested conditionally
+                    let cached = cached.ok_or_else(|| Error::NotCached(url.to_owned()))?;
+                    return Ok(FetchedFeed {
+                        path,
+                        changed: false,
+                        etag: cached.etag,
+                        last_modified: cached.last_modified,
+                        sha256: cached.sha256,
+                    });
+                }
+                Download::Complete {
+                    etag,
+                    last_modified,
+                    sha256,
+                } => (etag, last_modified, sha256),
+            };
+        fs::rename(&download_path, &path)?;
+
+        let changed = match &cached {
+            Some(cached) => cached.sha256 != sha256,
+            None => true,
+        };
+        let entry = CacheEntry {
+            url: url.to_owned(),
+            etag: etag.clone(),
+            last_modified: last_modified.clone(),
+            sha256: sha256.clone(),
+        };
+        fs::write(&entry_path, serde_json::to_vec_pretty(&entry)?)?;
+
+        Ok(FetchedFeed {
+            path,
+            changed,
+            etag,
+            last_modified,
+            sha256,
+        })
+    }
+
+    /// Requests a feed and downloads it to a path, retrying when either fails.
+    fn download(
+        &self,
+        url: &str,
+        cached: Option<&CacheEntry>,
+        path: &Path,
+    ) -> Result<Download, Error> {
+        let client = Client::builder().timeout(self.timeout).build()?;
+        let mut delay = self.retry_delay;
+        let mut attempt = 0;
+        loop {
+            let last = attempt == self.retries;
+            let mut request = client.get(url);
+            for (name, value) in &self.headers {
+                request = request.header(name.as_str(), value.as_str());
+            }
+            if let Some(cached) = cached {
+                if let Some(etag) = &cached.etag {
+                    request = request.header(IF_NONE_MATCH, etag.as_str());
+                }
+                if let Some(last_modified) = &cached.last_modified {
+                    request = request.header(IF_MODIFIED_SINCE, last_modified.as_str());
+                }
+            }
+
+            match request.send() {
+                Ok(response) => {
+                    let status = response.status();
+                    if status == StatusCode::NOT_MODIFIED {
+                        return Ok(Download::NotModified);
+                    }
+                    let transient =
+                        status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS;
+                    if !transient || last {
+                        match save(response.error_for_status()?, path) {
+                            Ok(download) => return Ok(download),
+                            Err(err) => {
+                                // Not left behind, whether the download is retried or not
+                                let _ = fs::remove_file(path);
+                                if last {
+                                    return Err(err);
+                                }
+                            }
+                        }
+                    }
+                }
+                Err(err) => {
+                    if !(err.is_connect() || err.is_timeout()) || last {
+                        return Err(err.into());
+                    }
+                }
+            }
+            thread::sleep(delay);
+            delay *= 2;
+            attempt += 1;
+        }
+    }
+}
+
+/// Outcome of the request for a feed.
+enum Download {
+    NotModified,
+    Complete {
+        etag: Option<String>,
+        last_modified: Option<String>,
+        sha256: String,
+    },
+}
+
+/// Writes the body of a response to a file.
+fn save(response: Response, path: &Path) -> Result<Download, Error> {
+    let header = |name| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(ToOwned::to_owned)
+    };
+    let etag = header(ETAG);
+    let last_modified = header(LAST_MODIFIED);
+
+    let mut body = HashingReader::new(response);
+    io::copy(&mut body, &mut File::create(path)?)?;
+    Ok(Download::Complete {
+        etag,
+        last_modified,
+        sha256: hashing::to_hex(&body.finalize()),
+    })
+}
+
+fn cache_key(url: &str) -> String {
+    hashing::to_hex(&hashing::content_hash(url.as_bytes()))
+}
+
+/// The cache entry of a feed, none if it cannot be read, in which case the feed is
+/// downloaded again.
+fn read_entry(path: &Path) -> Option<CacheEntry> {
+    serde_json::from_slice(&fs::read(path).ok()?).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+    };
+
+    /// Serves the given responses, one per connection, and returns the headers of the
+    /// requests received, in lowercase. The length of the body is sent unless the headers
+    /// of the response give another one.
+    fn serve(
+        responses: Vec<(&'static str, &'static str, Vec<u8>)>,
+    ) -> (String, thread::JoinHandle<Vec<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/gtfs.zip", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let mut requests = Vec::new();
+            for (status, headers, body) in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = Vec::new();
+                let mut reader = BufReader::new(&stream);
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    request.push(line.trim_end().to_lowercase());
+                    line.clear();
+                }
+                let length = if headers.contains("Content-Length") {
+                    String::new()
+                } else {
+                    format!("Content-Length: {}\r\n", body.len())
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 {}\r\n{}{}Connection: close\r\n\r\n",
+                    status, headers, length
+                )
+                .unwrap();
+                stream.write_all(&body).unwrap();
+                requests.push(request);
+            }
+            requests
+        });
+        (url, server)
+    }
+
+    #[test]
+    fn fetch() {
+        let feed = fs::read("./resources/zips/gtfs.zip").unwrap();
+        let (url, server) = serve(vec![
+            ("503 Service Unavailable", "", vec![]),
+            ("200 OK", "ETag: \"v1\"\r\n", feed.clone()),
+            ("304 Not Modified", "ETag: \"v1\"\r\n", vec![]),
+            // A server ignoring conditional requests
+            ("200 OK", "", feed.clone()),
+            ("404 Not Found", "", vec![]),
+        ]);
+        let cache_dir = std::env::temp_dir().join(format!("gtfs-fetch-{}", std::process::id()));
+        let fetcher = FeedFetcher::new(&cache_dir)
+            .header("X-Api-Key", "secret")
+            .retry_delay(Duration::from_millis(10));
+
+        let fetched = fetcher.fetch(&url).unwrap();
+        assert!(fetched.changed);
+        assert_eq!(fetched.path, fetcher.cache_path(&url));
+        assert_eq!(fetched.etag.as_deref(), Some("\"v1\""));
+        assert_eq!(fs::read(&fetched.path).unwrap(), feed);
+        assert!(fetched.reader().unwrap().stops().is_ok());
+
+        let not_modified = fetcher.fetch(&url).unwrap();
+        assert!(!not_modified.changed);
+        assert_eq!(not_modified.sha256, fetched.sha256);
+        let unchanged = fetcher.fetch(&url).unwrap();
+        assert!(!unchanged.changed);
+        assert_eq!(unchanged.etag, None);
+
+        assert!(matches!(fetcher.fetch(&url), Err(Error::Fetch(_))));
+        assert_eq!(fs::read(&fetched.path).unwrap(), feed);
+
+        let requests = server.join().unwrap();
+        assert!(requests
+            .iter()
+            .all(|request| request.contains(&"x-api-key: secret".to_owned())));
+        assert!(!requests[1].iter().any(|h| h.starts_with("if-none-match")));
+        assert!(requests[2].contains(&"if-none-match: \"v1\"".to_owned()));
+        assert!(!requests[4].iter().any(|h| h.starts_with("if-none-match")));
+        fs::remove_dir_all(cache_dir).unwrap();
+    }
+
+    #[test]
+    fn fetch_failures() {
+        let feed = fs::read("./resources/zips/gtfs.zip").unwrap();
+        let interrupted = || {
+            (
+                "200 OK",
+                "Content-Length: 1000000\r\n",
+                feed[..100].to_vec(),
+            )
+        };
+        let (url, server) = serve(vec![
+            // Not modified, although nothing is cached
+            ("304 Not Modified", "", vec![]),
+            interrupted(),
+            interrupted(),
+            interrupted(),
+            ("200 OK", "", feed.clone()),
+            interrupted(),
+            interrupted(),
+        ]);
+        let cache_dir =
+            std::env::temp_dir().join(format!("gtfs-fetch-failures-{}", std::process::id()));
+        let fetcher = FeedFetcher::new(&cache_dir)
+            .retries(1)
+            .retry_delay(Duration::from_millis(10));
+        let path = fetcher.cache_path(&url);
+        let download_path = path.with_extension("download");
+
+        assert!(matches!(fetcher.fetch(&url), Err(Error::NotCached(_))));
+        assert!(!path.exists());
+
+        assert!(fetcher.fetch(&url).is_err());
+        assert!(!path.exists());
+        assert!(!download_path.exists());
+
+        // Downloaded again after an interruption
+        let fetched = fetcher.fetch(&url).unwrap();
+        assert_eq!(fs::read(&fetched.path).unwrap(), feed);
+        assert!(!download_path.exists());
+
+        // The cached feed is kept
+        assert!(fetcher.fetch(&url).is_err());
+        assert_eq!(fs::read(&path).unwrap(), feed);
+        assert!(!download_path.exists());
+
+        assert_eq!(server.join().unwrap().len(), 7);
+        fs::remove_dir_all(cache_dir).unwrap();
+    }
+}
@@ -0,0 +1,228 @@
+//! Generation of walking transfers between nearby stops, for feeds whose transfers.txt is
+//! empty or partial.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    spatial::{StopFilter, StopIndex},
+    structures::{
+        pathways::Pathway,
+        stops::{Stop, StopLocationType},
+        transfers::{Transfer, TransferType},
+    },
+};
+
+/// Generates transfers between the stops within walking distance of each other, with a
+/// `min_transfer_time` estimated from their distance.
+///
+/// No transfer is generated between stops which already have one, such as those where it
+/// is not possible, nor between the stops of a station with pathways, which describe how to
+/// walk inside the station.
+///
+/// ```
+/// use gtfs_parser::{footpaths::TransferGenerator, writer::write_records, GtfsReader};
+///
+/// let mut gtfs = GtfsReader::from_path("./resources/zips/gtfs.zip").unwrap();
+/// let stops = gtfs.stops().unwrap();
+/// let mut transfers = gtfs.transfers().unwrap_or_default();
+///
+/// let generated = TransferGenerator::new(&stops, &[])
+///     .max_distance(300.0)
+///     .generate(&transfers);
+/// transfers.extend(generated);
+///
+/// let mut output = Vec::new();
+/// write_records(&mut output, &transfers).unwrap();
+/// ```
+pub struct TransferGenerator<'s> {
+    stops: &'s [Stop],
+    /// Stations whose stops are linked by pathways
+    with_pathways: HashSet<&'s str>,
+    max_distance: f64,
+    walking_speed: f64,
+}
+
+impl<'s> TransferGenerator<'s> {
+    pub fn new(stops: &'s [Stop], pathways: &'s [Pathway]) -> Self {
+        let parents: HashMap<&str, &str> = stops
+            .iter()
+            .filter_map(|stop| Some((stop.id.as_str(), stop.parent_station.as_deref()?)))
+            .collect();
+        let with_pathways = pathways
+            .iter()
+            .flat_map(|pathway| [pathway.from_stop_id.as_str(), pathway.to_stop_id.as_str()])
+            .filter_map(|stop| parents.get(stop).copied())
+            .collect();
+
+        Self {
+            stops,
+            with_pathways,
+            max_distance: 400.0,
+            walking_speed: 1.3,
+        }
+    }
+
+    /// Longest walk between two stops, in meters. Defaults to 400m.
+    pub fn max_distance(mut self, max_distance: f64) -> Self {
+        self.max_distance = max_distance;
+        self
+    }
+
+    /// Walking speed, in meters per second. Defaults to 1.3m/s.
+    pub fn walking_speed(mut self, walking_speed: f64) -> Self {
+        self.walking_speed = walking_speed;
+        self
+    }
+
+    /// Transfers between the stops within walking distance, in both directions, except those
+    /// between stops already linked by one of the given transfers which applies to any route
+    /// and trip.
+    pub fn generate(&self, existing: &[Transfer]) -> Vec<Transfer> {
+        // Transfers from or to a station apply to its stops
+        let mut children: HashMap<&str, Vec<&str>> = HashMap::new();
+        for stop in self.stops {
+            if let Some(parent) = &stop.parent_station {
+                children.entry(parent).or_default().push(&stop.id);
+            }
+        }
+        let expand = |stop_id| {
+            let stops = children.get(stop_id).into_iter().flatten().copied();
+            std::iter::once(stop_id).chain(stops)
+        };
+        let mut linked: HashSet<(&str, &str)> = HashSet::new();
+        // Transfers restricted to some routes or trips don't link the stops for the others
+        let stop_to_stop = existing.iter().filter(|transfer| {
+            transfer.from_route_id.is_none()
+                && transfer.to_route_id.is_none()
+                && transfer.from_trip_id.is_none()
+                && transfer.to_trip_id.is_none()
+        });
+        for transfer in stop_to_stop {
+            for from in expand(transfer.from_stop_id.as_str()) {
+                for to in expand(transfer.to_stop_id.as_str()) {
+                    linked.insert((from, to));
+                }
+            }
+        }
+
+        let index = StopIndex::from_stops(self.stops);
+        let filter = StopFilter {
+            location_types: vec![StopLocationType::StopPoint],
+            ..Default::default()
+        };
+
+        let mut transfers = Vec::new();
+        for from in self.stops {
+            let point = match (&from.location_type, from.point()) {
+                (StopLocationType::StopPoint, Some(point)) => point,
+                _ => continue,
+            };
+            for (to, distance) in index.within(&point, self.max_distance, &filter) {
+                if from.id == to.id
+                    || linked.contains(&(from.id.as_str(), to.id.as_str()))
+                    || self.inside_station(from, to)
+                {
+                    continue;
+                }
+                transfers.push(Transfer {
+                    from_stop_id: from.id.clone(),
+                    to_stop_id: to.id.clone(),
+                    transfer_type: TransferType::TimedMinimum,
+                    min_transfer_time: Some(self.walking_time(distance)),
+                    ..Default::default()
+                });
+            }
+        }
+        transfers
+    }
+
+    /// Seconds needed to walk a distance in meters.
+    pub fn walking_time(&self, distance: f64) -> u64 {
+        (distance / self.walking_speed).ceil() as u64
+    }
+
+    /// Whether two stops belong to the same station with pathways.
+    fn inside_station(&self, from: &Stop, to: &Stop) -> bool {
+        match (&from.parent_station, &to.parent_station) {
+            (Some(from), Some(to)) => from == to && self.with_pathways.contains(from.as_str()),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{geometry::Point, test::reader_from_files};
+
+    #[test]
+    fn generate() {
+        let mut gtfs = reader_from_files(&[
+            (
+                "stops.txt",
+                "stop_id,stop_name,stop_lat,stop_lon,location_type,parent_station\n\
+                 A,A,0,0,0,\n\
+                 B,B,0,0.002,0,\n\
+                 C,C,0,0.01,0,\n\
+                 S,Station,0,0.0021,1,\n\
+                 S1,Platform 1,0,0.0022,0,S\n\
+                 S2,Platform 2,0,0.0023,0,S\n",
+            ),
+            (
+                "pathways.txt",
+                "pathway_id,from_stop_id,to_stop_id,pathway_mode,is_bidirectional\n\
+                 P,S1,S2,1,1\n",
+            ),
+        ]);
+        let stops = gtfs.stops().unwrap();
+        let pathways = gtfs.pathways().unwrap();
+        let not_possible = Transfer {
+            from_stop_id: "A".to_owned(),
+            to_stop_id: "S".to_owned(),
+            transfer_type: TransferType::NotPossible,
+            ..Default::default()
+        };
+
+        // Only for the trip T1 arriving at B, so B and A are still linked by a footpath
+        let trip_specific = Transfer {
+            from_stop_id: "B".to_owned(),
+            to_stop_id: "A".to_owned(),
+            from_trip_id: Some("T1".to_owned()),
+            transfer_type: TransferType::NotPossible,
+            ..Default::default()
+        };
+
+        let generator = TransferGenerator::new(&stops, &pathways);
+        let mut transfers: Vec<(String, String, u64)> = generator
+            .generate(&[not_possible, trip_specific])
+            .into_iter()
+            .map(|t| (t.from_stop_id, t.to_stop_id, t.min_transfer_time.unwrap()))
+            .collect();
+        transfers.sort();
+
+        // Not to the platforms of S from A, nor between them
+        let walk = |from: f64, to: f64| {
+            let distance = Point::new(0.0, from).distance(&Point::new(0.0, to));
+            generator.walking_time(distance)
+        };
+        assert_eq!(walk(0.0, 0.002), 172);
+        assert_eq!(
+            transfers,
+            [
+                ("A".to_owned(), "B".to_owned(), walk(0.0, 0.002)),
+                ("B".to_owned(), "A".to_owned(), walk(0.0, 0.002)),
+                ("B".to_owned(), "S1".to_owned(), walk(0.002, 0.0022)),
+                ("B".to_owned(), "S2".to_owned(), walk(0.002, 0.0023)),
+                ("S1".to_owned(), "A".to_owned(), walk(0.0, 0.0022)),
+                ("S1".to_owned(), "B".to_owned(), walk(0.002, 0.0022)),
+                ("S2".to_owned(), "A".to_owned(), walk(0.0, 0.0023)),
+                ("S2".to_owned(), "B".to_owned(), walk(0.002, 0.0023)),
+            ]
+        );
+
+        assert!(TransferGenerator::new(&stops, &pathways)
+            .max_distance(10.0)
+            .generate(&[])
+            .is_empty());
+    }
+}
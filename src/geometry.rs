@@ -0,0 +1,325 @@
+//! Geographic helpers: distances between coordinates and positions along shapes.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::structures::{shapes::Shape, trips::Trip};
+
+/// Mean radius of the Earth, in meters.
+pub const EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// A WGS-84 coordinate.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+pub struct Point {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+impl Point {
+    pub fn new(latitude: f64, longitude: f64) -> Self {
+        Self {
+            latitude,
+            longitude,
+        }
+    }
+
+    /// Great-circle distance to another point, in meters, using the haversine formula.
+    pub fn distance(&self, other: &Point) -> f64 {
+        let (lat1, lat2) = (self.latitude.to_radians(), other.latitude.to_radians());
+        let d_lat = lat2 - lat1;
+        let d_lon = (other.longitude - self.longitude).to_radians();
+
+        let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS * a.sqrt().asin()
+    }
+
+    /// Initial bearing towards another point, in degrees clockwise from North.
+    pub fn bearing(&self, other: &Point) -> f64 {
+        let (lat1, lat2) = (self.latitude.to_radians(), other.latitude.to_radians());
+        let d_lon = (other.longitude - self.longitude).to_radians();
+
+        let y = d_lon.sin() * lat2.cos();
+        let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * d_lon.cos();
+        (y.atan2(x).to_degrees() + 360.0) % 360.0
+    }
+
+    /// Point at a distance in meters along the great circle starting at a bearing in degrees.
+    pub fn destination(&self, bearing: f64, distance: f64) -> Point {
+        let (lat1, lon1) = (self.latitude.to_radians(), self.longitude.to_radians());
+        let (bearing, angle) = (bearing.to_radians(), distance / EARTH_RADIUS);
+
+        let lat2 = (lat1.sin() * angle.cos() + lat1.cos() * angle.sin() * bearing.cos()).asin();
+        let lon2 = lon1
+            + (bearing.sin() * angle.sin() * lat1.cos())
+                .atan2(angle.cos() - lat1.sin() * lat2.sin());
+        Point::new(lat2.to_degrees(), lon2.to_degrees())
+    }
+
+    /// Point at the given fraction of the way towards another point.
+    ///
+    /// Coordinates are interpolated linearly, which is accurate enough over the short
+    /// distances between shape points.
+    pub fn lerp(&self, other: &Point, fraction: f64) -> Point {
+        Point::new(
+            self.latitude + (other.latitude - self.latitude) * fraction,
+            self.longitude + (other.longitude - self.longitude) * fraction,
+        )
+    }
+
+    /// Local planar coordinates in meters, relative to `origin`.
+    fn planar(&self, origin: &Point) -> (f64, f64) {
+        let x = (self.longitude - origin.longitude).to_radians()
+            * origin.latitude.to_radians().cos()
+            * EARTH_RADIUS;
+        let y = (self.latitude - origin.latitude).to_radians() * EARTH_RADIUS;
+        (x, y)
+    }
+}
+
+/// Where a point lies relative to a polyline.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Projection {
+    /// Distance along the polyline, in meters.
+    pub distance: f64,
+    /// Distance between the point and the polyline, in meters.
+    pub offset: f64,
+    /// Closest point of the polyline.
+    pub point: Point,
+}
+
+/// A line made of points, such as a shape or the stops of a trip, with the distance
+/// travelled up to each point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polyline {
+    points: Vec<Point>,
+    distances: Vec<f64>,
+}
+
+impl Polyline {
+    pub fn new(points: Vec<Point>) -> Self {
+        let mut distances = Vec::with_capacity(points.len());
+        let mut travelled = 0.0;
+        for (index, point) in points.iter().enumerate() {
+            if index > 0 {
+                travelled += points[index - 1].distance(point);
+            }
+            distances.push(travelled);
+        }
+
+        Self { points, distances }
+    }
+
+    /// Builds the polylines of the shapes, by shape id.
+    pub fn from_shapes(shapes: &[Shape]) -> HashMap<String, Polyline> {
+        let mut by_id: HashMap<&str, Vec<&Shape>> = HashMap::new();
+        for shape in shapes {
+            by_id.entry(shape.id.as_str()).or_default().push(shape);
+        }
+
+        by_id
+            .into_iter()
+            .map(|(id, mut points)| {
+                points.sort_by_key(|point| point.sequence);
+                let points = points
+                    .into_iter()
+                    .map(|point| Point::new(point.latitude, point.longitude))
+                    .collect();
+                (id.to_owned(), Polyline::new(points))
+            })
+            .collect()
+    }
+
+    pub fn points(&self) -> &[Point] {
+        &self.points
+    }
+
+    /// Length of the polyline, in meters.
+    pub fn length(&self) -> f64 {
+        self.distances.last().copied().unwrap_or_default()
+    }
+
+    /// Projects a point on the closest part of the polyline.
+    pub fn project(&self, point: &Point) -> Option<Projection> {
+        self.project_after(point, 0.0)
+    }
+
+    /// Projects a point on the closest part of the polyline past `min_distance`.
+    ///
+    /// This keeps the projections of consecutive points, such as the stops of a trip,
+    /// going forward on polylines which loop or go back and forth.
+    pub fn project_after(&self, point: &Point, min_distance: f64) -> Option<Projection> {
+        if self.points.len() == 1 {
+            return Some(Projection {
+                distance: 0.0,
+                offset: point.distance(&self.points[0]),
+                point: self.points[0],
+            });
+        }
+
+        let mut best: Option<Projection> = None;
+        for index in 1..self.points.len() {
+            if self.distances[index] < min_distance {
+                continue;
+            }
+            let (start, end) = (&self.points[index - 1], &self.points[index]);
+            let (x, y) = point.planar(start);
+            let (dx, dy) = end.planar(start);
+            let squared_length = dx * dx + dy * dy;
+            let mut fraction = if squared_length > 0.0 {
+                ((x * dx + y * dy) / squared_length).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+
+            let length = self.distances[index] - self.distances[index - 1];
+            let mut distance = self.distances[index - 1] + fraction * length;
+            if distance < min_distance {
+                distance = min_distance;
+                fraction = (min_distance - self.distances[index - 1]) / length;
+            }
+
+            let projected = start.lerp(end, fraction);
+            let offset = point.distance(&projected);
+            let closer = match best {
+                Some(best) => offset < best.offset,
+                None => true,
+            };
+            if closer {
+                best = Some(Projection {
+                    distance,
+                    offset,
+                    point: projected,
+                });
+            }
+        }
+
+        best
+    }
+
+    /// Point at the given distance along the polyline, clamped to its ends.
+    pub fn point_at(&self, distance: f64) -> Option<Point> {
+        let (index, fraction) = self.locate(distance)?;
+        Some(match self.points.get(index + 1) {
+            Some(next) => self.points[index].lerp(next, fraction),
+            None => self.points[index],
+        })
+    }
+
+    /// Bearing of the polyline at the given distance, in degrees clockwise from North.
+    pub fn bearing_at(&self, distance: f64) -> Option<f64> {
+        let (index, _) = self.locate(distance)?;
+        let index = index.min(self.points.len().checked_sub(2)?);
+        Some(self.points[index].bearing(&self.points[index + 1]))
+    }
+
+    /// Segment (by index of its first point) at the given distance, and the fraction of
+    /// the segment travelled.
+    fn locate(&self, distance: f64) -> Option<(usize, f64)> {
+        let last = self.points.len().checked_sub(1)?;
+        let index = self
+            .distances
+            .iter()
+            .rposition(|travelled| *travelled <= distance)
+            .unwrap_or(0)
+            .min(last);
+        if index == last {
+            return Some((index, 0.0));
+        }
+
+        let length = self.distances[index + 1] - self.distances[index];
+        let fraction = if length > 0.0 {
+            ((distance - self.distances[index]) / length).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        Some((index, fraction))
+    }
+}
+
+/// The path of a trip, with the distance travelled up to each of its stops.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TripPath {
+    pub line: Polyline,
+    /// Distance along `line` of each stop time of the trip, in meters.
+    pub stop_distances: Vec<f64>,
+}
+
+impl TripPath {
+    /// Path of a trip along its shape, or along straight lines between its stops when it
+    /// has no shape. Stops without coordinates are put at the distance of the previous stop.
+    pub fn new(trip: &Trip, shapes: &HashMap<String, Polyline>) -> Option<TripPath> {
+        let line = match trip.shape_id.as_ref().and_then(|id| shapes.get(id)) {
+            Some(shape) if shape.points.len() > 1 => shape.clone(),
+            _ => Polyline::new(
+                trip.stop_times
+                    .iter()
+                    .filter_map(|stop_time| stop_time.stop.point())
+                    .collect(),
+            ),
+        };
+        if line.points.is_empty() {
+            return None;
+        }
+
+        let mut travelled = 0.0;
+        let stop_distances = trip
+            .stop_times
+            .iter()
+            .map(|stop_time| {
+                if let Some(point) = stop_time.stop.point() {
+                    if let Some(projection) = line.project_after(&point, travelled) {
+                        travelled = projection.distance;
+                    }
+                }
+                travelled
+            })
+            .collect();
+
+        Some(TripPath {
+            line,
+            stop_distances,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn distances() {
+        let paris = Point::new(48.8566, 2.3522);
+        let london = Point::new(51.5074, -0.1278);
+
+        assert!((paris.distance(&london) - 343_560.0).abs() < 500.0);
+        assert!((paris.bearing(&london) - 330.0).abs() < 1.0);
+
+        let destination = paris.destination(paris.bearing(&london), paris.distance(&london));
+        assert!(destination.distance(&london) < 1.0);
+    }
+
+    #[test]
+    fn projection() {
+        // About 1.1km east, then 1.1km north
+        let line = Polyline::new(vec![
+            Point::new(0.0, 0.0),
+            Point::new(0.0, 0.01),
+            Point::new(0.01, 0.01),
+        ]);
+        let leg = Point::new(0.0, 0.0).distance(&Point::new(0.0, 0.01));
+        assert!((line.length() - 2.0 * leg).abs() < 1.0);
+
+        let projection = line.project(&Point::new(0.001, 0.005)).unwrap();
+        assert!((projection.distance - leg / 2.0).abs() < 1.0);
+        assert!((projection.offset - 111.2).abs() < 1.0);
+
+        let projection = line.project_after(&Point::new(0.0, 0.0), leg).unwrap();
+        assert!((projection.distance - leg).abs() < 1.0);
+
+        let middle = line.point_at(leg * 1.5).unwrap();
+        assert!((middle.latitude - 0.005).abs() < 1e-6);
+        assert!((line.bearing_at(leg * 1.5).unwrap() - 0.0).abs() < 1e-6);
+        assert_eq!(line.point_at(10.0 * leg), Some(Point::new(0.01, 0.01)));
+    }
+}
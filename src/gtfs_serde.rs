@@ -3,8 +3,8 @@ use chrono::Datelike;
 use chrono::NaiveDate;
 use itertools::Itertools;
 use rgb::RGB8;
-use serde::de;
-use serde::{Deserialize, Deserializer, Serializer};
+use serde::de::{self, Visitor};
+use serde::{forward_to_deserialize_any, Deserialize, Deserializer, Serializer};
 
 pub(crate) fn deserialize_bool<'de, D>(deserializer: D) -> Result<bool, D::Error>
 where
@@ -21,6 +21,22 @@ where
     }
 }
 
+/// Deserializes a numeric code, an empty value standing for the default of the field.
+pub(crate) fn deserialize_code<'de, D>(deserializer: D) -> Result<Option<u16>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    let s = s.trim();
+    if s.is_empty() {
+        Ok(None)
+    } else {
+        s.parse()
+            .map(Some)
+            .map_err(|_| de::Error::custom(Error::InvalidCode(s.to_owned())))
+    }
+}
+
 pub(crate) fn serialize_bool<S>(value: &bool, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
@@ -37,14 +53,42 @@ where
     D: Deserializer<'de>,
 {
     let s = String::deserialize(deserializer)?;
-    NaiveDate::parse_from_str(&s, "%Y%m%d").map_err(serde::de::Error::custom)
+    parse_gtfs_date(&s).map_err(de::Error::custom)
 }
 
 pub(crate) fn serialize_date<S>(date: &NaiveDate, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
-    serializer.serialize_str(format!("{}{}{}", date.year(), date.month(), date.day()).as_str())
+    serializer
+        .serialize_str(format!("{:04}{:02}{:02}", date.year(), date.month(), date.day()).as_str())
+}
+
+pub(crate) fn deserialize_option_date<'de, D>(
+    deserializer: D,
+) -> Result<Option<NaiveDate>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = Option::<String>::deserialize(deserializer)?;
+
+    match s {
+        None => Ok(None),
+        Some(d) => Ok(Some(parse_gtfs_date(&d).map_err(de::Error::custom)?)),
+    }
+}
+
+pub(crate) fn serialize_option_date<S>(
+    date: &Option<NaiveDate>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match date {
+        None => serializer.serialize_none(),
+        Some(d) => serialize_date(d, serializer),
+    }
 }
 
 pub(crate) fn deserialize_option_time<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
@@ -55,14 +99,7 @@ where
 
     match s {
         None => Ok(None),
-        Some(t) => {
-            let parts = t.trim_start().split(':').collect_vec();
-            if parts.len() != 3 {
-                Err(de::Error::custom(Error::InvalidTime(t.to_owned())))
-            } else {
-                Ok(Some(parse_time(parts).map_err(de::Error::custom)?))
-            }
-        }
+        Some(t) => Ok(Some(parse_gtfs_time(&t).map_err(de::Error::custom)?)),
     }
 }
 
@@ -71,13 +108,22 @@ where
     D: Deserializer<'de>,
 {
     let s = String::deserialize(deserializer)?;
+    parse_gtfs_time(&s).map_err(de::Error::custom)
+}
 
+/// Parses a GTFS time (HH:MM:SS, hours possibly past 24) into a number of seconds
+/// since the start of the service day.
+pub(crate) fn parse_gtfs_time(s: &str) -> Result<u64, Error> {
     let parts = s.trim_start().split(':').collect_vec();
     if parts.len() != 3 {
-        Err(de::Error::custom(Error::InvalidTime(s.to_owned())))
-    } else {
-        Ok(parse_time(parts).map_err(de::Error::custom)?)
+        return Err(Error::InvalidTime(s.to_owned()));
     }
+    parse_time(parts).map_err(|_| Error::InvalidTime(s.to_owned()))
+}
+
+/// Parses a GTFS date (YYYYMMDD).
+pub(crate) fn parse_gtfs_date(s: &str) -> Result<NaiveDate, Error> {
+    NaiveDate::parse_from_str(s, "%Y%m%d").map_err(|_| Error::InvalidDate(s.to_owned()))
 }
 
 fn parse_time(time_parts: Vec<&str>) -> Result<u64, std::num::ParseIntError> {
@@ -94,7 +140,7 @@ where
 {
     match time {
         None => serializer.serialize_none(),
-        Some(t) => serializer.serialize_str(format!("{}", t).as_str()),
+        Some(t) => serializer.serialize_str(&format_time(*t)),
     }
 }
 
@@ -102,7 +148,18 @@ pub(crate) fn serialize_time<S>(time: &u64, serializer: S) -> Result<S::Ok, S::E
 where
     S: Serializer,
 {
-    serializer.serialize_str(format!("{}", time).as_str())
+    serializer.serialize_str(&format_time(*time))
+}
+
+/// Formats a number of seconds since the start of the service day as a GTFS time (HH:MM:SS).
+/// Hours go past 24 for trips running after midnight.
+pub(crate) fn format_time(time: u64) -> String {
+    format!(
+        "{:02}:{:02}:{:02}",
+        time / 3600,
+        (time / 60) % 60,
+        time % 60
+    )
 }
 
 pub(crate) fn deserialize_option_color<'de, D>(de: D) -> Result<Option<RGB8>, D::Error>
@@ -143,3 +200,39 @@ fn parse_color(s: &str) -> Result<RGB8, Error> {
     let b = u8::from_str_radix(&s[4..6], 16).map_err(|_| Error::InvalidColor(s.to_owned()))?;
     Ok(RGB8::new(r, g, b))
 }
+
+/// Names of the columns a record is deserialized from.
+///
+/// The names are captured by handing the record a deserializer which only records
+/// the fields it is asked for, so that they stay in sync with the serde attributes.
+pub(crate) fn field_names<T: de::DeserializeOwned>() -> &'static [&'static str] {
+    struct FieldNames<'a>(&'a mut &'static [&'static str]);
+
+    impl<'de, 'a> Deserializer<'de> for FieldNames<'a> {
+        type Error = de::value::Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+            Err(de::Error::custom("not a struct"))
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            fields: &'static [&'static str],
+            _visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            *self.0 = fields;
+            Err(de::Error::custom("fields captured"))
+        }
+
+        forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf option unit unit_struct newtype_struct seq tuple
+            tuple_struct map enum identifier ignored_any
+        }
+    }
+
+    let mut fields: &'static [&'static str] = &[];
+    let _ = T::deserialize(FieldNames(&mut fields));
+    fields
+}
@@ -0,0 +1,210 @@
+//! SHA-256 fingerprints of the files of a feed: of their content, and of their records
+//! regardless of how they are laid out.
+
+use std::io::{self, Read};
+
+use sha2::{Digest, Sha256};
+
+use crate::BYTE_ORDER_MARK;
+
+/// A SHA-256 digest.
+pub(crate) type Hash = [u8; 32];
+
+pub(crate) fn to_hex(hash: &Hash) -> String {
+    hash.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub(crate) fn content_hash(content: &[u8]) -> Hash {
+    Sha256::digest(content).into()
+}
+
+/// Hash of named hashes, which does not depend on their order.
+pub(crate) fn combine<'n>(hashes: impl IntoIterator<Item = (&'n str, Hash)>) -> Hash {
+    let mut hashes: Vec<(&str, Hash)> = hashes.into_iter().collect();
+    hashes.sort();
+    let mut hasher = Sha256::new();
+    for (name, hash) in hashes {
+        update_field(&mut hasher, name.as_bytes());
+        hasher.update(hash);
+    }
+    hasher.finalize().into()
+}
+
+/// Adds a value prefixed by its length, so that consecutive values cannot be confused.
+fn update_field(hasher: &mut Sha256, value: &[u8]) {
+    hasher.update((value.len() as u64).to_le_bytes());
+    hasher.update(value);
+}
+
+/// A reader hashing everything read through it.
+pub(crate) struct HashingReader<R> {
+    inner: R,
+    hasher: Sha256,
+}
+
+impl<R: Read> HashingReader<R> {
+    pub(crate) fn new(inner: R) -> Self {
+        Self {
+            inner,
+            hasher: Sha256::new(),
+        }
+    }
+
+    pub(crate) fn finalize(self) -> Hash {
+        self.hasher.finalize().into()
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.hasher.update(&buf[..read]);
+        Ok(read)
+    }
+}
+
+/// Hash of the records of a csv file which ignores their order, the order of the columns,
+/// quoting, line endings, byte order marks, whitespace around values and empty values,
+/// which are the same as missing columns.
+pub(crate) struct RecordsHasher {
+    headers: Vec<Vec<u8>>,
+    records: Vec<Hash>,
+}
+
+impl RecordsHasher {
+    pub(crate) fn new(headers: &csv::ByteRecord) -> Self {
+        let mut headers: Vec<Vec<u8>> = headers.iter().map(|h| trim(h).to_vec()).collect();
+        if let Some(first) = headers.first_mut() {
+            if first.starts_with(&BYTE_ORDER_MARK) {
+                first.drain(..BYTE_ORDER_MARK.len());
+            }
+        }
+        Self {
+            headers,
+            records: Vec::new(),
+        }
+    }
+
+    pub(crate) fn add(&mut self, record: &csv::ByteRecord) {
+        let mut fields: Vec<(&[u8], &[u8])> = self
+            .headers
+            .iter()
+            .map(Vec::as_slice)
+            .zip(record.iter().map(trim))
+            .filter(|(_, value)| !value.is_empty())
+            .collect();
+        fields.sort();
+
+        let mut hasher = Sha256::new();
+        for (header, value) in fields {
+            update_field(&mut hasher, header);
+            update_field(&mut hasher, value);
+        }
+        self.records.push(hasher.finalize().into());
+    }
+
+    pub(crate) fn finalize(mut self) -> Hash {
+        self.records.sort_unstable();
+        let mut hasher = Sha256::new();
+        for record in &self.records {
+            hasher.update(record);
+        }
+        hasher.finalize().into()
+    }
+}
+
+/// Hash of the records of a csv file, as computed by [RecordsHasher].
+pub(crate) fn records_hash(content: &[u8]) -> Result<Hash, csv::Error> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(content);
+    let mut hasher = RecordsHasher::new(reader.byte_headers()?);
+    let mut record = csv::ByteRecord::new();
+    while reader.read_byte_record(&mut record)? {
+        hasher.add(&record);
+    }
+    Ok(hasher.finalize())
+}
+
+fn trim(value: &[u8]) -> &[u8] {
+    let start = value
+        .iter()
+        .position(|byte| !byte.is_ascii_whitespace())
+        .unwrap_or(value.len());
+    let end = value
+        .iter()
+        .rposition(|byte| !byte.is_ascii_whitespace())
+        .map_or(start, |end| end + 1);
+    &value[start..end]
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::reader_from_files;
+
+    const STOPS: &str = "stop_id,stop_name,stop_lat,stop_lon\nA,Alpha,0,0\nB,Beta,0,1\n";
+
+    #[test]
+    fn semantic_hash() {
+        let mut gtfs = reader_from_files(&[
+            ("stops.txt", STOPS),
+            ("routes.txt", "route_id,route_type\nR1,3\n"),
+        ]);
+        // Reordered records and columns, quoted, padded and empty values, CRLF line endings
+        // and a byte order mark, in a subdirectory
+        let mut reformatted = reader_from_files(&[
+            (
+                "feed/routes.txt",
+                "route_type,route_id,route_desc\r\n3,R1,\r\n",
+            ),
+            (
+                "feed/stops.txt",
+                "\u{feff}stop_name,stop_id,stop_lon,stop_lat,zone_id\r\n\
+                  Beta ,B,1,0,\r\n\"Alpha\",A,0,0,\r\n",
+            ),
+        ]);
+        let mut changed = reader_from_files(&[
+            (
+                "stops.txt",
+                "stop_id,stop_name,stop_lat,stop_lon\nA,Alpha,0,0\nB,Beta,0,2\n",
+            ),
+            ("routes.txt", "route_id,route_type\nR1,3\n"),
+        ]);
+
+        // Parsed files are hashed as they are read
+        gtfs.stops().unwrap();
+        reformatted.stops().unwrap();
+        assert_eq!(
+            gtfs.records_hash("stops.txt").unwrap(),
+            reformatted.records_hash("stops.txt").unwrap()
+        );
+        assert_ne!(
+            gtfs.file_hash("stops.txt").unwrap(),
+            reformatted.file_hash("stops.txt").unwrap()
+        );
+        assert_eq!(
+            gtfs.semantic_hash().unwrap(),
+            reformatted.semantic_hash().unwrap()
+        );
+        assert_ne!(
+            gtfs.semantic_hash().unwrap(),
+            changed.semantic_hash().unwrap()
+        );
+        assert_ne!(gtfs.feed_hash().unwrap(), reformatted.feed_hash().unwrap());
+
+        // The same as when the files are not parsed
+        let mut unparsed = reader_from_files(&[
+            ("stops.txt", STOPS),
+            ("routes.txt", "route_id,route_type\nR1,3\n"),
+        ]);
+        assert_eq!(
+            gtfs.file_hash("stops.txt").unwrap(),
+            "b7787aa9aaace5e1546c3af693f4673c71a9cd420089d90b841686f0b5a200a8"
+        );
+        assert_eq!(
+            gtfs.records_hash("stops.txt").unwrap(),
+            unparsed.records_hash("stops.txt").unwrap()
+        );
+        assert_eq!(gtfs.feed_hash().unwrap(), unparsed.feed_hash().unwrap());
+    }
+}
@@ -1,10 +1,14 @@
+use chrono::NaiveDate;
 use error::Error;
+use hashing::{Hash, HashingReader, RecordsHasher};
 use serde::{de::DeserializeOwned, Deserialize};
+use summary::FeedSummary;
+use validation::ValidationReport;
 
 use crate::structures::{
     agency::Agency,
     attributions::Attribution,
-    calendar::Calendar,
+    calendar::{self, Calendar},
     calendar_dates::CalendarDate,
     fare_attributes::FareAttribute,
     fare_rules::FareRule,
@@ -14,22 +18,40 @@ use crate::structures::{
     pathways::Pathway,
     routes::Route,
     shapes::Shape,
-    stop_times::{RawStopTime, StopTime},
+    stop_times::RawStopTime,
     stops::Stop,
     transfers::Transfer,
     trips::{RawTrip, Trip},
+    Record,
 };
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fs::File,
     io::{BufReader, Cursor, Read, Seek},
     path::Path,
     sync::Arc,
 };
 
+pub mod departures;
 pub mod error;
+pub mod fares;
+#[cfg(feature = "read-url")]
+pub mod fetch;
+pub mod footpaths;
+pub mod geometry;
 pub mod gtfs_serde;
+mod hashing;
+#[cfg(feature = "realtime")]
+pub mod realtime;
+pub mod routing;
+pub mod simulation;
+pub mod spatial;
+pub mod stations;
+pub mod statistics;
 pub mod structures;
+pub mod summary;
+pub mod validation;
+pub mod writer;
 
 /// https://en.wikipedia.org/wiki/Byte_order_mark
 const BYTE_ORDER_MARK: [u8; 3] = [0xEF, 0xBB, 0xBF];
@@ -73,10 +95,42 @@ impl ReadSeek for FromPath {}
 
 trait ReadSeek: Read + Seek {}
 
+/// A record deserialized from a GTFS file.
+struct Row<'r, D> {
+    /// 1-based line of the record, the header being the first line
+    line: u64,
+    record: D,
+    headers: &'r csv::StringRecord,
+    values: &'r csv::StringRecord,
+}
+
+impl<D: DeserializeOwned> Row<'_, D> {
+    /// Indexes of the columns which are not deserialized into the record.
+    fn unknown_columns(&self) -> Vec<usize> {
+        let fields = gtfs_serde::field_names::<D>();
+        self.headers
+            .iter()
+            .enumerate()
+            .filter(|(_, header)| !fields.contains(header))
+            .map(|(index, _)| index)
+            .collect()
+    }
+}
+
 pub struct GtfsReader {
     archive: zip::ZipArchive<Box<dyn ReadSeek>>,
     /// File mapping (filename, archive_index)
     file_mappings: HashMap<String, usize>,
+    /// Names of the files of the archive, in archive order
+    file_names: Vec<String>,
+    /// Whether coded values not defined by the GTFS reference are rejected
+    strict: bool,
+    /// Whether columns not defined by the GTFS reference are kept in the records' extras
+    keep_extras: bool,
+    /// Hashes of the content of the files already read, by archive index
+    content_hashes: HashMap<usize, Hash>,
+    /// Hashes of the records of the csv files already read, by archive index
+    records_hashes: HashMap<usize, Hash>,
 }
 
 impl GtfsReader {
@@ -99,34 +153,117 @@ impl GtfsReader {
     fn from_reader(reader: Box<dyn ReadSeek>) -> Result<GtfsReader, Error> {
         let mut archive = zip::ZipArchive::new(reader)?;
         let mut file_mappings = HashMap::new();
+        let mut file_names = Vec::new();
 
         // This is a bit roundabout, but we do this in case provided GTFS zip has its files nested
-        // inside another subdirectory
+        // inside another subdirectory: files are mapped by their full name as well as their file name
         for index in 0..archive.len() {
             let archive_file = archive.by_index(index)?;
+            if archive_file.is_dir() {
+                continue;
+            }
 
-            let path = std::path::Path::new(archive_file.name());
-            for dataset_file in DATASET_FILES.iter() {
-                if path.file_name() == Some(std::ffi::OsStr::new(dataset_file)) {
-                    file_mappings.insert(dataset_file.to_string(), index);
-                }
+            let name = archive_file.name().to_owned();
+            if let Some(file_name) = Path::new(&name).file_name().and_then(|f| f.to_str()) {
+                file_mappings.insert(file_name.to_owned(), index);
             }
+            file_mappings.insert(name.clone(), index);
+            file_names.push(name);
         }
 
         Ok(Self {
             archive,
             file_mappings,
+            file_names,
+            strict: false,
+            keep_extras: false,
+            content_hashes: HashMap::new(),
+            records_hashes: HashMap::new(),
         })
     }
 
-    fn read_gtfs<T: DeserializeOwned>(&mut self, filename: &str) -> Result<Vec<T>, Error> {
+    /// By default, coded fields holding a value not defined by the GTFS reference
+    /// (e.g. a `pickup_type` of 4) are kept as their `Other` variant.
+    ///
+    /// In strict mode, such values are reported as an [Error::UnknownCode] instead.
+    ///
+    /// ```
+    /// use gtfs_parser::GtfsReader;
+    ///
+    /// let mut gtfs = GtfsReader::from_path("./resources/zips/gtfs.zip")
+    ///     .unwrap()
+    ///     .strict(true);
+    /// assert!(gtfs.stops().is_ok());
+    /// ```
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Keeps the columns not defined by the GTFS reference in the `extras` of every record,
+    /// so that they are available alongside the standard fields without a [custom](GtfsReader::custom) pass.
+    ///
+    /// ```
+    /// use gtfs_parser::GtfsReader;
+    ///
+    /// let mut gtfs = GtfsReader::from_path("./resources/zips/gtfs.zip")
+    ///     .unwrap()
+    ///     .keep_extras(true);
+    /// let trips = gtfs.raw_trips().unwrap();
+    ///
+    /// assert_eq!(trips[0].extras["brigade_id"], "010/51");
+    /// ```
+    pub fn keep_extras(mut self, keep_extras: bool) -> Self {
+        self.keep_extras = keep_extras;
+        self
+    }
+
+    fn read_gtfs<T: DeserializeOwned + Record>(&mut self, filename: &str) -> Result<Vec<T>, Error> {
+        let objects = self.read_gtfs_lines(filename)?;
+        Ok(objects.into_iter().map(|(_, object)| object).collect())
+    }
+
+    /// Same as `read_gtfs`, with the line of each record in the file.
+    fn read_gtfs_lines<T: DeserializeOwned + Record>(
+        &mut self,
+        filename: &str,
+    ) -> Result<Vec<(u64, T)>, Error> {
         let (filename, index) = self
             .file_mappings
             .get_key_value(filename)
             .map(|(k, v)| (k.clone(), *v))
-            .unwrap();
+            .ok_or_else(|| Error::FileNotFound(filename.to_owned()))?;
 
-        self.read_objects(filename, index)
+        let strict = self.strict;
+        let keep_extras = self.keep_extras;
+        let mut extra_columns = None;
+        let mut objects = Vec::new();
+        self.read_records(&filename, index, |row: Result<Row<T>, Error>| {
+            let mut row = row?;
+            if strict {
+                if let Some((field, value)) = row.record.unknown_codes().into_iter().next() {
+                    return Err(Error::UnknownCode {
+                        filename: filename.clone(),
+                        line: row.line,
+                        field: field.to_owned(),
+                        value,
+                    });
+                }
+            }
+            if keep_extras {
+                let columns = extra_columns.get_or_insert_with(|| row.unknown_columns());
+                for column in columns.iter() {
+                    row.record.extras_mut().insert(
+                        row.headers[*column].to_owned(),
+                        row.values.get(*column).unwrap_or_default().to_owned(),
+                    );
+                }
+            }
+            objects.push((row.line, row.record));
+            Ok(())
+        })?;
+
+        Ok(objects)
     }
 
     /// Some GTFS providers add additional data along the GTFS standard,
@@ -134,6 +271,9 @@ impl GtfsReader {
     ///
     /// The custom() method provides an interface to deserialize such GTFS file into
     /// a user-defined struct.
+    /// Any csv file of the archive can be read that way, including files which are not part
+    /// of the GTFS reference. Files are looked up by file name, or by their full name
+    /// within the archive (see [files](GtfsReader::files)).
     ///
     /// ```
     /// use serde::{Deserialize, Serialize};
@@ -163,6 +303,183 @@ impl GtfsReader {
         }
     }
 
+    /// Names of all the files of the archive, including the ones which are not part of the GTFS reference.
+    ///
+    /// ```
+    /// use gtfs_parser::GtfsReader;
+    ///
+    /// let gtfs = GtfsReader::from_path("./resources/zips/subdirectory.zip").unwrap();
+    ///
+    /// assert!(gtfs.files().contains(&"gtfs/stops.txt"));
+    /// ```
+    pub fn files(&self) -> Vec<&str> {
+        self.file_names.iter().map(String::as_str).collect()
+    }
+
+    /// Whether the archive contains the file, looked up as in [custom](GtfsReader::custom).
+    pub fn has_file(&self, filename: &str) -> bool {
+        self.file_mappings.contains_key(filename)
+    }
+
+    /// Records of an optional file read with one of the methods of the reader, none when
+    /// the file is missing.
+    pub fn optional_file<T>(
+        &mut self,
+        filename: &str,
+        read: fn(&mut Self) -> Result<Vec<T>, Error>,
+    ) -> Result<Vec<T>, Error> {
+        if self.has_file(filename) {
+            read(self)
+        } else {
+            Ok(vec![])
+        }
+    }
+
+    /// Raw content of any file of the archive, e.g. for non csv files such as GeoJSON or README files.
+    /// Files are looked up as in [custom](GtfsReader::custom).
+    pub fn raw(&mut self, filename: &str) -> Result<Vec<u8>, Error> {
+        let index = self.file_index(filename)?;
+
+        let mut zipfile = self.archive.by_index(index)?;
+        let mut content = Vec::with_capacity(zipfile.size() as usize);
+        zipfile
+            .read_to_end(&mut content)
+            .map_err(|err| Error::FileReadError {
+                filename: filename.to_owned(),
+                source: err,
+            })?;
+        self.content_hashes
+            .insert(index, hashing::content_hash(&content));
+
+        Ok(content)
+    }
+
+    /// Hexadecimal SHA-256 digest of the content of a file, looked up as in [custom](GtfsReader::custom).
+    ///
+    /// The digest of a file is computed while it is read, so files which have already been
+    /// read are not read again.
+    ///
+    /// ```
+    /// use gtfs_parser::GtfsReader;
+    ///
+    /// let mut gtfs = GtfsReader::from_path("./resources/zips/gtfs.zip").unwrap();
+    /// let stops = gtfs.stops().unwrap();
+    ///
+    /// assert_eq!(gtfs.file_hash("stops.txt").unwrap().len(), 64);
+    /// ```
+    pub fn file_hash(&mut self, filename: &str) -> Result<String, Error> {
+        let index = self.file_index(filename)?;
+        if !self.content_hashes.contains_key(&index) {
+            self.raw(filename)?;
+        }
+        Ok(hashing::to_hex(&self.content_hashes[&index]))
+    }
+
+    /// Hexadecimal SHA-256 digest of the records of a csv file, which does not change when
+    /// the file is laid out differently: when its records or columns are reordered, when
+    /// values are quoted or padded with spaces, when empty columns are added or removed, or
+    /// when the line endings or byte order mark change.
+    pub fn records_hash(&mut self, filename: &str) -> Result<String, Error> {
+        let index = self.file_index(filename)?;
+        if !self.records_hashes.contains_key(&index) {
+            let content = self.raw(filename)?;
+            let hash = hashing::records_hash(&content).map_err(|source| Error::CSVError {
+                filename: filename.to_owned(),
+                source,
+                line_in_error: None,
+            })?;
+            self.records_hashes.insert(index, hash);
+        }
+        Ok(hashing::to_hex(&self.records_hashes[&index]))
+    }
+
+    /// Hexadecimal SHA-256 digest of the whole archive, combining the [hash](GtfsReader::file_hash)
+    /// of each file with its name. Unlike a digest of the zip file, it does not depend on
+    /// the compression nor on the order of the files in the archive.
+    pub fn feed_hash(&mut self) -> Result<String, Error> {
+        let names = self.file_names.clone();
+        for name in &names {
+            self.file_hash(name)?;
+        }
+        let hashes = names.iter().map(|name| {
+            (
+                name.as_str(),
+                self.content_hashes[&self.file_mappings[name]],
+            )
+        });
+        Ok(hashing::to_hex(&hashing::combine(hashes)))
+    }
+
+    /// Hexadecimal SHA-256 digest of the records of all the csv files of the archive, combining
+    /// their [records hash](GtfsReader::records_hash) with their file name. Two feeds with
+    /// the same semantic hash hold the same data, whatever the layout of their files or of
+    /// the archive. Files other than `.txt` files are left out.
+    ///
+    /// ```
+    /// use gtfs_parser::GtfsReader;
+    ///
+    /// let mut gtfs = GtfsReader::from_path("./resources/zips/gtfs.zip").unwrap();
+    /// let mut nested = GtfsReader::from_path("./resources/zips/subdirectory.zip").unwrap();
+    ///
+    /// assert_eq!(
+    ///     gtfs.records_hash("stops.txt").unwrap(),
+    ///     nested.records_hash("gtfs/stops.txt").unwrap()
+    /// );
+    /// // The trips of the nested feed have no brigade_id
+    /// assert_ne!(gtfs.semantic_hash().unwrap(), nested.semantic_hash().unwrap());
+    /// ```
+    pub fn semantic_hash(&mut self) -> Result<String, Error> {
+        let mut names = Vec::new();
+        for name in self.file_names.clone() {
+            let file_name = match Path::new(&name).file_name().and_then(|f| f.to_str()) {
+                Some(file_name) if file_name.ends_with(".txt") => file_name.to_owned(),
+                _ => continue,
+            };
+            self.records_hash(&name)?;
+            names.push((file_name, self.file_mappings[&name]));
+        }
+        let hashes = names
+            .iter()
+            .map(|(file_name, index)| (file_name.as_str(), self.records_hashes[index]));
+        Ok(hashing::to_hex(&hashing::combine(hashes)))
+    }
+
+    fn file_index(&self, filename: &str) -> Result<usize, Error> {
+        self.file_mappings
+            .get(filename)
+            .copied()
+            .ok_or_else(|| Error::FileNotFound(filename.to_owned()))
+    }
+
+    /// Checks the whole archive, collecting every finding instead of stopping at the first error.
+    ///
+    /// ```
+    /// use gtfs_parser::GtfsReader;
+    ///
+    /// let mut gtfs = GtfsReader::from_path("./resources/zips/gtfs.zip").unwrap();
+    /// let report = gtfs.validate();
+    ///
+    /// assert!(report.has_errors());
+    /// ```
+    pub fn validate(&mut self) -> ValidationReport {
+        validation::validate(self)
+    }
+
+    /// Describes the feed: its files with their number of records and hash, its agencies,
+    /// route types and extent, and the dates covered by its service.
+    ///
+    /// ```
+    /// use gtfs_parser::GtfsReader;
+    ///
+    /// let mut gtfs = GtfsReader::from_path("./resources/zips/gtfs.zip").unwrap();
+    /// let summary = gtfs.summary().unwrap();
+    ///
+    /// assert_eq!(summary.records("stops.txt"), Some(5));
+    /// ```
+    pub fn summary(&mut self) -> Result<FeedSummary, Error> {
+        summary::summarize(self)
+    }
+
     pub fn agencies(&mut self) -> Result<Vec<Agency>, Error> {
         self.read_gtfs("agency.txt")
     }
@@ -197,6 +514,16 @@ impl GtfsReader {
         Ok(dates)
     }
 
+    /// Ids of the services running on the given date.
+    ///
+    /// Either calendar.txt or calendar_dates.txt may be missing from the archive.
+    pub fn services_on(&mut self, date: NaiveDate) -> Result<HashSet<String>, Error> {
+        let calendar = self.optional_file("calendar.txt", Self::calendar)?;
+        let calendar_dates = self.optional_file("calendar_dates.txt", Self::calendar_dates)?;
+
+        Ok(calendar::services_on(&calendar, &calendar_dates, date))
+    }
+
     pub fn fare_attributes(&mut self) -> Result<Vec<FareAttribute>, Error> {
         self.read_gtfs("fare_attributes.txt")
     }
@@ -247,7 +574,7 @@ impl GtfsReader {
 
     pub fn trips(&mut self) -> Result<HashMap<String, Trip>, Error> {
         let raw_trips = self.raw_trips()?;
-        let raw_stop_times = self.raw_stop_times()?;
+        let raw_stop_times = self.read_gtfs_lines::<RawStopTime>("stop_times.txt")?;
 
         let stops: HashMap<String, Arc<Stop>> = self
             .stops()?
@@ -256,23 +583,10 @@ impl GtfsReader {
             .collect();
 
         let mut trips = to_map(raw_trips.into_iter().map(Trip::from));
-
-        for raw in raw_stop_times {
-            let trip = &mut trips
-                .get_mut(&raw.trip_id)
-                .ok_or_else(|| Error::ReferenceError(raw.trip_id.to_string()))?;
-
-            let stop = stops
-                .get(&raw.stop_id)
-                .ok_or_else(|| Error::ReferenceError(raw.stop_id.to_string()))?;
-            trip.stop_times
-                .push(StopTime::from(&raw, Arc::clone(&stop)));
-        }
-
-        for trip in &mut trips.values_mut() {
-            trip.stop_times
-                .sort_by(|a, b| a.stop_sequence.cmp(&b.stop_sequence));
-        }
+        let raw_stop_times = raw_stop_times
+            .into_iter()
+            .map(|(line, stop_time)| (Some(line), stop_time));
+        structures::trips::link_stop_times(&mut trips, raw_stop_times, &stops)?;
 
         Ok(trips)
     }
@@ -281,17 +595,42 @@ impl GtfsReader {
     where
         for<'de> D: Deserialize<'de>,
     {
-        let mut zipfile = self
+        let mut objects = Vec::new();
+        self.read_records(&filename, index, |row| {
+            objects.push(row?.record);
+            Ok(())
+        })?;
+
+        Ok(objects)
+    }
+
+    /// Deserializes every record of a file and hands it, along with its line number
+    /// and raw values, to `on_record`.
+    ///
+    /// Record-level errors are handed over as well, which lets the caller decide
+    /// whether to bail out (by returning the error) or to keep going.
+    fn read_records<D, F>(
+        &mut self,
+        filename: &str,
+        index: usize,
+        mut on_record: F,
+    ) -> Result<(), Error>
+    where
+        for<'de> D: Deserialize<'de>,
+        F: FnMut(Result<Row<D>, Error>) -> Result<(), Error>,
+    {
+        let zipfile = self
             .archive
             .by_index(index)
             .map_err(|_| Error::FileNotFound(format!("Missing file: {}", filename)))?;
+        let mut zipfile = HashingReader::new(zipfile);
 
         let mut bom = [0; 3];
 
         zipfile
             .read_exact(&mut bom)
             .map_err(|err| Error::FileReadError {
-                filename: filename.clone(),
+                filename: filename.to_owned(),
                 source: err,
             })?;
 
@@ -309,48 +648,85 @@ impl GtfsReader {
         let headers = reader
             .headers()
             .map_err(|e| Error::CSVError {
-                filename: filename.clone(),
+                filename: filename.to_owned(),
                 source: e,
                 line_in_error: None,
             })?
             .clone();
+        let mut records_hasher = RecordsHasher::new(headers.as_byte_record());
+        let line_error = |line: u64, values: &csv::StringRecord| error::LineError {
+            line,
+            column: None,
+            headers: headers.iter().map(ToOwned::to_owned).collect(),
+            values: values.iter().map(ToOwned::to_owned).collect(),
+        };
 
-        let mut objects = Vec::new();
-        for record in reader.records() {
-            let string_record = record.map_err(|err| Error::CSVError {
-                filename: filename.clone(),
-                source: err,
-                line_in_error: Some(error::LineError {
-                    headers: headers
-                        .into_iter()
-                        .map(|header| header.to_owned())
-                        .collect(),
-                    values: vec![],
-                }),
-            })?;
+        // Records are read as bytes so that their values are still available when they are not valid UTF-8
+        let mut byte_record = csv::ByteRecord::new();
+        loop {
+            match reader.read_byte_record(&mut byte_record) {
+                Ok(true) => {}
+                Ok(false) => break,
+                Err(err) => {
+                    let line = err.position().map(|p| p.line()).unwrap_or_default();
+                    // The rest of the file cannot be trusted after a read error
+                    return on_record(Err(Error::CSVError {
+                        filename: filename.to_owned(),
+                        line_in_error: Some(line_error(line, &csv::StringRecord::new())),
+                        source: err,
+                    }));
+                }
+            }
+
+            records_hasher.add(&byte_record);
+
+            let line = byte_record
+                .position()
+                .map(|position| position.line())
+                .unwrap_or_default();
+
+            let string_record = match csv::StringRecord::from_byte_record(byte_record.clone()) {
+                Ok(string_record) => string_record,
+                Err(err) => {
+                    let column = err.utf8_error().field();
+                    let values = csv::StringRecord::from_byte_record_lossy(err.into_byte_record());
+                    let mut line_in_error = line_error(line, &values);
+                    line_in_error.column = headers.get(column).map(ToOwned::to_owned);
+                    on_record(Err(Error::InvalidUtf8 {
+                        filename: filename.to_owned(),
+                        line_in_error,
+                    }))?;
+                    continue;
+                }
+            };
 
             let obj = string_record
                 .deserialize(Some(&headers))
                 .map_err(|err| Error::CSVError {
-                    filename: filename.clone(),
+                    filename: filename.to_owned(),
+                    line_in_error: Some(line_error(line, &string_record).locate::<D>(&err)),
                     source: err,
-                    line_in_error: Some(error::LineError {
-                        headers: headers
-                            .into_iter()
-                            .map(|header| header.to_owned())
-                            .collect(),
-                        values: string_record.into_iter().map(ToOwned::to_owned).collect(),
-                    }),
-                })?;
-
-            objects.push(obj);
+                });
+
+            on_record(obj.map(|record| Row {
+                line,
+                record,
+                headers: &headers,
+                values: &string_record,
+            }))?;
         }
 
-        Ok(objects)
+        // The whole file has been read
+        let (_, zipfile) = reader.into_inner().into_inner();
+        self.content_hashes.insert(index, zipfile.finalize());
+        self.records_hashes.insert(index, records_hasher.finalize());
+
+        Ok(())
     }
 }
 
 #[cfg(test)]
+#[allow(deprecated, clippy::bool_assert_comparison)]
 mod test {
 
     use chrono::NaiveDate;
@@ -372,7 +748,20 @@ mod test {
     };
 
     use super::*;
-    use std::path::PathBuf;
+    use std::{io::Write, path::PathBuf};
+
+    /// Builds a reader over an in-memory archive made of the given (filename, content) pairs.
+    pub(crate) fn reader_from_files(files: &[(&str, &str)]) -> GtfsReader {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in files {
+            writer
+                .start_file(*name, zip::write::FileOptions::default())
+                .unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        let cursor = writer.finish().unwrap();
+        GtfsReader::from_reader(Box::new(Cursor::new(cursor.into_inner()))).unwrap()
+    }
 
     fn parse_time_over_midnight(time: u64) -> String {
         let hours = time / 3600;
@@ -413,6 +802,226 @@ mod test {
         assert_eq!(trip_brigade[0].trip_id, "trip1");
     }
 
+    const UNKNOWN_CODES_STOP_TIMES: &str =
+        "trip_id,arrival_time,departure_time,stop_id,stop_sequence,pickup_type,drop_off_type\n\
+        trip1,14:00:00,14:00:00,stop1,1,4,\n";
+
+    #[test]
+    fn unknown_codes_lenient() {
+        let mut gtfs = reader_from_files(&[("stop_times.txt", UNKNOWN_CODES_STOP_TIMES)]);
+        let stop_times = gtfs.raw_stop_times().unwrap();
+
+        assert_eq!(stop_times[0].pickup_type, PickupDropOffType::Other(4));
+        assert_eq!(stop_times[0].drop_off_type, PickupDropOffType::Regular);
+    }
+
+    #[test]
+    fn unknown_codes_strict() {
+        let mut gtfs =
+            reader_from_files(&[("stop_times.txt", UNKNOWN_CODES_STOP_TIMES)]).strict(true);
+
+        match gtfs.raw_stop_times() {
+            Err(Error::UnknownCode {
+                filename,
+                line,
+                field,
+                value,
+            }) => {
+                assert_eq!(filename, "stop_times.txt");
+                assert_eq!(line, 2);
+                assert_eq!(field, "pickup_type");
+                assert_eq!(value, 4);
+            }
+            other => panic!("expected an unknown code error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn invalid_codes() {
+        let mut gtfs = reader_from_files(&[(
+            "transfers.txt",
+            "from_stop_id,to_stop_id,transfer_type\nS1,S2,timed\n",
+        )]);
+
+        assert!(gtfs.transfers().is_err());
+    }
+
+    #[test]
+    fn services_on() {
+        let mut gtfs = GtfsReader::from_path(PathBuf::from("./resources/zips/gtfs.zip")).unwrap();
+
+        let services = gtfs
+            .services_on(NaiveDate::from_ymd_opt(2006, 7, 3).unwrap())
+            .unwrap();
+        assert_eq!(services, ["WE".to_owned()].iter().cloned().collect());
+
+        let services = gtfs
+            .services_on(NaiveDate::from_ymd_opt(2006, 7, 5).unwrap())
+            .unwrap();
+        assert_eq!(services, ["WD".to_owned()].iter().cloned().collect());
+    }
+
+    #[test]
+    fn error_line_and_column() {
+        let mut gtfs = reader_from_files(&[(
+            "stop_times.txt",
+            "trip_id,arrival_time,departure_time,stop_id,stop_sequence\n\
+             T1,08:00:00,08:00:00,S1,1\n\
+             T1,8h05,08:05:00,S2,2\n",
+        )]);
+
+        let err = gtfs.raw_stop_times().unwrap_err();
+        assert!(err.to_string().starts_with(
+            "Failed to read csv file 'stop_times.txt' at line 3, column 'arrival_time' (value '8h05')"
+        ));
+        match err {
+            Error::CSVError {
+                line_in_error: Some(line),
+                ..
+            } => {
+                assert_eq!(line.line, 3);
+                assert_eq!(line.column.as_deref(), Some("arrival_time"));
+                assert_eq!(line.values.len(), 5);
+            }
+            err => panic!("unexpected error {:?}", err),
+        }
+    }
+
+    #[test]
+    fn error_column_with_custom_deserializer() {
+        // The invalid pickup_type has the value of stop_id
+        let mut gtfs = reader_from_files(&[(
+            "stop_times.txt",
+            "trip_id,arrival_time,departure_time,stop_id,stop_sequence,pickup_type\n\
+             T,08:00:00,08:00:00,x,1,x\n",
+        )]);
+
+        match gtfs.raw_stop_times().unwrap_err() {
+            Error::CSVError {
+                line_in_error: Some(line),
+                ..
+            } => {
+                assert_eq!(line.column.as_deref(), Some("pickup_type"));
+                assert_eq!(line.value(), Some("x"));
+            }
+            err => panic!("unexpected error {:?}", err),
+        }
+    }
+
+    #[test]
+    fn reference_error_context() {
+        let mut gtfs = reader_from_files(&[
+            ("trips.txt", "route_id,service_id,trip_id\nR1,S,T1\n"),
+            ("stops.txt", "stop_id,stop_name\nS1,Main\n"),
+            (
+                "stop_times.txt",
+                "trip_id,arrival_time,departure_time,stop_id,stop_sequence\n\
+                 T1,08:00:00,08:00:00,S1,1\n\
+                 T1,08:05:00,08:05:00,S9,2\n",
+            ),
+        ]);
+
+        let err = gtfs.trips().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unknown stop_id 'S9' in 'stop_times.txt' at line 3, it is not defined in 'stops.txt'"
+        );
+    }
+
+    #[test]
+    fn create_trips_reference_error() {
+        let mut gtfs = reader_from_files(&[
+            ("trips.txt", "route_id,service_id,trip_id\nR1,S,T1\n"),
+            ("stops.txt", "stop_id,stop_name\nS1,Main\n"),
+            (
+                "stop_times.txt",
+                "trip_id,arrival_time,departure_time,stop_id,stop_sequence\n\
+                 T1,08:05:00,08:05:00,S9,2\n",
+            ),
+        ]);
+        let stops = gtfs
+            .stops()
+            .unwrap()
+            .into_iter()
+            .map(|stop| (stop.id.clone(), Arc::new(stop)))
+            .collect();
+
+        // The line is unknown once the stop times are read
+        let err = Trip::create_trips(
+            gtfs.raw_trips().unwrap(),
+            gtfs.raw_stop_times().unwrap(),
+            &stops,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unknown stop_id 'S9' in 'stop_times.txt', it is not defined in 'stops.txt'"
+        );
+    }
+
+    #[test]
+    fn non_standard_files() {
+        use serde::Deserialize;
+        #[derive(Deserialize, Debug)]
+        struct RouteDirection {
+            route_id: String,
+            direction_name: String,
+        }
+
+        let mut gtfs = reader_from_files(&[
+            (
+                "feed/route_directions.txt",
+                "route_id,direction_name\nA,North\n",
+            ),
+            ("feed/stops.geojson", "{\"type\": \"FeatureCollection\"}"),
+            ("README", "Sample feed"),
+        ]);
+
+        assert_eq!(
+            gtfs.files(),
+            vec!["feed/route_directions.txt", "feed/stops.geojson", "README"]
+        );
+        assert!(gtfs.has_file("route_directions.txt"));
+
+        let directions: Vec<RouteDirection> = gtfs.custom("route_directions.txt").unwrap();
+        assert_eq!(directions[0].route_id, "A");
+        assert_eq!(directions[0].direction_name, "North");
+        let directions: Vec<RouteDirection> = gtfs.custom("feed/route_directions.txt").unwrap();
+        assert_eq!(directions.len(), 1);
+
+        assert_eq!(gtfs.raw("README").unwrap(), b"Sample feed");
+        assert_eq!(
+            gtfs.raw("stops.geojson").unwrap(),
+            b"{\"type\": \"FeatureCollection\"}"
+        );
+        assert!(matches!(
+            gtfs.raw("vehicles.txt"),
+            Err(Error::FileNotFound(_))
+        ));
+        assert!(matches!(gtfs.stops(), Err(Error::FileNotFound(_))));
+    }
+
+    #[test]
+    fn trips_extras() {
+        let mut gtfs = GtfsReader::from_path(PathBuf::from("./resources/zips/gtfs.zip"))
+            .unwrap()
+            .keep_extras(true);
+        let trips = gtfs.trips().unwrap();
+        let trip = &trips["trip1"];
+
+        assert_eq!(trip.extras["brigade_id"], "010/51");
+        assert_eq!(trip.stop_times[0].extras["stop_time_desc"], "");
+        assert!(!trip.extras.contains_key("trip_id"));
+    }
+
+    #[test]
+    fn no_extras_by_default() {
+        let mut gtfs = GtfsReader::from_path(PathBuf::from("./resources/zips/gtfs.zip")).unwrap();
+        let trips = gtfs.raw_trips().unwrap();
+
+        assert!(trips[0].extras.is_empty());
+    }
+
     macro_rules! test_gtfs {
         ($function:ident, $method:ident, $zip:literal) => {
             #[test]
@@ -450,6 +1059,10 @@ mod test {
     test_gtfs! { fare_rules_from_zip_with_bom, fare_rules, "gtfs_with_bom.zip" }
     test_gtfs! { fare_rules_from_zip_subdirectory, fare_rules, "subdirectory.zip" }
 
+    test_gtfs! { feed_info_from_zip, feed_info, "gtfs.zip" }
+    test_gtfs! { feed_info_from_zip_with_bom, feed_info, "gtfs_with_bom.zip" }
+    test_gtfs! { feed_info_from_zip_subdirectory, feed_info, "subdirectory.zip" }
+
     test_gtfs! { frequencies_from_zip, frequencies, "gtfs.zip" }
     test_gtfs! { frequencies_from_zip_with_bom, frequencies, "gtfs_with_bom.zip" }
     test_gtfs! { frequencies_from_zip_subdirectory, frequencies, "subdirectory.zip" }
@@ -546,6 +1159,14 @@ mod test {
         assert_eq!(target.destination_id, Some("1".to_string()));
     }
 
+    fn feed_info(target: Vec<FeedInfo>) {
+        let target = &target[0];
+        assert_eq!(target.name, "SNCF");
+        assert_eq!(target.start_date, NaiveDate::from_ymd_opt(2018, 7, 9));
+        assert_eq!(target.end_date, NaiveDate::from_ymd_opt(2018, 9, 27));
+        assert_eq!(target.version.as_deref(), Some("0.3"));
+    }
+
     fn frequencies(target: Vec<Frequency>) {
         let target = &target[2];
         assert_eq!(target.trip_id, "AWE1");
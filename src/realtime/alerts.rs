@@ -0,0 +1,501 @@
+//! Resolution of GTFS-Realtime service alerts to the entities of the static schedule.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    convert::TryFrom,
+};
+
+use chrono::{NaiveDate, TimeZone};
+
+use super::{Alert, EntitySelector, FeedMessage, TimeRange};
+use crate::structures::{
+    agency::Agency,
+    calendar::service_day_start,
+    routes::{Route, RouteType},
+    stop_times::StopTime,
+    stops::Stop,
+    trips::Trip,
+};
+
+/// The routes, trips and stops affected by an alert, sorted by id.
+#[derive(Debug, Clone, Default)]
+pub struct AffectedEntities<'s> {
+    /// Routes affected as a whole, by selectors on an agency, a route or a route type.
+    pub routes: Vec<&'s Route>,
+    /// Trips affected at all or some of their stops.
+    pub trips: Vec<&'s Trip>,
+    pub stops: Vec<&'s Stop>,
+}
+
+/// A service date the alerts are resolved on.
+struct ServiceDay<'s> {
+    date: NaiveDate,
+    start: i64,
+    services: &'s HashSet<String>,
+}
+
+/// Resolves the `informed_entity` selectors of alerts against the static schedule.
+///
+/// The fields of a selector must all match an entity for it to be affected, and an alert
+/// affects the entities matched by any of its selectors. Selectors on a stop affect it
+/// along with the stops of a station, and only at those stops when they also select trips.
+///
+/// ```
+/// use gtfs_parser::{realtime::{alerts::AlertResolver, FeedMessage}, GtfsReader};
+///
+/// let mut gtfs = GtfsReader::from_path("./resources/zips/gtfs.zip").unwrap();
+/// let (agencies, routes, stops) = (gtfs.agencies().unwrap(), gtfs.routes().unwrap(), gtfs.stops().unwrap());
+/// let trips = gtfs.trips().unwrap();
+/// let resolver = AlertResolver::new(&agencies, &routes, &trips, &stops);
+///
+/// let feed = FeedMessage::from_path("./resources/realtime/alerts.pb").unwrap();
+/// let affected = resolver.resolve(feed.alerts().next().unwrap());
+///
+/// assert_eq!(affected.stops[0].id, "stop2");
+/// ```
+pub struct AlertResolver<'s> {
+    routes: HashMap<&'s str, &'s Route>,
+    trips: &'s HashMap<String, Trip>,
+    stops: HashMap<&'s str, &'s Stop>,
+    /// Agency of the routes without agency_id, when the feed has a single agency.
+    default_agency: Option<&'s str>,
+    day: Option<ServiceDay<'s>>,
+}
+
+impl<'s> AlertResolver<'s> {
+    pub fn new(
+        agencies: &'s [Agency],
+        routes: &'s [Route],
+        trips: &'s HashMap<String, Trip>,
+        stops: &'s [Stop],
+    ) -> Self {
+        let default_agency = match agencies {
+            [agency] => agency.id.as_deref(),
+            _ => None,
+        };
+
+        Self {
+            routes: routes
+                .iter()
+                .map(|route| (route.id.as_str(), route))
+                .collect(),
+            trips,
+            stops: stops.iter().map(|stop| (stop.id.as_str(), stop)).collect(),
+            default_agency,
+            day: None,
+        }
+    }
+
+    /// Restricts the resolution to the trips of the running `services` of `date`, in the
+    /// time zone of the agency, which run during the active periods of the alerts.
+    pub fn on<Tz: TimeZone>(
+        mut self,
+        date: NaiveDate,
+        services: &'s HashSet<String>,
+        timezone: &Tz,
+    ) -> Self {
+        self.day = Some(ServiceDay {
+            date,
+            start: service_day_start(date, timezone),
+            services,
+        });
+        self
+    }
+
+    /// Routes, trips and stops affected by an alert.
+    pub fn resolve(&self, alert: &Alert) -> AffectedEntities<'s> {
+        let selectors: Vec<&EntitySelector> = alert
+            .informed_entity
+            .iter()
+            .filter(|selector| !is_empty(selector))
+            .collect();
+        let mut trips = BTreeMap::new();
+        let mut stops = BTreeMap::new();
+
+        for trip in self.trips.values() {
+            if !self.runs_during(trip, &alert.active_period) {
+                continue;
+            }
+            for selector in selectors.iter().filter(|s| self.matches_trip(s, trip)) {
+                let stop_id = match &selector.stop_id {
+                    Some(stop_id) => stop_id,
+                    None => {
+                        trips.insert(trip.id.as_str(), trip);
+                        continue;
+                    }
+                };
+                for stop_time in &trip.stop_times {
+                    if is_stop(&stop_time.stop, stop_id)
+                        && self.calls_during(stop_time, &alert.active_period)
+                    {
+                        trips.insert(trip.id.as_str(), trip);
+                        stops.insert(stop_time.stop.id.as_str(), &*stop_time.stop);
+                    }
+                }
+            }
+        }
+
+        // Stops selected on their own are affected even when no trip calls at them
+        for stop_id in selectors
+            .iter()
+            .filter(|selector| !selects_trips(selector))
+            .filter_map(|selector| selector.stop_id.as_ref())
+        {
+            for stop in self.stops.values().filter(|stop| is_stop(stop, stop_id)) {
+                stops.insert(stop.id.as_str(), stop);
+            }
+        }
+
+        let mut routes: Vec<&Route> = self
+            .routes
+            .values()
+            .filter(|route| {
+                selectors.iter().any(|selector| {
+                    selector.trip.is_none()
+                        && selector.stop_id.is_none()
+                        && selector.direction_id.is_none()
+                        && self.matches_route(selector, route)
+                })
+            })
+            .filter(|route| {
+                self.day.is_none() || trips.values().any(|trip| trip.route_id == route.id)
+            })
+            .copied()
+            .collect();
+        routes.sort_by(|a, b| a.id.cmp(&b.id));
+
+        AffectedEntities {
+            routes,
+            trips: trips.into_values().collect(),
+            stops: stops.into_values().collect(),
+        }
+    }
+
+    /// Whether an alert applies to the departure of a trip from a stop at the given POSIX time.
+    pub fn applies(&self, alert: &Alert, trip: &Trip, stop_time: &StopTime, time: i64) -> bool {
+        is_active(&alert.active_period, time)
+            && alert.informed_entity.iter().any(|selector| {
+                !is_empty(selector)
+                    && self.matches_trip(selector, trip)
+                    && match &selector.stop_id {
+                        Some(stop_id) => is_stop(&stop_time.stop, stop_id),
+                        None => true,
+                    }
+            })
+    }
+
+    /// Alerts of a feed which apply to the departure of a trip from a stop at the given
+    /// POSIX time.
+    pub fn alerts_at<'f>(
+        &self,
+        feed: &'f FeedMessage,
+        trip: &Trip,
+        stop_time: &StopTime,
+        time: i64,
+    ) -> Vec<&'f Alert> {
+        feed.alerts()
+            .filter(|alert| self.applies(alert, trip, stop_time, time))
+            .collect()
+    }
+
+    fn matches_route(&self, selector: &EntitySelector, route: &Route) -> bool {
+        if let Some(agency_id) = &selector.agency_id {
+            if route.agency_id.as_deref().or(self.default_agency) != Some(agency_id.as_str()) {
+                return false;
+            }
+        }
+        if let Some(route_type) = selector.route_type {
+            let selected = u16::try_from(route_type).ok().map(RouteType::from);
+            if selected != Some(route.route_type) {
+                return false;
+            }
+        }
+        match &selector.route_id {
+            Some(route_id) => *route_id == route.id,
+            None => true,
+        }
+    }
+
+    fn matches_trip(&self, selector: &EntitySelector, trip: &Trip) -> bool {
+        if selector.agency_id.is_some() || selector.route_type.is_some() {
+            match self.routes.get(trip.route_id.as_str()) {
+                Some(route) if self.matches_route(selector, route) => (),
+                _ => return false,
+            }
+        }
+        if let Some(route_id) = &selector.route_id {
+            if *route_id != trip.route_id {
+                return false;
+            }
+        }
+        if let Some(direction_id) = selector.direction_id {
+            if trip.direction_id.map(|d| d as u32) != Some(direction_id) {
+                return false;
+            }
+        }
+
+        let descriptor = match &selector.trip {
+            Some(descriptor) => descriptor,
+            None => return true,
+        };
+        if let (Some(start_date), Some(day)) = (&descriptor.start_date, &self.day) {
+            if *start_date != day.date.format("%Y%m%d").to_string() {
+                return false;
+            }
+        }
+        match &descriptor.trip_id {
+            Some(trip_id) => *trip_id == trip.id,
+            None => {
+                descriptor.route_id.is_some()
+                    && descriptor.route_id.as_ref() == Some(&trip.route_id)
+                    && match descriptor.direction_id {
+                        Some(direction_id) => {
+                            trip.direction_id.map(|d| d as u32) == Some(direction_id)
+                        }
+                        None => true,
+                    }
+            }
+        }
+    }
+
+    /// Whether the trip runs on the service day, if any, during one of the periods.
+    fn runs_during(&self, trip: &Trip, periods: &[TimeRange]) -> bool {
+        let day = match &self.day {
+            Some(day) => day,
+            None => return true,
+        };
+        if !day.services.contains(&trip.service_id) {
+            return false;
+        }
+
+        let times = trip
+            .stop_times
+            .iter()
+            .flat_map(|stop_time| stop_time.arrival_time.or(stop_time.departure_time))
+            .map(|time| time as i64 + day.start);
+        match times.clone().min().zip(times.max()) {
+            Some((first, last)) => {
+                periods.is_empty()
+                    || periods.iter().any(|period| {
+                        let (start, end) = bounds(period);
+                        start <= last && first <= end
+                    })
+            }
+            None => false,
+        }
+    }
+
+    /// Whether the trip calls at the stop during one of the periods, on the service day if any.
+    fn calls_during(&self, stop_time: &StopTime, periods: &[TimeRange]) -> bool {
+        let day = match &self.day {
+            Some(day) => day,
+            None => return true,
+        };
+        match stop_time.departure_time.or(stop_time.arrival_time) {
+            Some(time) => is_active(periods, time as i64 + day.start),
+            None => true,
+        }
+    }
+}
+
+/// Whether one of the periods, if any, contains the POSIX time.
+fn is_active(periods: &[TimeRange], time: i64) -> bool {
+    periods.is_empty()
+        || periods.iter().any(|period| {
+            let (start, end) = bounds(period);
+            start <= time && time <= end
+        })
+}
+
+fn bounds(period: &TimeRange) -> (i64, i64) {
+    (
+        period.start.map_or(i64::MIN, |start| start as i64),
+        period.end.map_or(i64::MAX, |end| end as i64),
+    )
+}
+
+/// Selectors must specify at least one field, those which do not select anything.
+fn is_empty(selector: &EntitySelector) -> bool {
+    selector.agency_id.is_none()
+        && selector.route_id.is_none()
+        && selector.route_type.is_none()
+        && selector.trip.is_none()
+        && selector.stop_id.is_none()
+        && selector.direction_id.is_none()
+}
+
+fn selects_trips(selector: &EntitySelector) -> bool {
+    selector.agency_id.is_some()
+        || selector.route_id.is_some()
+        || selector.route_type.is_some()
+        || selector.trip.is_some()
+        || selector.direction_id.is_some()
+}
+
+/// Whether the stop is the given one or one of the stops of that station.
+fn is_stop(stop: &Stop, stop_id: &str) -> bool {
+    stop.id == stop_id || stop.parent_station.as_deref() == Some(stop_id)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        realtime::{alert::Effect, TripDescriptor},
+        test::reader_from_files,
+    };
+    use chrono::Utc;
+
+    struct Static {
+        agencies: Vec<Agency>,
+        routes: Vec<Route>,
+        trips: HashMap<String, Trip>,
+        stops: Vec<Stop>,
+    }
+
+    fn date() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, 4).unwrap()
+    }
+
+    fn at(hours: i64, minutes: i64) -> u64 {
+        (service_day_start(date(), &Utc) + hours * 3600 + minutes * 60) as u64
+    }
+
+    /// A bus route R1 calling at stops A, B (in station ST) and C, and a tram route R2.
+    fn feed() -> Static {
+        let mut gtfs = reader_from_files(&[
+            (
+                "agency.txt",
+                "agency_id,agency_name,agency_url,agency_timezone\n\
+                 AG,Agency,https://example.com,UTC\n",
+            ),
+            (
+                "routes.txt",
+                "route_id,route_short_name,route_long_name,route_type\nR1,1,,3\nR2,2,,0\n",
+            ),
+            (
+                "stops.txt",
+                "stop_id,stop_name,location_type,parent_station\n\
+                 A,A,0,\nB,B,0,ST\nC,C,0,\nST,Station,1,\n",
+            ),
+            (
+                "trips.txt",
+                "route_id,service_id,trip_id,direction_id\n\
+                 R1,WD,T1,0\nR1,WD,T2,1\nR2,WD,T3,0\nR2,WE,T4,0\n",
+            ),
+            (
+                "stop_times.txt",
+                "trip_id,arrival_time,departure_time,stop_id,stop_sequence\n\
+                 T1,08:00:00,08:00:00,A,1\nT1,08:10:00,08:10:00,B,2\n\
+                 T2,09:00:00,09:00:00,B,1\nT2,09:10:00,09:10:00,A,2\n\
+                 T3,08:00:00,08:00:00,C,1\nT3,08:10:00,08:10:00,B,2\n\
+                 T4,08:00:00,08:00:00,C,1\n",
+            ),
+        ]);
+        Static {
+            agencies: gtfs.agencies().unwrap(),
+            routes: gtfs.routes().unwrap(),
+            trips: gtfs.trips().unwrap(),
+            stops: gtfs.stops().unwrap(),
+        }
+    }
+
+    fn alert(selectors: Vec<EntitySelector>) -> Alert {
+        Alert {
+            informed_entity: selectors,
+            effect: Some(Effect::Detour as i32),
+            ..Default::default()
+        }
+    }
+
+    fn ids<'a, T>(entities: &[&'a T], id: impl Fn(&'a T) -> &'a str) -> Vec<&'a str> {
+        entities.iter().map(|entity| id(entity)).collect()
+    }
+
+    #[test]
+    fn resolve() {
+        let gtfs = feed();
+        let resolver = AlertResolver::new(&gtfs.agencies, &gtfs.routes, &gtfs.trips, &gtfs.stops);
+
+        // Buses of the agency, the only agency covering routes without agency_id
+        let affected = resolver.resolve(&alert(vec![EntitySelector {
+            agency_id: Some("AG".to_owned()),
+            route_type: Some(3),
+            ..Default::default()
+        }]));
+        assert_eq!(ids(&affected.routes, |r| &r.id), ["R1"]);
+        assert_eq!(ids(&affected.trips, |t| &t.id), ["T1", "T2"]);
+        assert!(affected.stops.is_empty());
+
+        // A direction of a route is not the whole route
+        let affected = resolver.resolve(&alert(vec![EntitySelector {
+            route_id: Some("R1".to_owned()),
+            direction_id: Some(1),
+            ..Default::default()
+        }]));
+        assert!(affected.routes.is_empty());
+        assert_eq!(ids(&affected.trips, |t| &t.id), ["T2"]);
+
+        // A station, and the stops of a trip
+        let affected = resolver.resolve(&alert(vec![
+            EntitySelector {
+                stop_id: Some("ST".to_owned()),
+                ..Default::default()
+            },
+            EntitySelector {
+                trip: Some(TripDescriptor {
+                    trip_id: Some("T3".to_owned()),
+                    ..Default::default()
+                }),
+                stop_id: Some("C".to_owned()),
+                ..Default::default()
+            },
+        ]));
+        assert_eq!(ids(&affected.trips, |t| &t.id), ["T1", "T2", "T3"]);
+        assert_eq!(ids(&affected.stops, |s| &s.id), ["B", "C", "ST"]);
+    }
+
+    #[test]
+    fn active_periods() {
+        let gtfs = feed();
+        let services = ["WD".to_owned()].iter().cloned().collect();
+        let resolver = AlertResolver::new(&gtfs.agencies, &gtfs.routes, &gtfs.trips, &gtfs.stops)
+            .on(date(), &services, &Utc);
+
+        let mut closure = alert(vec![EntitySelector {
+            stop_id: Some("B".to_owned()),
+            ..Default::default()
+        }]);
+        closure.active_period = vec![TimeRange {
+            start: Some(at(8, 30)),
+            end: Some(at(10, 0)),
+        }];
+        let affected = resolver.resolve(&closure);
+        assert_eq!(ids(&affected.trips, |t| &t.id), ["T2"]);
+
+        let tram = resolver.resolve(&alert(vec![EntitySelector {
+            route_id: Some("R2".to_owned()),
+            ..Default::default()
+        }]));
+        assert_eq!(ids(&tram.trips, |t| &t.id), ["T3"]);
+
+        let t2 = &gtfs.trips["T2"];
+        let departure = &t2.stop_times[0];
+        assert!(resolver.applies(&closure, t2, departure, at(9, 0) as i64));
+        assert!(!resolver.applies(&closure, t2, departure, at(10, 30) as i64));
+        assert!(!resolver.applies(&closure, t2, &t2.stop_times[1], at(9, 10) as i64));
+
+        let feed = FeedMessage {
+            entity: vec![crate::realtime::FeedEntity {
+                id: "closure".to_owned(),
+                alert: Some(closure.clone()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert_eq!(
+            resolver.alerts_at(&feed, t2, departure, at(9, 0) as i64),
+            [&closure]
+        );
+    }
+}
@@ -0,0 +1,300 @@
+//! Archives of timestamped GTFS-Realtime snapshots, stored as the entries of a zip file.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{File, OpenOptions},
+    io::{Read, Seek, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use zip::{write::FileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use super::FeedMessage;
+use crate::error::Error;
+
+/// Name of the archive entry of the snapshot taken at a POSIX time, padded to sort by time.
+fn entry_name(timestamp: u64) -> String {
+    format!("{:010}.pb", timestamp)
+}
+
+fn entry_timestamp(name: &str) -> Option<u64> {
+    name.strip_suffix(".pb")?.parse().ok()
+}
+
+/// Records GTFS-Realtime snapshots in a compressed archive.
+///
+/// Snapshots are stored under the timestamp of their header, falling back to the time they
+/// are recorded at. A snapshot with the timestamp of one already recorded is not stored
+/// again, so that a feed can be polled more often than it is updated.
+///
+/// The index of the archive is only written by [finish](SnapshotRecorder::finish) and
+/// [checkpoint](SnapshotRecorder::checkpoint): when the process stops without finishing,
+/// the snapshots recorded since the last checkpoint are lost, along with the whole archive
+/// if there was none. Recorders running for a long time should checkpoint periodically.
+///
+/// ```no_run
+/// use gtfs_parser::realtime::archive::SnapshotRecorder;
+/// use std::{thread, time::Duration};
+///
+/// let mut recorder = SnapshotRecorder::open("trip_updates.zip").unwrap();
+/// loop {
+///     recorder.record_url("https://example.com/gtfs-rt/trip-updates").unwrap();
+///     recorder.checkpoint().unwrap();
+///     thread::sleep(Duration::from_secs(30));
+/// }
+/// ```
+pub struct SnapshotRecorder<W: Write + Seek> {
+    writer: ZipWriter<W>,
+    timestamps: BTreeSet<u64>,
+}
+
+impl<W: Write + Seek> SnapshotRecorder<W> {
+    /// Recorder writing a new archive.
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: ZipWriter::new(writer),
+            timestamps: BTreeSet::new(),
+        }
+    }
+
+    /// Records a snapshot, returning whether it was stored.
+    pub fn record(&mut self, feed: &FeedMessage) -> Result<bool, Error> {
+        let timestamp = match feed.header.timestamp {
+            Some(timestamp) => timestamp,
+            None => SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |now| now.as_secs()),
+        };
+        self.record_bytes(timestamp, &feed.to_bytes())
+    }
+
+    /// Records the encoded snapshot taken at a POSIX time, returning whether it was stored.
+    pub fn record_bytes(&mut self, timestamp: u64, bytes: &[u8]) -> Result<bool, Error> {
+        if self.timestamps.contains(&timestamp) {
+            return Ok(false);
+        }
+
+        let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+        self.writer.start_file(entry_name(timestamp), options)?;
+        self.writer.write_all(bytes)?;
+        self.timestamps.insert(timestamp);
+        Ok(true)
+    }
+
+    /// Downloads and records a snapshot.
+    #[cfg(feature = "read-url")]
+    pub fn record_url<U: reqwest::IntoUrl>(&mut self, url: U) -> Result<FeedMessage, Error> {
+        let feed = FeedMessage::from_url(url)?;
+        self.record(&feed)?;
+        Ok(feed)
+    }
+
+    /// Writes the index of the archive, which cannot be read without it.
+    pub fn finish(mut self) -> Result<W, Error> {
+        Ok(self.writer.finish()?)
+    }
+}
+
+impl<W: Read + Write + Seek> SnapshotRecorder<W> {
+    /// Writes the index of the archive, so that it can be read with every snapshot recorded
+    /// so far, and goes on appending to it.
+    pub fn checkpoint(&mut self) -> Result<(), Error> {
+        let inner = self.writer.finish()?;
+        self.writer = ZipWriter::new_append(inner)?;
+        Ok(())
+    }
+}
+
+impl SnapshotRecorder<File> {
+    /// Recorder appending to the archive at the given path, created if it does not exist.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        if !path.as_ref().exists() {
+            // Readable to be appended to after a checkpoint
+            let file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(path)?;
+            return Ok(Self::new(file));
+        }
+
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        let timestamps = SnapshotArchive::new(&file)?
+            .timestamps()
+            .into_iter()
+            .collect();
+        Ok(Self {
+            writer: ZipWriter::new_append(file)?,
+            timestamps,
+        })
+    }
+}
+
+/// An archive of GTFS-Realtime snapshots, as written by [SnapshotRecorder].
+///
+/// ```
+/// use gtfs_parser::realtime::archive::{SnapshotArchive, SnapshotRecorder};
+/// use gtfs_parser::realtime::FeedMessage;
+/// use std::io::Cursor;
+///
+/// let feed = FeedMessage::from_path("./resources/realtime/trip_updates.pb").unwrap();
+/// let mut recorder = SnapshotRecorder::new(Cursor::new(Vec::new()));
+/// recorder.record(&feed).unwrap();
+///
+/// let mut archive = SnapshotArchive::new(recorder.finish().unwrap()).unwrap();
+/// for snapshot in archive.snapshots() {
+///     let (timestamp, feed) = snapshot.unwrap();
+///     assert_eq!(feed.header.timestamp, Some(timestamp));
+/// }
+/// ```
+pub struct SnapshotArchive<R: Read + Seek> {
+    archive: ZipArchive<R>,
+    /// Name of the archive entry of each snapshot, by timestamp
+    entries: BTreeMap<u64, String>,
+}
+
+impl<R: Read + Seek> SnapshotArchive<R> {
+    pub fn new(reader: R) -> Result<Self, Error> {
+        let archive = ZipArchive::new(reader)?;
+        let entries = archive
+            .file_names()
+            .filter_map(|name| Some((entry_timestamp(name)?, name.to_owned())))
+            .collect();
+
+        Ok(Self { archive, entries })
+    }
+
+    /// Timestamps of the snapshots, in chronological order.
+    pub fn timestamps(&self) -> Vec<u64> {
+        self.entries.keys().copied().collect()
+    }
+
+    /// The snapshot taken at a POSIX time, if any.
+    pub fn snapshot(&mut self, timestamp: u64) -> Result<Option<FeedMessage>, Error> {
+        match self.entries.get(&timestamp).cloned() {
+            Some(name) => self.read(&name).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// The snapshots taken within a range of POSIX times, in chronological order.
+    pub fn snapshots_between(
+        &mut self,
+        from: u64,
+        to: u64,
+    ) -> impl Iterator<Item = Result<(u64, FeedMessage), Error>> + '_ {
+        let entries: Vec<(u64, String)> = self
+            .entries
+            .range(from..=to)
+            .map(|(timestamp, name)| (*timestamp, name.clone()))
+            .collect();
+        entries
+            .into_iter()
+            .map(move |(timestamp, name)| Ok((timestamp, self.read(&name)?)))
+    }
+
+    /// Every snapshot, in chronological order.
+    pub fn snapshots(&mut self) -> impl Iterator<Item = Result<(u64, FeedMessage), Error>> + '_ {
+        self.snapshots_between(0, u64::MAX)
+    }
+
+    fn read(&mut self, name: &str) -> Result<FeedMessage, Error> {
+        let mut bytes = Vec::new();
+        self.archive.by_name(name)?.read_to_end(&mut bytes)?;
+        FeedMessage::from_bytes(&bytes)
+    }
+}
+
+impl SnapshotArchive<File> {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::new(File::open(path)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::realtime::FeedHeader;
+    use std::io::Cursor;
+
+    fn feed(timestamp: u64) -> FeedMessage {
+        FeedMessage {
+            header: FeedHeader {
+                gtfs_realtime_version: "2.0".to_owned(),
+                timestamp: Some(timestamp),
+                ..Default::default()
+            },
+            entity: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn record_and_replay() {
+        let mut recorder = SnapshotRecorder::new(Cursor::new(Vec::new()));
+        assert!(recorder.record(&feed(1709539260)).unwrap());
+        assert!(recorder.record(&feed(1709539200)).unwrap());
+        assert!(!recorder.record(&feed(1709539260)).unwrap());
+
+        let mut archive = SnapshotArchive::new(recorder.finish().unwrap()).unwrap();
+        assert_eq!(archive.timestamps(), [1709539200, 1709539260]);
+        assert_eq!(
+            archive.snapshot(1709539260).unwrap(),
+            Some(feed(1709539260))
+        );
+        assert_eq!(archive.snapshot(1709539230).unwrap(), None);
+
+        let replayed: Vec<u64> = archive
+            .snapshots()
+            .map(|snapshot| snapshot.unwrap().1.header.timestamp())
+            .collect();
+        assert_eq!(replayed, [1709539200, 1709539260]);
+        assert_eq!(archive.snapshots_between(1709539230, u64::MAX).count(), 1);
+    }
+
+    #[test]
+    fn append() {
+        let path = std::env::temp_dir().join(format!("snapshots-{}.zip", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut recorder = SnapshotRecorder::open(&path).unwrap();
+        recorder.record(&feed(1709539200)).unwrap();
+        recorder.finish().unwrap();
+
+        let mut recorder = SnapshotRecorder::open(&path).unwrap();
+        assert!(!recorder.record(&feed(1709539200)).unwrap());
+        assert!(recorder.record(&feed(1709539230)).unwrap());
+        recorder.checkpoint().unwrap();
+
+        // Readable without finishing
+        let archive = SnapshotArchive::open(&path).unwrap();
+        assert_eq!(archive.timestamps(), [1709539200, 1709539230]);
+
+        assert!(!recorder.record(&feed(1709539230)).unwrap());
+        assert!(recorder.record(&feed(1709539260)).unwrap());
+        recorder.finish().unwrap();
+
+        let archive = SnapshotArchive::open(&path).unwrap();
+        assert_eq!(archive.timestamps(), [1709539200, 1709539230, 1709539260]);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn checkpoint_new_archive() {
+        let path = std::env::temp_dir().join(format!("checkpoint-{}.zip", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut recorder = SnapshotRecorder::open(&path).unwrap();
+        recorder.record(&feed(1709539200)).unwrap();
+        recorder.checkpoint().unwrap();
+        let archive = SnapshotArchive::open(&path).unwrap();
+        assert_eq!(archive.timestamps(), [1709539200]);
+
+        recorder.record(&feed(1709539230)).unwrap();
+        recorder.finish().unwrap();
+        let archive = SnapshotArchive::open(&path).unwrap();
+        assert_eq!(archive.timestamps(), [1709539200, 1709539230]);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
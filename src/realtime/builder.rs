@@ -0,0 +1,505 @@
+//! Building of GTFS-Realtime feeds referencing a static feed.
+
+use std::collections::HashSet;
+
+use super::{
+    feed_header::Incrementality,
+    matching::VehicleMatch,
+    trip_descriptor::ScheduleRelationship,
+    trip_update::{StopTimeEvent, StopTimeUpdate},
+    vehicle_position::VehicleStopStatus,
+    Alert, FeedEntity, FeedHeader, FeedMessage, TripDescriptor, TripUpdate, VehiclePosition,
+};
+use crate::{error::Error, gtfs_serde::format_time, GtfsReader};
+
+/// Version of the GTFS-Realtime specification the feeds are built for.
+const GTFS_REALTIME_VERSION: &str = "2.0";
+
+/// Ids of the static feed which realtime entities may reference.
+#[derive(Debug, Default)]
+pub struct StaticIds {
+    pub agencies: HashSet<String>,
+    pub routes: HashSet<String>,
+    pub trips: HashSet<String>,
+    pub stops: HashSet<String>,
+}
+
+impl StaticIds {
+    pub fn from_reader(gtfs: &mut GtfsReader) -> Result<StaticIds, Error> {
+        Ok(StaticIds {
+            agencies: gtfs
+                .agencies()?
+                .into_iter()
+                .filter_map(|agency| agency.id)
+                .collect(),
+            routes: gtfs.routes()?.into_iter().map(|route| route.id).collect(),
+            trips: gtfs.raw_trips()?.into_iter().map(|trip| trip.id).collect(),
+            stops: gtfs.stops()?.into_iter().map(|stop| stop.id).collect(),
+        })
+    }
+}
+
+/// Builds a feed whose entities only reference ids of the static feed.
+///
+/// ```
+/// use gtfs_parser::{realtime::{builder::{FeedBuilder, StaticIds}, TripDescriptor, TripUpdate}, GtfsReader};
+///
+/// let mut gtfs = GtfsReader::from_path("./resources/zips/gtfs.zip").unwrap();
+/// let ids = StaticIds::from_reader(&mut gtfs).unwrap();
+///
+/// let mut builder = FeedBuilder::new(&ids).timestamp(1151935200);
+/// let update = TripUpdate {
+///     trip: TripDescriptor { trip_id: Some("trip1".to_owned()), ..Default::default() },
+///     delay: Some(60),
+///     ..Default::default()
+/// };
+/// builder.trip_update("trip1", update).unwrap();
+///
+/// let unknown = TripUpdate {
+///     trip: TripDescriptor { trip_id: Some("unknown".to_owned()), ..Default::default() },
+///     ..Default::default()
+/// };
+/// assert!(builder.trip_update("unknown", unknown).is_err());
+///
+/// let bytes = builder.build().to_bytes();
+/// ```
+pub struct FeedBuilder<'s> {
+    ids: &'s StaticIds,
+    header: FeedHeader,
+    entities: Vec<FeedEntity>,
+    entity_ids: HashSet<String>,
+}
+
+impl<'s> FeedBuilder<'s> {
+    pub fn new(ids: &'s StaticIds) -> Self {
+        Self {
+            ids,
+            header: FeedHeader {
+                gtfs_realtime_version: GTFS_REALTIME_VERSION.to_owned(),
+                incrementality: Some(Incrementality::FullDataset as i32),
+                ..Default::default()
+            },
+            entities: vec![],
+            entity_ids: HashSet::new(),
+        }
+    }
+
+    /// Moment when the content of the feed has been created, in POSIX time.
+    pub fn timestamp(mut self, timestamp: u64) -> Self {
+        self.header.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Whether the feed only holds the changes since the previous one, `FULL_DATASET` by default.
+    pub fn incrementality(mut self, incrementality: Incrementality) -> Self {
+        self.header.incrementality = Some(incrementality as i32);
+        self
+    }
+
+    pub fn trip_update(&mut self, id: &str, update: TripUpdate) -> Result<&mut Self, Error> {
+        self.check_trip_update(id, &update)?;
+        self.push(FeedEntity {
+            id: id.to_owned(),
+            trip_update: Some(update),
+            ..Default::default()
+        })
+    }
+
+    pub fn vehicle_position(
+        &mut self,
+        id: &str,
+        vehicle: VehiclePosition,
+    ) -> Result<&mut Self, Error> {
+        self.check_vehicle_position(id, &vehicle)?;
+        self.push(FeedEntity {
+            id: id.to_owned(),
+            vehicle: Some(vehicle),
+            ..Default::default()
+        })
+    }
+
+    pub fn alert(&mut self, id: &str, alert: Alert) -> Result<&mut Self, Error> {
+        let check = self.check(id);
+        for selector in &alert.informed_entity {
+            check.reference(
+                "agency_id",
+                &selector.agency_id,
+                &self.ids.agencies,
+                "agency.txt",
+            )?;
+            check.reference(
+                "route_id",
+                &selector.route_id,
+                &self.ids.routes,
+                "routes.txt",
+            )?;
+            check.stop(&selector.stop_id)?;
+            if let Some(trip) = &selector.trip {
+                check.trip(trip)?;
+            }
+        }
+
+        self.push(FeedEntity {
+            id: id.to_owned(),
+            alert: Some(alert),
+            ..Default::default()
+        })
+    }
+
+    /// Deletes an entity of a previous feed, in differential feeds.
+    pub fn deleted(&mut self, id: &str) -> Result<&mut Self, Error> {
+        self.push(FeedEntity {
+            id: id.to_owned(),
+            is_deleted: Some(true),
+            ..Default::default()
+        })
+    }
+
+    /// Adds the position of a vehicle which has been matched to a trip, along with a
+    /// TripUpdate carrying its schedule deviation from its next stop on.
+    ///
+    /// The entities are identified by the id of the vehicle, or else by the id of the trip.
+    pub fn matched_vehicle(
+        &mut self,
+        vehicle: &VehiclePosition,
+        matched: &VehicleMatch,
+    ) -> Result<&mut Self, Error> {
+        let id = vehicle
+            .vehicle
+            .as_ref()
+            .and_then(|descriptor| descriptor.id.clone())
+            .unwrap_or_else(|| matched.trip_id.clone());
+        let trip = TripDescriptor {
+            trip_id: Some(matched.trip_id.clone()),
+            route_id: Some(matched.route_id.clone()),
+            schedule_relationship: Some(ScheduleRelationship::Scheduled as i32),
+            start_time: matched.start_time.map(format_time),
+            start_date: vehicle
+                .trip
+                .as_ref()
+                .and_then(|trip| trip.start_date.clone()),
+            ..Default::default()
+        };
+
+        let mut position = vehicle.clone();
+        position.trip = Some(trip.clone());
+        if let Some(next_stop) = &matched.next_stop {
+            position.current_stop_sequence = Some(u32::from(next_stop.stop_sequence));
+            position.stop_id = Some(next_stop.stop_id.clone());
+            position.current_status = Some(VehicleStopStatus::InTransitTo as i32);
+        }
+        let position_id = format!("{}-position", id);
+        self.check_vehicle_position(&position_id, &position)?;
+
+        let deviation = match matched.deviation {
+            Some(deviation) => deviation as i32,
+            None => {
+                return self.push(FeedEntity {
+                    id: position_id,
+                    vehicle: Some(position),
+                    ..Default::default()
+                })
+            }
+        };
+        let update = TripUpdate {
+            trip,
+            vehicle: vehicle.vehicle.clone(),
+            stop_time_update: matched
+                .next_stop
+                .iter()
+                .map(|next_stop| StopTimeUpdate {
+                    stop_sequence: Some(u32::from(next_stop.stop_sequence)),
+                    stop_id: Some(next_stop.stop_id.clone()),
+                    arrival: Some(StopTimeEvent {
+                        delay: Some(deviation),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                })
+                .collect(),
+            timestamp: vehicle.timestamp,
+            delay: Some(deviation),
+            ..Default::default()
+        };
+        let update_id = format!("{}-update", id);
+        self.check_trip_update(&update_id, &update)?;
+
+        // Both entities are checked first not to add only one of them
+        self.push(FeedEntity {
+            id: position_id,
+            vehicle: Some(position),
+            ..Default::default()
+        })?;
+        self.push(FeedEntity {
+            id: update_id,
+            trip_update: Some(update),
+            ..Default::default()
+        })
+    }
+
+    pub fn build(&self) -> FeedMessage {
+        FeedMessage {
+            header: self.header.clone(),
+            entity: self.entities.clone(),
+        }
+    }
+
+    fn check<'a>(&'a self, entity: &'a str) -> References<'a> {
+        References {
+            ids: self.ids,
+            entity,
+        }
+    }
+
+    fn check_trip_update(&self, id: &str, update: &TripUpdate) -> Result<(), Error> {
+        self.check_unique(id)?;
+        let check = self.check(id);
+        check.trip(&update.trip)?;
+        for stop_time_update in &update.stop_time_update {
+            check.stop(&stop_time_update.stop_id)?;
+            let properties = stop_time_update.stop_time_properties.as_ref();
+            check.stop(&properties.and_then(|p| p.assigned_stop_id.clone()))?;
+        }
+        Ok(())
+    }
+
+    fn check_vehicle_position(&self, id: &str, vehicle: &VehiclePosition) -> Result<(), Error> {
+        self.check_unique(id)?;
+        let check = self.check(id);
+        if let Some(trip) = &vehicle.trip {
+            check.trip(trip)?;
+        }
+        check.stop(&vehicle.stop_id)
+    }
+
+    fn check_unique(&self, id: &str) -> Result<(), Error> {
+        if self.entity_ids.contains(id) {
+            return Err(Error::DuplicateEntity(id.to_owned()));
+        }
+        Ok(())
+    }
+
+    fn push(&mut self, entity: FeedEntity) -> Result<&mut Self, Error> {
+        self.check_unique(&entity.id)?;
+        self.entity_ids.insert(entity.id.clone());
+        self.entities.push(entity);
+        Ok(self)
+    }
+}
+
+/// Checks the references of an entity to the static feed.
+struct References<'a> {
+    ids: &'a StaticIds,
+    entity: &'a str,
+}
+
+impl References<'_> {
+    fn reference(
+        &self,
+        field: &str,
+        id: &Option<String>,
+        known: &HashSet<String>,
+        parent_filename: &str,
+    ) -> Result<(), Error> {
+        match id {
+            Some(id) if !known.contains(id) => Err(Error::UnknownRealtimeReference {
+                entity: self.entity.to_owned(),
+                field: field.to_owned(),
+                id: id.to_owned(),
+                parent_filename: parent_filename.to_owned(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    fn stop(&self, stop_id: &Option<String>) -> Result<(), Error> {
+        self.reference("stop_id", stop_id, &self.ids.stops, "stops.txt")
+    }
+
+    /// Trips which are not part of the static schedule may have unknown ids.
+    fn trip(&self, trip: &TripDescriptor) -> Result<(), Error> {
+        match trip.schedule_relationship() {
+            ScheduleRelationship::Added | ScheduleRelationship::New => {}
+            _ => self.reference("trip_id", &trip.trip_id, &self.ids.trips, "trips.txt")?,
+        }
+        self.reference("route_id", &trip.route_id, &self.ids.routes, "routes.txt")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::realtime::{
+        alert::Effect, matching::StopProgress, trip_update::stop_time_update, EntitySelector,
+        Position, VehicleDescriptor,
+    };
+
+    fn ids() -> StaticIds {
+        let set = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect();
+        StaticIds {
+            agencies: set(&["AG"]),
+            routes: set(&["R1"]),
+            trips: set(&["T1", "T2"]),
+            stops: set(&["A", "B", "C"]),
+        }
+    }
+
+    fn trip(trip_id: &str, relationship: ScheduleRelationship) -> TripDescriptor {
+        TripDescriptor {
+            trip_id: Some(trip_id.to_owned()),
+            schedule_relationship: Some(relationship as i32),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn build_feed() {
+        let ids = ids();
+        let mut builder = FeedBuilder::new(&ids).timestamp(1709539200);
+        builder
+            .trip_update(
+                "T1",
+                TripUpdate {
+                    trip: trip("T1", ScheduleRelationship::Scheduled),
+                    stop_time_update: vec![StopTimeUpdate {
+                        stop_id: Some("B".to_owned()),
+                        schedule_relationship: Some(
+                            stop_time_update::ScheduleRelationship::Skipped as i32,
+                        ),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+            .alert(
+                "detour",
+                Alert {
+                    informed_entity: vec![EntitySelector {
+                        route_id: Some("R1".to_owned()),
+                        ..Default::default()
+                    }],
+                    effect: Some(Effect::Detour as i32),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let feed = builder.build();
+
+        assert_eq!(FeedMessage::from_bytes(&feed.to_bytes()).unwrap(), feed);
+
+        let json: serde_json::Value = serde_json::from_str(&feed.to_json().unwrap()).unwrap();
+        assert_eq!(json["header"]["incrementality"], "FULL_DATASET");
+        assert_eq!(json["header"]["timestamp"], 1709539200);
+        let update = &json["entity"][0]["trip_update"];
+        assert_eq!(update["trip"]["trip_id"], "T1");
+        assert_eq!(
+            update["stop_time_update"][0]["schedule_relationship"],
+            "SKIPPED"
+        );
+        assert!(update.get("delay").is_none());
+        assert_eq!(json["entity"][1]["alert"]["effect"], "DETOUR");
+    }
+
+    #[test]
+    fn reference_checks() {
+        let ids = ids();
+        let mut builder = FeedBuilder::new(&ids);
+
+        let unknown_stop = TripUpdate {
+            trip: trip("T1", ScheduleRelationship::Scheduled),
+            stop_time_update: vec![StopTimeUpdate {
+                stop_id: Some("Z".to_owned()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert_eq!(
+            builder
+                .trip_update("1", unknown_stop)
+                .err()
+                .unwrap()
+                .to_string(),
+            "Unknown stop_id 'Z' in realtime entity '1', it is not defined in 'stops.txt'"
+        );
+
+        let added = TripUpdate {
+            trip: trip("X1", ScheduleRelationship::Added),
+            ..Default::default()
+        };
+        builder.trip_update("2", added.clone()).unwrap();
+        assert!(matches!(
+            builder.trip_update("2", added),
+            Err(Error::DuplicateEntity(_))
+        ));
+
+        let alert = Alert {
+            informed_entity: vec![EntitySelector {
+                agency_id: Some("XX".to_owned()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert!(builder.alert("3", alert).is_err());
+        assert_eq!(builder.build().entity.len(), 1);
+    }
+
+    #[test]
+    fn matched_vehicle() {
+        let ids = ids();
+        let mut builder = FeedBuilder::new(&ids);
+        let ping = VehiclePosition {
+            vehicle: Some(VehicleDescriptor {
+                id: Some("bus-1".to_owned()),
+                ..Default::default()
+            }),
+            position: Some(Position {
+                latitude: 0.0,
+                longitude: 0.005,
+                ..Default::default()
+            }),
+            timestamp: Some(1709539200),
+            ..Default::default()
+        };
+        let stop = |stop_id: &str, stop_sequence| StopProgress {
+            stop_id: stop_id.to_owned(),
+            stop_sequence,
+            distance: 0.0,
+        };
+        let matched = VehicleMatch {
+            trip_id: "T1".to_owned(),
+            route_id: "R1".to_owned(),
+            start_time: Some(8 * 3600),
+            inferred: true,
+            distance: 556.0,
+            offset: 0.0,
+            previous_stop: Some(stop("A", 1)),
+            next_stop: Some(stop("B", 2)),
+            deviation: Some(120),
+        };
+        builder.matched_vehicle(&ping, &matched).unwrap();
+        let feed = builder.build();
+
+        let position = feed.vehicle_positions().next().unwrap();
+        assert_eq!(feed.entity[0].id, "bus-1-position");
+        assert_eq!(position.trip.as_ref().unwrap().trip_id(), "T1");
+        assert_eq!(position.trip.as_ref().unwrap().start_time(), "08:00:00");
+        assert_eq!(position.stop_id(), "B");
+
+        let update = feed.trip_updates().next().unwrap();
+        assert_eq!(update.delay, Some(120));
+        assert_eq!(
+            update.stop_time_update[0].arrival.as_ref().unwrap().delay,
+            Some(120)
+        );
+
+        // Neither entity is added when one of them can't be
+        let mut builder = FeedBuilder::new(&ids);
+        builder.deleted("bus-1-update").unwrap();
+        assert!(matches!(
+            builder.matched_vehicle(&ping, &matched),
+            Err(Error::DuplicateEntity(_))
+        ));
+        assert_eq!(builder.build().entity.len(), 1);
+    }
+}
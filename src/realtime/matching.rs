@@ -0,0 +1,478 @@
+//! Matching of GTFS-Realtime vehicle positions to the trips of the static schedule.
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::{NaiveDate, TimeZone};
+
+use super::{vehicle_position::VehicleStopStatus, VehiclePosition};
+use crate::{
+    geometry::{Point, Polyline, TripPath},
+    gtfs_serde::parse_gtfs_time,
+    structures::{calendar::service_day_start, frequencies::Frequency, shapes::Shape, trips::Trip},
+};
+
+/// Distance from the path of a trip beyond which a vehicle is not considered to be running it.
+const DEFAULT_MAX_OFFSET: f64 = 200.0;
+/// Margins around the scheduled run of a trip within which a vehicle may be running it,
+/// in seconds: vehicles may show up early at their first stop and run late.
+const EARLY_MARGIN: i64 = 15 * 60;
+const LATE_MARGIN: i64 = 60 * 60;
+/// Distance, in meters, deemed as bad as a second of schedule deviation when inferring trips.
+const METERS_PER_SECOND: f64 = 5.0;
+
+/// A stop of the matched trip.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StopProgress {
+    pub stop_id: String,
+    pub stop_sequence: u16,
+    /// Distance of the stop along the path of the trip, in meters.
+    pub distance: f64,
+}
+
+/// A vehicle position matched to a trip.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VehicleMatch {
+    pub trip_id: String,
+    pub route_id: String,
+    /// Start time of the matched run of a frequency-based trip, in seconds since the start
+    /// of the service day.
+    pub start_time: Option<u64>,
+    /// Whether the trip was inferred from the position, the vehicle not reporting a known trip.
+    pub inferred: bool,
+    /// Distance travelled along the path of the trip, in meters.
+    pub distance: f64,
+    /// Distance between the vehicle and the path of the trip, in meters.
+    pub offset: f64,
+    /// Last stop reached, none before the first one.
+    pub previous_stop: Option<StopProgress>,
+    /// Next stop to be reached, none past the last one.
+    pub next_stop: Option<StopProgress>,
+    /// Seconds behind schedule, negative when ahead, if the position has a timestamp.
+    pub deviation: Option<i64>,
+}
+
+/// Matches vehicle positions to the trips running on a service date.
+///
+/// Positions are matched to the trip they report when it is known. Otherwise the trip is
+/// inferred among the trips of the reported route and direction, if any, which run at the
+/// time of the position, as the one whose path is the closest to the vehicle and whose
+/// schedule fits best. Frequency-based trips are matched to the run of frequencies.txt the
+/// vehicle reports with its start time, or else to the one whose schedule fits best.
+///
+/// ```
+/// use chrono::{NaiveDate, Utc};
+/// use gtfs_parser::{realtime::{matching::VehicleMatcher, FeedMessage}, GtfsReader};
+///
+/// let mut gtfs = GtfsReader::from_path("./resources/zips/gtfs.zip").unwrap();
+/// let trips = gtfs.trips().unwrap();
+/// let date = NaiveDate::from_ymd_opt(2006, 7, 3).unwrap();
+/// let services = ["service1".to_owned()].iter().cloned().collect();
+///
+/// let (frequencies, shapes) = (gtfs.frequencies().unwrap(), gtfs.shapes().unwrap());
+/// let matcher = VehicleMatcher::new(&trips, &frequencies, &shapes, &services, date, &Utc);
+/// let feed = FeedMessage::from_path("./resources/realtime/vehicle_positions.pb").unwrap();
+/// let matched = matcher.match_position(feed.vehicle_positions().next().unwrap()).unwrap();
+///
+/// assert_eq!(matched.trip_id, "trip1");
+/// ```
+pub struct VehicleMatcher<'t> {
+    paths: HashMap<&'t str, (&'t Trip, TripPath)>,
+    /// Start times of the runs of frequency-based trips
+    start_times: HashMap<&'t str, Vec<u64>>,
+    day_start: i64,
+    max_offset: f64,
+}
+
+/// A run of a trip: its start time if frequency-based, and the number of seconds it runs
+/// after the schedule of its stop times.
+type Run = (Option<u64>, i64);
+
+impl<'t> VehicleMatcher<'t> {
+    /// Matcher for the trips of the running `services` of `date`, in the time zone of the agency.
+    pub fn new<Tz: TimeZone>(
+        trips: &'t HashMap<String, Trip>,
+        frequencies: &'t [Frequency],
+        shapes: &[Shape],
+        services: &HashSet<String>,
+        date: NaiveDate,
+        timezone: &Tz,
+    ) -> Self {
+        let shapes = Polyline::from_shapes(shapes);
+        let paths = trips
+            .values()
+            .filter(|trip| services.contains(&trip.service_id))
+            .filter_map(|trip| Some((trip.id.as_str(), (trip, TripPath::new(trip, &shapes)?))))
+            .collect();
+        let mut start_times: HashMap<&str, Vec<u64>> = HashMap::new();
+        for frequency in frequencies {
+            start_times
+                .entry(frequency.trip_id.as_str())
+                .or_default()
+                .extend(frequency.start_times());
+        }
+
+        Self {
+            paths,
+            start_times,
+            day_start: service_day_start(date, timezone),
+            max_offset: DEFAULT_MAX_OFFSET,
+        }
+    }
+
+    /// Distance from the path of a trip, in meters, beyond which vehicles without a known trip
+    /// are not matched to it. Defaults to 200 meters.
+    pub fn max_offset(mut self, meters: f64) -> Self {
+        self.max_offset = meters;
+        self
+    }
+
+    pub fn match_position(&self, vehicle: &VehiclePosition) -> Option<VehicleMatch> {
+        let position = vehicle.position.as_ref()?;
+        let point = Point::new(f64::from(position.latitude), f64::from(position.longitude));
+        let timestamp = vehicle.timestamp.map(|t| t as i64);
+        let descriptor = vehicle.trip.as_ref();
+
+        if let Some((trip, path)) = descriptor
+            .and_then(|trip| trip.trip_id.as_ref())
+            .and_then(|trip_id| self.paths.get(trip_id.as_str()))
+        {
+            let start_time = descriptor
+                .and_then(|trip| trip.start_time.as_deref())
+                .and_then(|start_time| parse_gtfs_time(start_time).ok());
+            let runs = self.runs(trip);
+            let reported: Vec<Run> = runs
+                .iter()
+                .copied()
+                .filter(|(start, _)| start_time.is_some() && *start == start_time)
+                .collect();
+            let runs = if reported.is_empty() { runs } else { reported };
+            return runs
+                .into_iter()
+                .map(|run| self.locate(trip, path, run, &point, timestamp, Some(vehicle), false))
+                .min_by_key(|matched| matched.deviation.map(i64::abs));
+        }
+
+        let route_id = descriptor.and_then(|trip| trip.route_id.as_ref());
+        let direction_id = descriptor.and_then(|trip| trip.direction_id);
+        self.paths
+            .values()
+            .filter(|(trip, _)| match route_id {
+                Some(route_id) => *route_id == trip.route_id,
+                None => true,
+            })
+            .filter(|(trip, _)| match direction_id {
+                Some(direction_id) => trip.direction_id.map(|d| d as u32) == Some(direction_id),
+                None => true,
+            })
+            .flat_map(|(trip, path)| {
+                self.runs(trip)
+                    .into_iter()
+                    .filter(move |run| match timestamp {
+                        Some(timestamp) => self.runs_at(trip, *run, timestamp),
+                        None => true,
+                    })
+                    .map(move |run| (trip, path, run))
+            })
+            .map(|(trip, path, run)| self.locate(trip, path, run, &point, timestamp, None, true))
+            .filter(|matched| matched.offset <= self.max_offset)
+            .min_by(|a, b| score(a).total_cmp(&score(b)))
+    }
+
+    /// The runs of a trip: one per start time of a frequency-based trip, or else the one of
+    /// its stop times.
+    fn runs(&self, trip: &Trip) -> Vec<Run> {
+        let first = trip
+            .stop_times
+            .iter()
+            .find_map(|stop_time| stop_time.departure_time.or(stop_time.arrival_time));
+        match (self.start_times.get(trip.id.as_str()), first) {
+            (Some(start_times), Some(first)) => start_times
+                .iter()
+                .map(|start| (Some(*start), *start as i64 - first as i64))
+                .collect(),
+            _ => vec![(None, 0)],
+        }
+    }
+
+    /// Whether a run of the trip may be running at the given POSIX time.
+    fn runs_at(&self, trip: &Trip, (_, shift): Run, timestamp: i64) -> bool {
+        let times = trip
+            .stop_times
+            .iter()
+            .flat_map(|stop_time| stop_time.arrival_time.or(stop_time.departure_time))
+            .map(|time| time as i64 + shift + self.day_start);
+        match times.clone().min().zip(times.max()) {
+            Some((first, last)) => {
+                first - EARLY_MARGIN <= timestamp && timestamp <= last + LATE_MARGIN
+            }
+            None => false,
+        }
+    }
+
+    /// Locates a vehicle along the path of a run of a trip. The stop the vehicle reports, if
+    /// any, restricts its location to the part of the path around that stop.
+    #[allow(clippy::too_many_arguments)]
+    fn locate(
+        &self,
+        trip: &Trip,
+        path: &TripPath,
+        (start_time, shift): Run,
+        point: &Point,
+        timestamp: Option<i64>,
+        vehicle: Option<&VehiclePosition>,
+        inferred: bool,
+    ) -> VehicleMatch {
+        let reported_stop = vehicle.and_then(|vehicle| {
+            let index = trip.stop_times.iter().position(|stop_time| {
+                match (&vehicle.current_stop_sequence, &vehicle.stop_id) {
+                    (Some(sequence), _) => u32::from(stop_time.stop_sequence) == *sequence,
+                    (None, Some(stop_id)) => stop_time.stop.id == *stop_id,
+                    (None, None) => false,
+                }
+            })?;
+            Some((index, vehicle.current_status()))
+        });
+
+        let projection = match reported_stop {
+            Some((index, VehicleStopStatus::StoppedAt)) => {
+                let distance = path.stop_distances[index];
+                path.line
+                    .point_at(distance)
+                    .map(|stop| (distance, point.distance(&stop)))
+            }
+            Some((index, _)) => {
+                let from = index
+                    .checked_sub(1)
+                    .map_or(0.0, |previous| path.stop_distances[previous]);
+                path.line.project_after(point, from).map(|projection| {
+                    (
+                        projection.distance.min(path.stop_distances[index]),
+                        projection.offset,
+                    )
+                })
+            }
+            None => path
+                .line
+                .project(point)
+                .map(|projection| (projection.distance, projection.offset)),
+        };
+        let (distance, offset) = projection.unwrap_or((0.0, f64::INFINITY));
+
+        let stop = |index: usize| StopProgress {
+            stop_id: trip.stop_times[index].stop.id.clone(),
+            stop_sequence: trip.stop_times[index].stop_sequence,
+            distance: path.stop_distances[index],
+        };
+        let next = match reported_stop {
+            Some((index, VehicleStopStatus::StoppedAt)) => Some(index + 1),
+            Some((index, _)) => Some(index),
+            None => path
+                .stop_distances
+                .iter()
+                .position(|stop_distance| *stop_distance > distance),
+        }
+        .filter(|index| *index < trip.stop_times.len());
+        let previous = match next {
+            Some(next) => next.checked_sub(1),
+            None => trip.stop_times.len().checked_sub(1),
+        };
+
+        let deviation = timestamp.and_then(|timestamp| {
+            let scheduled = scheduled_at(trip, path, distance)?;
+            Some(timestamp - self.day_start - shift - scheduled.round() as i64)
+        });
+
+        VehicleMatch {
+            trip_id: trip.id.clone(),
+            route_id: trip.route_id.clone(),
+            start_time,
+            inferred,
+            distance,
+            offset,
+            previous_stop: previous.map(stop),
+            next_stop: next.map(stop),
+            deviation,
+        }
+    }
+}
+
+/// How poorly a position matches an inferred trip.
+fn score(matched: &VehicleMatch) -> f64 {
+    let deviation = matched.deviation.unwrap_or_default().abs() as f64;
+    matched.offset + deviation * METERS_PER_SECOND
+}
+
+/// Scheduled time at which a trip is at the given distance along its path, in seconds since
+/// the start of the service day, interpolated between the times of its stops.
+fn scheduled_at(trip: &Trip, path: &TripPath, distance: f64) -> Option<f64> {
+    let times = trip
+        .stop_times
+        .iter()
+        .zip(&path.stop_distances)
+        .flat_map(|(stop_time, stop_distance)| {
+            vec![stop_time.arrival_time, stop_time.departure_time]
+                .into_iter()
+                .flatten()
+                .map(move |time| (*stop_distance, time as f64))
+        })
+        .collect::<Vec<_>>();
+
+    let index = times
+        .iter()
+        .rposition(|(stop_distance, _)| *stop_distance <= distance)
+        .unwrap_or(0);
+    let (from_distance, from_time) = *times.get(index)?;
+    Some(match times.get(index + 1) {
+        Some((to_distance, to_time)) if *to_distance > from_distance => {
+            let fraction = (distance - from_distance) / (to_distance - from_distance);
+            from_time + fraction.max(0.0) * (to_time - from_time)
+        }
+        _ => from_time,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        realtime::{Position, TripDescriptor},
+        test::reader_from_files,
+    };
+    use chrono::Utc;
+
+    fn date() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, 4).unwrap()
+    }
+
+    fn at(hours: i64, minutes: i64) -> u64 {
+        (service_day_start(date(), &Utc) + hours * 3600 + minutes * 60) as u64
+    }
+
+    /// A line going east along the equator, with stops A, B and C about 1.1km apart.
+    fn feed() -> (HashMap<String, Trip>, Vec<Shape>) {
+        let mut gtfs = reader_from_files(&[
+            (
+                "stops.txt",
+                "stop_id,stop_name,stop_lat,stop_lon\nA,A,0,0\nB,B,0,0.01\nC,C,0,0.02\n",
+            ),
+            (
+                "shapes.txt",
+                "shape_id,shape_pt_lat,shape_pt_lon,shape_pt_sequence\n\
+                 S1,0,0,1\nS1,0.0001,0.015,2\nS1,0,0.02,3\n",
+            ),
+            (
+                "trips.txt",
+                "route_id,service_id,trip_id,direction_id,shape_id\n\
+                 R1,WD,T1,0,S1\nR1,WD,T2,0,S1\nR2,WD,T3,1,\n",
+            ),
+            (
+                "stop_times.txt",
+                "trip_id,arrival_time,departure_time,stop_id,stop_sequence\n\
+                 T1,08:00:00,08:00:00,A,1\nT1,08:10:00,08:10:00,B,2\nT1,08:20:00,08:20:00,C,3\n\
+                 T2,09:00:00,09:00:00,A,1\nT2,09:10:00,09:10:00,B,2\nT2,09:20:00,09:20:00,C,3\n\
+                 T3,08:00:00,08:00:00,C,1\nT3,08:10:00,08:10:00,B,2\nT3,08:20:00,08:20:00,A,3\n",
+            ),
+        ]);
+        (gtfs.trips().unwrap(), gtfs.shapes().unwrap())
+    }
+
+    fn vehicle(
+        trip_id: Option<&str>,
+        latitude: f32,
+        longitude: f32,
+        timestamp: u64,
+    ) -> VehiclePosition {
+        VehiclePosition {
+            trip: trip_id.map(|trip_id| TripDescriptor {
+                trip_id: Some(trip_id.to_owned()),
+                ..Default::default()
+            }),
+            position: Some(Position {
+                latitude,
+                longitude,
+                ..Default::default()
+            }),
+            timestamp: Some(timestamp),
+            ..Default::default()
+        }
+    }
+
+    fn services() -> HashSet<String> {
+        ["WD".to_owned()].iter().cloned().collect()
+    }
+
+    #[test]
+    fn reported_trip() {
+        let (trips, shapes) = feed();
+        let matcher = VehicleMatcher::new(&trips, &[], &shapes, &services(), date(), &Utc);
+
+        let matched = matcher
+            .match_position(&vehicle(Some("T1"), 0.0, 0.005, at(8, 7)))
+            .unwrap();
+        assert!(!matched.inferred);
+        assert!((matched.distance - 556.0).abs() < 5.0);
+        assert_eq!(matched.previous_stop.unwrap().stop_id, "A");
+        assert_eq!(matched.next_stop.unwrap().stop_id, "B");
+        assert!((matched.deviation.unwrap() - 120).abs() <= 2);
+
+        let mut stopped = vehicle(Some("T1"), 0.0002, 0.0101, at(8, 9));
+        stopped.stop_id = Some("B".to_owned());
+        stopped.current_status = Some(VehicleStopStatus::StoppedAt as i32);
+        let matched = matcher.match_position(&stopped).unwrap();
+        assert_eq!(matched.previous_stop.unwrap().stop_id, "B");
+        assert_eq!(matched.next_stop.unwrap().stop_id, "C");
+        assert_eq!(matched.deviation, Some(-60));
+    }
+
+    #[test]
+    fn inferred_trip() {
+        let (trips, shapes) = feed();
+        let matcher = VehicleMatcher::new(&trips, &[], &shapes, &services(), date(), &Utc);
+
+        let matched = matcher
+            .match_position(&vehicle(None, 0.0, 0.015, at(8, 16)))
+            .unwrap();
+        assert!(matched.inferred);
+        assert_eq!(matched.trip_id, "T1");
+        assert!((matched.deviation.unwrap() - 60).abs() <= 2);
+
+        // Past the last stop
+        let matched = matcher
+            .match_position(&vehicle(None, 0.0, 0.0201, at(9, 21)))
+            .unwrap();
+        assert_eq!(matched.trip_id, "T2");
+        assert_eq!(matched.previous_stop.unwrap().stop_id, "C");
+        assert!(matched.next_stop.is_none());
+
+        assert!(matcher
+            .match_position(&vehicle(None, 0.1, 0.01, at(8, 16)))
+            .is_none());
+    }
+
+    #[test]
+    fn frequency_based_trip() {
+        let (trips, shapes) = feed();
+        let mut gtfs = reader_from_files(&[(
+            "frequencies.txt",
+            "trip_id,start_time,end_time,headway_secs\nT3,10:00:00,12:00:00,1800\n",
+        )]);
+        let frequencies = gtfs.frequencies().unwrap();
+        let matcher = VehicleMatcher::new(&trips, &frequencies, &shapes, &services(), date(), &Utc);
+
+        // Around B, going west, long after the 08:00 template of T3
+        let matched = matcher
+            .match_position(&vehicle(None, 0.0, 0.0101, at(11, 11)))
+            .unwrap();
+        assert_eq!(matched.trip_id, "T3");
+        assert_eq!(matched.start_time, Some(11 * 3600));
+        assert!((matched.deviation.unwrap() - 60).abs() <= 30);
+
+        // The run the vehicle reports
+        let mut reported = vehicle(Some("T3"), 0.0, 0.0101, at(11, 11));
+        reported.trip.as_mut().unwrap().start_time = Some("10:30:00".to_owned());
+        let matched = matcher.match_position(&reported).unwrap();
+        assert!(!matched.inferred);
+        assert_eq!(matched.start_time, Some(10 * 3600 + 1800));
+        assert!((matched.deviation.unwrap() - 1860).abs() <= 60);
+    }
+}
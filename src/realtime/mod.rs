@@ -0,0 +1,190 @@
+//! Decoding of GTFS-Realtime feeds (https://gtfs.org/realtime/reference/).
+//!
+//! A feed is decoded into a [FeedMessage], whose entities carry the TripUpdates,
+//! VehiclePositions and Alerts of the feed.
+//!
+//! ```
+//! use gtfs_parser::realtime::FeedMessage;
+//!
+//! let feed = FeedMessage::from_path("./resources/realtime/trip_updates.pb").unwrap();
+//! let update = feed.trip_updates().next().unwrap();
+//!
+//! assert_eq!(update.trip.trip_id(), "trip1");
+//! ```
+pub mod alerts;
+pub mod archive;
+pub mod builder;
+pub mod matching;
+pub mod performance;
+pub mod predictions;
+mod proto;
+
+pub use proto::*;
+
+use crate::error::Error;
+use prost::Message;
+use std::{fs, path::Path};
+
+impl FeedMessage {
+    pub fn from_bytes(bytes: &[u8]) -> Result<FeedMessage, Error> {
+        Ok(FeedMessage::decode(bytes)?)
+    }
+
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<FeedMessage, Error> {
+        let bytes = fs::read(path)?;
+        Self::from_bytes(&bytes)
+    }
+
+    #[cfg(feature = "read-url")]
+    pub fn from_url<U: reqwest::IntoUrl>(url: U) -> Result<FeedMessage, Error> {
+        let bytes = reqwest::blocking::get(url)?.error_for_status()?.bytes()?;
+        Self::from_bytes(&bytes)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.encode_to_vec()
+    }
+
+    /// JSON form of the feed, for debugging. Enumerations are written by name.
+    pub fn to_json(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// The entities which are not deleted.
+    fn entities(&self) -> impl Iterator<Item = &FeedEntity> {
+        self.entity.iter().filter(|entity| !entity.is_deleted())
+    }
+
+    pub fn trip_updates(&self) -> impl Iterator<Item = &TripUpdate> {
+        self.entities()
+            .filter_map(|entity| entity.trip_update.as_ref())
+    }
+
+    pub fn vehicle_positions(&self) -> impl Iterator<Item = &VehiclePosition> {
+        self.entities().filter_map(|entity| entity.vehicle.as_ref())
+    }
+
+    pub fn alerts(&self) -> impl Iterator<Item = &Alert> {
+        self.entities().filter_map(|entity| entity.alert.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn trip_updates() {
+        let feed = FeedMessage::from_path("./resources/realtime/trip_updates.pb").unwrap();
+        assert_eq!(feed.header.gtfs_realtime_version, "2.0");
+        assert_eq!(
+            feed.header.incrementality(),
+            feed_header::Incrementality::FullDataset
+        );
+
+        let updates = feed.trip_updates().collect::<Vec<_>>();
+        assert_eq!(updates.len(), 2);
+
+        let stop_time_updates = &updates[0].stop_time_update;
+        assert_eq!(stop_time_updates[0].stop_id(), "stop2");
+        assert_eq!(stop_time_updates[0].arrival.as_ref().unwrap().delay(), 120);
+        assert_eq!(
+            stop_time_updates[1].schedule_relationship(),
+            trip_update::stop_time_update::ScheduleRelationship::Skipped
+        );
+        assert_eq!(
+            updates[1].trip.schedule_relationship(),
+            trip_descriptor::ScheduleRelationship::Canceled
+        );
+    }
+
+    #[test]
+    fn vehicle_positions() {
+        let feed = FeedMessage::from_path("./resources/realtime/vehicle_positions.pb").unwrap();
+        let vehicles = feed.vehicle_positions().collect::<Vec<_>>();
+
+        assert_eq!(vehicles[0].trip.as_ref().unwrap().trip_id(), "trip1");
+        assert_eq!(
+            vehicles[0].current_status(),
+            vehicle_position::VehicleStopStatus::StoppedAt
+        );
+        assert_eq!(vehicles[0].position.as_ref().unwrap().bearing(), 90.0);
+        assert!(vehicles[1].trip.is_none());
+        assert_eq!(
+            vehicles[1].current_status(),
+            vehicle_position::VehicleStopStatus::InTransitTo
+        );
+    }
+
+    #[test]
+    fn alerts() {
+        let feed = FeedMessage::from_path("./resources/realtime/alerts.pb").unwrap();
+        let alert = feed.alerts().next().unwrap();
+
+        assert_eq!(alert.informed_entity.len(), 2);
+        assert_eq!(alert.effect(), alert::Effect::Detour);
+        assert_eq!(alert.severity_level(), alert::SeverityLevel::Warning);
+        let header = &alert.header_text.as_ref().unwrap().translation;
+        assert_eq!(header[1].language(), "fr");
+        assert_eq!(header[1].text, "Déviation de la ligne 17");
+    }
+
+    #[test]
+    fn nyct_extensions() {
+        let feed = FeedMessage::from_path("./resources/realtime/nyct.pb").unwrap();
+        let nyct_header = feed.header.nyct_feed_header.as_ref().unwrap();
+        assert_eq!(nyct_header.nyct_subway_version, "1.0");
+
+        let update = feed.trip_updates().next().unwrap();
+        let nyct_trip = update.trip.nyct_trip_descriptor.as_ref().unwrap();
+        assert_eq!(nyct_trip.train_id(), "01 1336 242/SFT");
+        assert_eq!(
+            nyct_trip.direction(),
+            nyct_trip_descriptor::Direction::South
+        );
+
+        let nyct_stop_time = update.stop_time_update[0]
+            .nyct_stop_time_update
+            .as_ref()
+            .unwrap();
+        assert_eq!(nyct_stop_time.actual_track(), "2");
+    }
+
+    #[test]
+    fn invalid_feed() {
+        assert!(matches!(
+            FeedMessage::from_bytes(b"not a protobuf"),
+            Err(Error::Decode(_))
+        ));
+    }
+
+    #[cfg(feature = "read-url")]
+    #[test]
+    fn from_url() {
+        use std::io::{BufRead, BufReader, Write};
+
+        let body = fs::read("./resources/realtime/alerts.pb").unwrap();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/alerts.pb", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            // Skip the request, up to the blank line ending its headers
+            let mut request = BufReader::new(&stream);
+            let mut line = String::new();
+            while request.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/x-protobuf\r\nContent-Length: {}\r\n\r\n",
+                body.len()
+            )
+            .unwrap();
+            stream.write_all(&body).unwrap();
+        });
+
+        let feed = FeedMessage::from_url(&url).unwrap();
+        server.join().unwrap();
+        assert_eq!(feed.alerts().count(), 1);
+    }
+}
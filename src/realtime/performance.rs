@@ -0,0 +1,551 @@
+//! On-time performance of a service date, from GTFS-Realtime snapshots joined with the
+//! static schedule.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    io::{Read, Seek},
+};
+
+use chrono::{NaiveDate, TimeZone};
+
+use super::{
+    archive::SnapshotArchive,
+    predictions::{Predictions, StopTimeStatus, TripStatus},
+    vehicle_position::VehicleStopStatus,
+    FeedMessage, VehiclePosition,
+};
+use crate::{
+    error::Error,
+    gtfs_serde::parse_gtfs_time,
+    structures::{calendar::service_day_start, frequencies::Frequency, trips::Trip},
+};
+
+/// Arrivals more than a minute early or more than 5 minutes late are not on time.
+const DEFAULT_EARLY: i64 = 60;
+const DEFAULT_LATE: i64 = 5 * 60;
+
+/// The observed arrival of a trip instance at a stop. Times are in POSIX time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObservedArrival {
+    pub trip_id: String,
+    pub route_id: String,
+    /// Start time of frequency-based trip instances, in seconds since the start of the service day.
+    pub start_time: Option<u64>,
+    pub stop_id: String,
+    pub stop_sequence: u32,
+    pub scheduled: i64,
+    pub observed: i64,
+}
+
+impl ObservedArrival {
+    /// Seconds behind schedule, negative when early.
+    pub fn delay(&self) -> i64 {
+        self.observed - self.scheduled
+    }
+}
+
+/// Counts of observed arrivals by punctuality.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct OnTimePerformance {
+    pub early: usize,
+    pub on_time: usize,
+    pub late: usize,
+}
+
+impl OnTimePerformance {
+    pub fn observed(&self) -> usize {
+        self.early + self.on_time + self.late
+    }
+
+    /// Share of the observed arrivals which are on time, none without observations.
+    pub fn on_time_ratio(&self) -> Option<f64> {
+        match self.observed() {
+            0 => None,
+            observed => Some(self.on_time as f64 / observed as f64),
+        }
+    }
+}
+
+/// Regularity of the observed headways between the arrivals of a route at a stop.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct HeadwayRegularity {
+    pub headways: usize,
+    /// Mean of the observed headways, in seconds.
+    pub mean: f64,
+    /// Standard deviation of the observed headways, in seconds.
+    pub std_dev: f64,
+    /// Mean of the scheduled headways between the same arrivals, in seconds.
+    pub scheduled_mean: f64,
+}
+
+impl HeadwayRegularity {
+    /// Standard deviation relative to the mean headway, 0 for perfectly regular service.
+    pub fn coefficient_of_variation(&self) -> f64 {
+        if self.mean > 0.0 {
+            self.std_dev / self.mean
+        } else {
+            0.0
+        }
+    }
+}
+
+/// A trip instance canceled in the snapshots.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Cancellation {
+    pub trip_id: String,
+    pub route_id: Option<String>,
+    pub start_time: Option<u64>,
+}
+
+/// On-time performance of a service date.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PerformanceReport {
+    /// Observed arrivals, by trip instance and stop sequence.
+    pub arrivals: Vec<ObservedArrival>,
+    pub by_route: BTreeMap<String, OnTimePerformance>,
+    pub by_stop: BTreeMap<String, OnTimePerformance>,
+    /// Performance by hour of the scheduled arrival, since the start of the service day.
+    pub by_hour: BTreeMap<i64, OnTimePerformance>,
+    pub cancellations: Vec<Cancellation>,
+    /// Headway regularity by route and stop ids.
+    pub headways: BTreeMap<(String, String), HeadwayRegularity>,
+}
+
+/// Identifies a stop of a trip instance.
+type StopKey = (String, Option<u64>, u32);
+
+/// Joins GTFS-Realtime snapshots of a service date with the static schedule.
+///
+/// Snapshots must be added in chronological order. The arrival at a stop is observed from
+/// the first snapshot taken after it, either as the time of the vehicle position stopped
+/// at that stop or as the arrival predicted by the TripUpdates, which is then in the past.
+/// A trip instance is canceled if its last update cancels it.
+///
+/// ```
+/// use chrono::{NaiveDate, Utc};
+/// use gtfs_parser::{realtime::{performance::PerformanceAnalysis, FeedMessage}, GtfsReader};
+///
+/// let mut gtfs = GtfsReader::from_path("./resources/zips/gtfs.zip").unwrap();
+/// let trips = gtfs.trips().unwrap();
+/// let date = NaiveDate::from_ymd_opt(2006, 7, 3).unwrap();
+///
+/// let mut analysis = PerformanceAnalysis::new(&trips, &[], date, &Utc);
+/// analysis.add(&FeedMessage::from_path("./resources/realtime/trip_updates.pb").unwrap());
+///
+/// let report = analysis.report();
+/// assert_eq!(report.cancellations[0].trip_id, "AWE1");
+/// ```
+pub struct PerformanceAnalysis<'t> {
+    predictions: Predictions<'t>,
+    frequency_trips: HashSet<&'t str>,
+    day_start: i64,
+    early: i64,
+    late: i64,
+    arrivals: BTreeMap<StopKey, ObservedArrival>,
+    cancellations: BTreeMap<(String, Option<u64>), Cancellation>,
+}
+
+impl<'t> PerformanceAnalysis<'t> {
+    /// Analysis of `date` in the given time zone, which is the one of the agency.
+    pub fn new<Tz: TimeZone>(
+        trips: &'t HashMap<String, Trip>,
+        frequencies: &'t [Frequency],
+        date: NaiveDate,
+        timezone: &Tz,
+    ) -> Self {
+        Self {
+            predictions: Predictions::new(trips, frequencies, date, timezone),
+            frequency_trips: frequencies.iter().map(|f| f.trip_id.as_str()).collect(),
+            day_start: service_day_start(date, timezone),
+            early: DEFAULT_EARLY,
+            late: DEFAULT_LATE,
+            arrivals: BTreeMap::new(),
+            cancellations: BTreeMap::new(),
+        }
+    }
+
+    /// Seconds an arrival may be early or late while still being on time.
+    /// Defaults to 1 minute early and 5 minutes late.
+    pub fn on_time_window(mut self, early: i64, late: i64) -> Self {
+        self.early = early;
+        self.late = late;
+        self
+    }
+
+    /// Adds a snapshot of TripUpdates and/or VehiclePositions.
+    pub fn add(&mut self, feed: &FeedMessage) {
+        for vehicle in feed.vehicle_positions() {
+            self.observe_vehicle(vehicle);
+        }
+
+        let has_updates = feed.trip_updates().next().is_some();
+        if !has_updates {
+            return;
+        }
+        self.predictions.apply(feed);
+        let timestamp = feed.header.timestamp.map(|timestamp| timestamp as i64);
+
+        for trip in self.predictions.updated_trips() {
+            let key = (trip.trip_id.clone(), trip.start_time);
+            if trip.status == TripStatus::Canceled {
+                self.cancellations.insert(
+                    key,
+                    Cancellation {
+                        trip_id: trip.trip_id,
+                        route_id: trip.route_id,
+                        start_time: trip.start_time,
+                    },
+                );
+                continue;
+            }
+            self.cancellations.remove(&key);
+
+            let (now, route_id) = match (timestamp, &trip.route_id) {
+                (Some(now), Some(route_id)) => (now, route_id),
+                _ => continue,
+            };
+            for stop_time in &trip.stop_times {
+                let observed = stop_time.arrival.or(stop_time.departure);
+                let scheduled = stop_time
+                    .scheduled_arrival
+                    .or(stop_time.scheduled_departure);
+                if let (StopTimeStatus::Scheduled, Some(observed), Some(scheduled)) =
+                    (stop_time.status, observed, scheduled)
+                {
+                    if observed <= now {
+                        self.arrivals
+                            .entry((
+                                trip.trip_id.clone(),
+                                trip.start_time,
+                                stop_time.stop_sequence,
+                            ))
+                            .or_insert_with(|| ObservedArrival {
+                                trip_id: trip.trip_id.clone(),
+                                route_id: route_id.clone(),
+                                start_time: trip.start_time,
+                                stop_id: stop_time.stop_id.clone(),
+                                stop_sequence: stop_time.stop_sequence,
+                                scheduled,
+                                observed,
+                            });
+                    }
+                }
+            }
+        }
+    }
+
+    /// Adds the snapshots of an archive, in chronological order.
+    pub fn replay<R: Read + Seek>(
+        &mut self,
+        archive: &mut SnapshotArchive<R>,
+    ) -> Result<(), Error> {
+        for snapshot in archive.snapshots() {
+            self.add(&snapshot?.1);
+        }
+        Ok(())
+    }
+
+    pub fn report(&self) -> PerformanceReport {
+        let mut report = PerformanceReport {
+            arrivals: self.arrivals.values().cloned().collect(),
+            cancellations: self.cancellations.values().cloned().collect(),
+            ..Default::default()
+        };
+
+        for arrival in &report.arrivals {
+            let delay = arrival.delay();
+            let hour = (arrival.scheduled - self.day_start).div_euclid(3600);
+            for performance in [
+                report.by_route.entry(arrival.route_id.clone()).or_default(),
+                report.by_stop.entry(arrival.stop_id.clone()).or_default(),
+                report.by_hour.entry(hour).or_default(),
+            ] {
+                if delay < -self.early {
+                    performance.early += 1;
+                } else if delay > self.late {
+                    performance.late += 1;
+                } else {
+                    performance.on_time += 1;
+                }
+            }
+        }
+
+        let mut by_route_stop: HashMap<(&str, &str), Vec<&ObservedArrival>> = HashMap::new();
+        for arrival in &report.arrivals {
+            by_route_stop
+                .entry((&arrival.route_id, &arrival.stop_id))
+                .or_default()
+                .push(arrival);
+        }
+        report.headways = by_route_stop
+            .into_iter()
+            .filter_map(|((route_id, stop_id), arrivals)| {
+                let regularity = regularity(arrivals)?;
+                Some(((route_id.to_owned(), stop_id.to_owned()), regularity))
+            })
+            .collect();
+
+        report
+    }
+
+    /// Observes the arrival of a vehicle stopped at a stop of a known trip.
+    fn observe_vehicle(&mut self, vehicle: &VehiclePosition) {
+        let (descriptor, timestamp) = match (&vehicle.trip, vehicle.timestamp) {
+            (Some(descriptor), Some(timestamp)) => (descriptor, timestamp as i64),
+            _ => return,
+        };
+        if vehicle.current_status() != VehicleStopStatus::StoppedAt {
+            return;
+        }
+        let trip_id = match &descriptor.trip_id {
+            Some(trip_id) => trip_id,
+            None => return,
+        };
+        let start_time = if self.frequency_trips.contains(trip_id.as_str()) {
+            match descriptor.start_time.as_deref().map(parse_gtfs_time) {
+                Some(Ok(start_time)) => Some(start_time),
+                _ => return,
+            }
+        } else {
+            None
+        };
+
+        let trip = match self.predictions.trip(trip_id, start_time) {
+            Some(trip) => trip,
+            None => return,
+        };
+        let stop_time = trip.stop_times.iter().find(|stop_time| {
+            match (vehicle.current_stop_sequence, &vehicle.stop_id) {
+                (Some(sequence), _) => stop_time.stop_sequence == sequence,
+                (None, Some(stop_id)) => stop_time.stop_id == *stop_id,
+                (None, None) => false,
+            }
+        });
+        let (stop_time, route_id) = match (stop_time, &trip.route_id) {
+            (Some(stop_time), Some(route_id)) => (stop_time, route_id),
+            _ => return,
+        };
+        let scheduled = match stop_time
+            .scheduled_arrival
+            .or(stop_time.scheduled_departure)
+        {
+            Some(scheduled) => scheduled,
+            None => return,
+        };
+
+        self.arrivals
+            .entry((trip.trip_id.clone(), start_time, stop_time.stop_sequence))
+            .or_insert_with(|| ObservedArrival {
+                trip_id: trip.trip_id.clone(),
+                route_id: route_id.clone(),
+                start_time,
+                stop_id: stop_time.stop_id.clone(),
+                stop_sequence: stop_time.stop_sequence,
+                scheduled,
+                observed: timestamp,
+            });
+    }
+}
+
+/// Regularity of the headways between arrivals, none with fewer than two arrivals.
+fn regularity(mut arrivals: Vec<&ObservedArrival>) -> Option<HeadwayRegularity> {
+    if arrivals.len() < 2 {
+        return None;
+    }
+
+    arrivals.sort_by_key(|arrival| arrival.observed);
+    let observed: Vec<f64> = arrivals
+        .windows(2)
+        .map(|pair| (pair[1].observed - pair[0].observed) as f64)
+        .collect();
+    let mut scheduled: Vec<i64> = arrivals.iter().map(|arrival| arrival.scheduled).collect();
+    scheduled.sort_unstable();
+
+    let count = observed.len() as f64;
+    let mean = observed.iter().sum::<f64>() / count;
+    let variance = observed.iter().map(|h| (h - mean).powi(2)).sum::<f64>() / count;
+    let scheduled_span = scheduled[scheduled.len() - 1] - scheduled[0];
+
+    Some(HeadwayRegularity {
+        headways: observed.len(),
+        mean,
+        std_dev: variance.sqrt(),
+        scheduled_mean: scheduled_span as f64 / count,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        realtime::{
+            archive::SnapshotRecorder,
+            trip_descriptor::ScheduleRelationship,
+            trip_update::{StopTimeEvent, StopTimeUpdate},
+            FeedEntity, FeedHeader, TripDescriptor, TripUpdate,
+        },
+        test::reader_from_files,
+    };
+    use chrono::Utc;
+    use std::io::Cursor;
+
+    fn date() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, 4).unwrap()
+    }
+
+    fn at(hours: i64, minutes: i64) -> i64 {
+        service_day_start(date(), &Utc) + hours * 3600 + minutes * 60
+    }
+
+    /// Route R1 calls at A then B every 15 minutes from 08:00.
+    fn trips() -> HashMap<String, Trip> {
+        reader_from_files(&[
+            ("stops.txt", "stop_id,stop_name\nA,A\nB,B\n"),
+            (
+                "trips.txt",
+                "route_id,service_id,trip_id\nR1,WD,T1\nR1,WD,T2\nR1,WD,T3\n",
+            ),
+            (
+                "stop_times.txt",
+                "trip_id,arrival_time,departure_time,stop_id,stop_sequence\n\
+                 T1,08:00:00,08:00:00,A,1\nT1,08:10:00,08:10:00,B,2\n\
+                 T2,08:15:00,08:15:00,A,1\nT2,08:25:00,08:25:00,B,2\n\
+                 T3,08:30:00,08:30:00,A,1\nT3,08:40:00,08:40:00,B,2\n",
+            ),
+        ])
+        .trips()
+        .unwrap()
+    }
+
+    fn snapshot(timestamp: i64, entities: Vec<FeedEntity>) -> FeedMessage {
+        FeedMessage {
+            header: FeedHeader {
+                gtfs_realtime_version: "2.0".to_owned(),
+                timestamp: Some(timestamp as u64),
+                ..Default::default()
+            },
+            entity: entities,
+        }
+    }
+
+    fn delayed(trip_id: &str, delay: i32) -> FeedEntity {
+        FeedEntity {
+            id: trip_id.to_owned(),
+            trip_update: Some(TripUpdate {
+                trip: TripDescriptor {
+                    trip_id: Some(trip_id.to_owned()),
+                    ..Default::default()
+                },
+                stop_time_update: vec![StopTimeUpdate {
+                    stop_sequence: Some(1),
+                    arrival: Some(StopTimeEvent {
+                        delay: Some(delay),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn canceled(trip_id: &str) -> FeedEntity {
+        FeedEntity {
+            id: trip_id.to_owned(),
+            trip_update: Some(TripUpdate {
+                trip: TripDescriptor {
+                    trip_id: Some(trip_id.to_owned()),
+                    schedule_relationship: Some(ScheduleRelationship::Canceled as i32),
+                    ..Default::default()
+                },
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn stopped(trip_id: &str, stop_sequence: u32, timestamp: i64) -> FeedEntity {
+        FeedEntity {
+            id: format!("{}-vehicle", trip_id),
+            vehicle: Some(VehiclePosition {
+                trip: Some(TripDescriptor {
+                    trip_id: Some(trip_id.to_owned()),
+                    ..Default::default()
+                }),
+                current_stop_sequence: Some(stop_sequence),
+                current_status: Some(VehicleStopStatus::StoppedAt as i32),
+                timestamp: Some(timestamp as u64),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn performance() {
+        let trips = trips();
+        let snapshots = vec![
+            snapshot(at(7, 55), vec![delayed("T1", 60), delayed("T2", 0)]),
+            // T1 is later than predicted: its arrivals are observed once in the past
+            snapshot(at(8, 5), vec![delayed("T1", 120), delayed("T2", 600)]),
+            snapshot(
+                at(8, 12),
+                vec![delayed("T1", 120), delayed("T2", 600), canceled("T3")],
+            ),
+            snapshot(at(8, 26), vec![stopped("T2", 1, at(8, 25))]),
+            snapshot(at(8, 45), vec![]),
+        ];
+
+        let mut recorder = SnapshotRecorder::new(Cursor::new(Vec::new()));
+        for snapshot in &snapshots {
+            recorder.record(snapshot).unwrap();
+        }
+        let mut archive = SnapshotArchive::new(recorder.finish().unwrap()).unwrap();
+
+        let mut analysis = PerformanceAnalysis::new(&trips, &[], date(), &Utc);
+        analysis.replay(&mut archive).unwrap();
+        let report = analysis.report();
+
+        let arrivals: Vec<(&str, u32, i64)> = report
+            .arrivals
+            .iter()
+            .map(|arrival| {
+                (
+                    arrival.trip_id.as_str(),
+                    arrival.stop_sequence,
+                    arrival.delay(),
+                )
+            })
+            .collect();
+        assert_eq!(arrivals, [("T1", 1, 120), ("T1", 2, 120), ("T2", 1, 600)]);
+
+        assert_eq!(
+            report.by_route["R1"],
+            OnTimePerformance {
+                early: 0,
+                on_time: 2,
+                late: 1
+            }
+        );
+        assert_eq!(report.by_stop["A"].late, 1);
+        assert_eq!(report.by_hour[&8].observed(), 3);
+        assert_eq!(report.by_route["R1"].on_time_ratio(), Some(2.0 / 3.0));
+
+        assert_eq!(report.cancellations.len(), 1);
+        assert_eq!(report.cancellations[0].trip_id, "T3");
+
+        // T1 and T2 arrived at A 23 minutes apart, for 15 scheduled
+        let headway = report.headways[&("R1".to_owned(), "A".to_owned())];
+        assert_eq!(headway.headways, 1);
+        assert_eq!(headway.mean, 23.0 * 60.0);
+        assert_eq!(headway.scheduled_mean, 15.0 * 60.0);
+        assert_eq!(headway.coefficient_of_variation(), 0.0);
+
+        let mut strict = PerformanceAnalysis::new(&trips, &[], date(), &Utc).on_time_window(0, 60);
+        for snapshot in &snapshots {
+            strict.add(snapshot);
+        }
+        assert_eq!(strict.report().by_route["R1"].late, 3);
+    }
+}
@@ -0,0 +1,769 @@
+//! Predicted arrival and departure times, from the static schedule and GTFS-Realtime TripUpdates.
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::{NaiveDate, TimeZone};
+
+use super::{
+    feed_header::Incrementality,
+    trip_descriptor::ScheduleRelationship,
+    trip_update::{stop_time_update, StopTimeEvent, StopTimeUpdate},
+    FeedMessage, TripUpdate,
+};
+use crate::{
+    gtfs_serde::{parse_gtfs_date, parse_gtfs_time},
+    structures::{calendar::service_day_start, frequencies::Frequency, trips::Trip},
+};
+
+/// Status of a trip instance.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TripStatus {
+    /// Runs according to the static schedule, possibly with delays.
+    Scheduled,
+    /// Not part of the static schedule.
+    Added,
+    /// Frequency-based trip running without a schedule.
+    Unscheduled,
+    /// Copy of a scheduled trip, run at another time.
+    Duplicated,
+    /// Removed from the schedule.
+    Canceled,
+}
+
+/// Status of a stop of a trip instance.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum StopTimeStatus {
+    /// Served by the trip.
+    Scheduled,
+    /// Not served by the trip.
+    Skipped,
+    /// Served by the trip, but no prediction is available.
+    NoData,
+}
+
+/// A stop of a trip instance. Times are in POSIX time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PredictedStopTime {
+    pub stop_id: String,
+    pub stop_sequence: u32,
+    pub status: StopTimeStatus,
+    pub scheduled_arrival: Option<i64>,
+    pub scheduled_departure: Option<i64>,
+    /// Predicted arrival, if any realtime data applies to this stop.
+    pub arrival: Option<i64>,
+    /// Predicted departure, if any realtime data applies to this stop.
+    pub departure: Option<i64>,
+}
+
+impl PredictedStopTime {
+    pub fn arrival_delay(&self) -> Option<i64> {
+        Some(self.arrival? - self.scheduled_arrival?)
+    }
+
+    pub fn departure_delay(&self) -> Option<i64> {
+        Some(self.departure? - self.scheduled_departure?)
+    }
+}
+
+/// A trip instance of the service date, with its predicted stop times.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PredictedTrip {
+    pub trip_id: String,
+    pub route_id: Option<String>,
+    /// Start time of the instance, in seconds since the start of the service day.
+    /// Only set for frequency-based, added and duplicated trips.
+    pub start_time: Option<u64>,
+    pub status: TripStatus,
+    pub stop_times: Vec<PredictedStopTime>,
+}
+
+/// Identifies a trip instance within the service date.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct TripKey {
+    trip_id: String,
+    start_time: Option<u64>,
+}
+
+/// Applies the TripUpdates of GTFS-Realtime feeds to the trips of a service date.
+///
+/// Feeds with a `FULL_DATASET` incrementality replace all the updates known so far, while
+/// `DIFFERENTIAL` ones replace or delete them entity by entity.
+///
+/// ```
+/// use chrono::{NaiveDate, Utc};
+/// use gtfs_parser::{realtime::{predictions::Predictions, FeedMessage}, GtfsReader};
+///
+/// let mut gtfs = GtfsReader::from_path("./resources/zips/gtfs.zip").unwrap();
+/// let trips = gtfs.trips().unwrap();
+/// let date = NaiveDate::from_ymd_opt(2006, 7, 3).unwrap();
+///
+/// let mut predictions = Predictions::new(&trips, &[], date, &Utc);
+/// predictions.apply(&FeedMessage::from_path("./resources/realtime/trip_updates.pb").unwrap());
+///
+/// let trip = predictions.trip("trip1", None).unwrap();
+/// assert_eq!(trip.stop_times[0].arrival_delay(), Some(120));
+/// ```
+pub struct Predictions<'t> {
+    trips: &'t HashMap<String, Trip>,
+    frequencies: HashMap<&'t str, Vec<&'t Frequency>>,
+    date: NaiveDate,
+    /// POSIX time of the start of the service day, noon minus 12 hours in local time
+    day_start: i64,
+    updates: HashMap<TripKey, TripUpdate>,
+    /// Trip instance updated by each feed entity
+    entities: HashMap<String, TripKey>,
+}
+
+impl<'t> Predictions<'t> {
+    /// Schedule of `date` in the given time zone, which is the one of the agency.
+    pub fn new<Tz: TimeZone>(
+        trips: &'t HashMap<String, Trip>,
+        frequencies: &'t [Frequency],
+        date: NaiveDate,
+        timezone: &Tz,
+    ) -> Self {
+        let mut by_trip: HashMap<&str, Vec<&Frequency>> = HashMap::new();
+        for frequency in frequencies {
+            by_trip
+                .entry(frequency.trip_id.as_str())
+                .or_default()
+                .push(frequency);
+        }
+
+        Self {
+            trips,
+            frequencies: by_trip,
+            date,
+            day_start: service_day_start(date, timezone),
+            updates: HashMap::new(),
+            entities: HashMap::new(),
+        }
+    }
+
+    /// Applies the TripUpdates of a feed. Updates for other service dates are ignored.
+    pub fn apply(&mut self, feed: &FeedMessage) {
+        if feed.header.incrementality() == Incrementality::FullDataset {
+            self.updates.clear();
+            self.entities.clear();
+        }
+
+        for entity in &feed.entity {
+            if let Some(key) = self.entities.remove(&entity.id) {
+                self.updates.remove(&key);
+            }
+            if entity.is_deleted() {
+                continue;
+            }
+            if let Some(update) = &entity.trip_update {
+                if let Some(key) = self.key(update) {
+                    self.entities.insert(entity.id.clone(), key.clone());
+                    self.updates.insert(key, update.clone());
+                }
+            }
+        }
+    }
+
+    /// The trip instance identified by its trip id and, for frequency-based, added and
+    /// duplicated trips, its start time.
+    pub fn trip(&self, trip_id: &str, start_time: Option<u64>) -> Option<PredictedTrip> {
+        let key = TripKey {
+            trip_id: trip_id.to_owned(),
+            start_time,
+        };
+        match (self.updates.get(&key), self.trips.get(trip_id)) {
+            (Some(update), _) => Some(self.predict_update(&key, update)),
+            (None, Some(trip)) => Some(self.predict(&key, trip, None)),
+            (None, None) => None,
+        }
+    }
+
+    /// Every trip instance which has an update.
+    pub fn updated_trips(&self) -> Vec<PredictedTrip> {
+        let mut trips = self
+            .updates
+            .iter()
+            .map(|(key, update)| self.predict_update(key, update))
+            .collect::<Vec<_>>();
+        sort(&mut trips);
+        trips
+    }
+
+    /// Every trip instance of the service date: the trips of the running `services`,
+    /// one instance per start time for frequency-based trips, and the added ones.
+    pub fn trips(&self, services: &HashSet<String>) -> Vec<PredictedTrip> {
+        let mut keys = HashSet::new();
+        for trip in self.trips.values() {
+            if !services.contains(&trip.service_id) {
+                continue;
+            }
+            match self.frequencies.get(trip.id.as_str()) {
+                Some(frequencies) => {
+                    for start_time in frequencies.iter().flat_map(|f| f.start_times()) {
+                        keys.insert(TripKey {
+                            trip_id: trip.id.clone(),
+                            start_time: Some(start_time),
+                        });
+                    }
+                }
+                None => {
+                    keys.insert(TripKey {
+                        trip_id: trip.id.clone(),
+                        start_time: None,
+                    });
+                }
+            }
+        }
+
+        let mut trips = keys
+            .iter()
+            .filter_map(|key| self.trip(&key.trip_id, key.start_time))
+            .collect::<Vec<_>>();
+        trips.extend(
+            self.updates
+                .iter()
+                .filter(|(key, update)| {
+                    // Added trips, or instances of running trips which are not in the schedule
+                    let base = update
+                        .trip
+                        .trip_id
+                        .as_ref()
+                        .and_then(|trip_id| self.trips.get(trip_id));
+                    !keys.contains(*key)
+                        && match base {
+                            Some(trip) => services.contains(&trip.service_id),
+                            None => true,
+                        }
+                })
+                .map(|(key, update)| self.predict_update(key, update)),
+        );
+        sort(&mut trips);
+        trips
+    }
+
+    /// Trip instance an update applies to, if it applies to the service date.
+    fn key(&self, update: &TripUpdate) -> Option<TripKey> {
+        let trip = &update.trip;
+        if let Some(start_date) = &trip.start_date {
+            if parse_gtfs_date(start_date).ok()? != self.date {
+                return None;
+            }
+        }
+        let parse_start_time = |time: &Option<String>| -> Option<u64> {
+            time.as_deref().and_then(|time| parse_gtfs_time(time).ok())
+        };
+
+        if trip.schedule_relationship() == ScheduleRelationship::Duplicated {
+            let properties = update.trip_properties.as_ref()?;
+            return Some(TripKey {
+                trip_id: properties.trip_id.clone()?,
+                start_time: parse_start_time(&properties.start_time),
+            });
+        }
+
+        let trip_id = trip.trip_id.clone()?;
+        // Instances of trips which are not frequency-based are told apart by their date only
+        let start_time = if self.trips.contains_key(&trip_id)
+            && !self.frequencies.contains_key(trip_id.as_str())
+        {
+            None
+        } else {
+            parse_start_time(&trip.start_time)
+        };
+        Some(TripKey {
+            trip_id,
+            start_time,
+        })
+    }
+
+    fn predict_update(&self, key: &TripKey, update: &TripUpdate) -> PredictedTrip {
+        let relationship = update.trip.schedule_relationship();
+        let scheduled = update
+            .trip
+            .trip_id
+            .as_ref()
+            .and_then(|trip_id| self.trips.get(trip_id));
+        match scheduled {
+            Some(trip)
+                if relationship != ScheduleRelationship::Added
+                    && relationship != ScheduleRelationship::New =>
+            {
+                self.predict(key, trip, Some(update))
+            }
+            _ => self.added(key, update),
+        }
+    }
+
+    /// Applies an update, if any, to a trip of the static schedule.
+    fn predict(&self, key: &TripKey, trip: &Trip, update: Option<&TripUpdate>) -> PredictedTrip {
+        // Frequency-based and duplicated instances are shifted from the times of stop_times.txt
+        let first_time = trip
+            .stop_times
+            .first()
+            .and_then(|st| st.departure_time.or(st.arrival_time))
+            .unwrap_or_default();
+        let offset = self.day_start
+            + key
+                .start_time
+                .map_or(0, |start_time| start_time as i64 - first_time as i64);
+
+        let mut stop_times = trip
+            .stop_times
+            .iter()
+            .map(|st| PredictedStopTime {
+                stop_id: st.stop.id.clone(),
+                stop_sequence: st.stop_sequence as u32,
+                status: StopTimeStatus::Scheduled,
+                scheduled_arrival: st.arrival_time.map(|t| t as i64 + offset),
+                scheduled_departure: st.departure_time.map(|t| t as i64 + offset),
+                arrival: None,
+                departure: None,
+            })
+            .collect::<Vec<_>>();
+
+        let mut status = TripStatus::Scheduled;
+        if let Some(update) = update {
+            status = match update.trip.schedule_relationship() {
+                ScheduleRelationship::Canceled | ScheduleRelationship::Deleted => {
+                    TripStatus::Canceled
+                }
+                ScheduleRelationship::Unscheduled => TripStatus::Unscheduled,
+                ScheduleRelationship::Duplicated => TripStatus::Duplicated,
+                _ => TripStatus::Scheduled,
+            };
+            if status == TripStatus::Canceled {
+                for stop_time in &mut stop_times {
+                    stop_time.status = StopTimeStatus::Skipped;
+                }
+            } else {
+                propagate(&mut stop_times, update);
+            }
+        }
+
+        PredictedTrip {
+            trip_id: key.trip_id.clone(),
+            route_id: Some(trip.route_id.clone()),
+            start_time: key.start_time,
+            status,
+            stop_times,
+        }
+    }
+
+    /// Builds a trip which is not part of the static schedule from its update.
+    fn added(&self, key: &TripKey, update: &TripUpdate) -> PredictedTrip {
+        let stop_times = update
+            .stop_time_update
+            .iter()
+            .enumerate()
+            .map(|(index, stu)| {
+                let skipped =
+                    stu.schedule_relationship() == stop_time_update::ScheduleRelationship::Skipped;
+                let time = |event: &Option<StopTimeEvent>| {
+                    event
+                        .as_ref()
+                        .and_then(|event| event.time)
+                        .filter(|_| !skipped)
+                };
+                let arrival = time(&stu.arrival);
+                PredictedStopTime {
+                    stop_id: stop_id(stu).unwrap_or_default(),
+                    stop_sequence: stu.stop_sequence.unwrap_or(index as u32),
+                    status: if skipped {
+                        StopTimeStatus::Skipped
+                    } else {
+                        StopTimeStatus::Scheduled
+                    },
+                    scheduled_arrival: None,
+                    scheduled_departure: None,
+                    arrival,
+                    departure: time(&stu.departure).or(arrival),
+                }
+            })
+            .collect();
+
+        PredictedTrip {
+            trip_id: key.trip_id.clone(),
+            route_id: update.trip.route_id.clone(),
+            start_time: key.start_time,
+            status: match update.trip.schedule_relationship() {
+                ScheduleRelationship::Canceled | ScheduleRelationship::Deleted => {
+                    TripStatus::Canceled
+                }
+                ScheduleRelationship::Unscheduled => TripStatus::Unscheduled,
+                _ => TripStatus::Added,
+            },
+            stop_times,
+        }
+    }
+}
+
+/// Id of the stop served, which may be assigned in realtime.
+fn stop_id(stu: &StopTimeUpdate) -> Option<String> {
+    stu.stop_time_properties
+        .as_ref()
+        .and_then(|properties| properties.assigned_stop_id.clone())
+        .or_else(|| stu.stop_id.clone())
+}
+
+/// Applies the StopTimeUpdates of a trip to its stop times.
+///
+/// A delay applies to the following stops until another StopTimeUpdate is given. Stops before
+/// the first StopTimeUpdate only get the trip delay, if any.
+fn propagate(stop_times: &mut [PredictedStopTime], update: &TripUpdate) {
+    // Match each update to a stop time, by stop_sequence or else by stop_id
+    let mut updates: Vec<Option<&StopTimeUpdate>> = vec![None; stop_times.len()];
+    let mut next = 0;
+    for stu in &update.stop_time_update {
+        let found = stop_times[next..]
+            .iter()
+            .position(|st| match stu.stop_sequence {
+                Some(sequence) => st.stop_sequence == sequence,
+                None => Some(&st.stop_id) == stu.stop_id.as_ref(),
+            });
+        if let Some(index) = found {
+            updates[next + index] = Some(stu);
+            next += index + 1;
+        }
+    }
+
+    let mut delay = update.delay.map(i64::from);
+    for (stop_time, stu) in stop_times.iter_mut().zip(updates) {
+        let stu = match stu {
+            Some(stu) => stu,
+            None => {
+                stop_time.arrival = delay.and_then(|d| Some(stop_time.scheduled_arrival? + d));
+                stop_time.departure = delay.and_then(|d| Some(stop_time.scheduled_departure? + d));
+                continue;
+            }
+        };
+
+        if let Some(stop_id) = stop_id(stu) {
+            stop_time.stop_id = stop_id;
+        }
+        match stu.schedule_relationship() {
+            stop_time_update::ScheduleRelationship::Skipped => {
+                stop_time.status = StopTimeStatus::Skipped;
+            }
+            stop_time_update::ScheduleRelationship::NoData => {
+                stop_time.status = StopTimeStatus::NoData;
+                delay = None;
+            }
+            _ => {
+                let event_time = |event: &Option<StopTimeEvent>, scheduled: Option<i64>| {
+                    let event = event.as_ref()?;
+                    event
+                        .time
+                        .or_else(|| Some(scheduled? + i64::from(event.delay?)))
+                };
+
+                let arrival = event_time(&stu.arrival, stop_time.scheduled_arrival);
+                let arrival_delay = match (arrival, stop_time.scheduled_arrival) {
+                    (Some(arrival), Some(scheduled)) => Some(arrival - scheduled),
+                    _ => delay,
+                };
+                let departure = event_time(&stu.departure, stop_time.scheduled_departure)
+                    .or_else(|| Some(stop_time.scheduled_departure? + arrival_delay?));
+
+                stop_time.arrival =
+                    arrival.or_else(|| Some(stop_time.scheduled_arrival? + arrival_delay?));
+                stop_time.departure = match (departure, stop_time.arrival) {
+                    // A vehicle cannot leave before it arrives
+                    (Some(departure), Some(arrival)) => Some(departure.max(arrival)),
+                    (departure, _) => departure,
+                };
+                delay = match (stop_time.departure, stop_time.scheduled_departure) {
+                    (Some(departure), Some(scheduled)) => Some(departure - scheduled),
+                    _ => arrival_delay,
+                };
+            }
+        }
+    }
+}
+
+fn sort(trips: &mut [PredictedTrip]) {
+    let start = |trip: &PredictedTrip| {
+        trip.stop_times
+            .first()
+            .and_then(|st| st.scheduled_departure.or(st.departure))
+    };
+    trips.sort_by(|a, b| {
+        start(a)
+            .cmp(&start(b))
+            .then_with(|| a.trip_id.cmp(&b.trip_id))
+            .then_with(|| a.start_time.cmp(&b.start_time))
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        realtime::{
+            trip_update::{stop_time_update::ScheduleRelationship as Stop, TripProperties},
+            FeedEntity, FeedHeader, TripDescriptor,
+        },
+        test::reader_from_files,
+    };
+    use chrono::Utc;
+
+    fn date() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, 4).unwrap()
+    }
+
+    /// POSIX time of a time of the service day.
+    fn at(hours: i64, minutes: i64) -> i64 {
+        Utc.from_utc_datetime(&date().and_hms_opt(0, 0, 0).unwrap())
+            .timestamp()
+            + hours * 3600
+            + minutes * 60
+    }
+
+    fn feed() -> (HashMap<String, Trip>, Vec<Frequency>) {
+        let mut gtfs = reader_from_files(&[
+            ("stops.txt", "stop_id,stop_name\nA,A\nB,B\nC,C\nD,D\n"),
+            (
+                "trips.txt",
+                "route_id,service_id,trip_id\nR1,WD,T1\nR1,WD,F1\nR1,WE,T2\n",
+            ),
+            (
+                "stop_times.txt",
+                "trip_id,arrival_time,departure_time,stop_id,stop_sequence\n\
+                 T1,08:00:00,08:00:00,A,1\n\
+                 T1,08:10:00,08:11:00,B,2\n\
+                 T1,08:20:00,08:20:00,C,3\n\
+                 T1,08:30:00,08:30:00,D,4\n\
+                 F1,06:00:00,06:00:00,A,1\n\
+                 F1,06:05:00,06:05:00,B,2\n\
+                 T2,09:00:00,09:00:00,A,1\n\
+                 T2,09:10:00,09:10:00,B,2\n",
+            ),
+            (
+                "frequencies.txt",
+                "trip_id,start_time,end_time,headway_secs\nF1,06:00:00,07:00:00,1800\n",
+            ),
+        ]);
+        (gtfs.trips().unwrap(), gtfs.frequencies().unwrap())
+    }
+
+    fn message(incrementality: Incrementality, entities: Vec<(&str, TripUpdate)>) -> FeedMessage {
+        FeedMessage {
+            header: FeedHeader {
+                gtfs_realtime_version: "2.0".to_owned(),
+                incrementality: Some(incrementality as i32),
+                ..Default::default()
+            },
+            entity: entities
+                .into_iter()
+                .map(|(id, update)| FeedEntity {
+                    id: id.to_owned(),
+                    trip_update: Some(update),
+                    ..Default::default()
+                })
+                .collect(),
+        }
+    }
+
+    fn update(
+        trip_id: &str,
+        relationship: ScheduleRelationship,
+        stop_time_update: Vec<StopTimeUpdate>,
+    ) -> TripUpdate {
+        TripUpdate {
+            trip: TripDescriptor {
+                trip_id: Some(trip_id.to_owned()),
+                start_date: Some("20240304".to_owned()),
+                schedule_relationship: Some(relationship as i32),
+                ..Default::default()
+            },
+            stop_time_update,
+            ..Default::default()
+        }
+    }
+
+    fn event(delay: Option<i32>, time: Option<i64>) -> Option<StopTimeEvent> {
+        Some(StopTimeEvent {
+            delay,
+            time,
+            ..Default::default()
+        })
+    }
+
+    fn stop_time_update(
+        stop_id: &str,
+        relationship: stop_time_update::ScheduleRelationship,
+        arrival: Option<StopTimeEvent>,
+        departure: Option<StopTimeEvent>,
+    ) -> StopTimeUpdate {
+        StopTimeUpdate {
+            stop_id: Some(stop_id.to_owned()),
+            schedule_relationship: Some(relationship as i32),
+            arrival,
+            departure,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn delay_propagation() {
+        let (trips, frequencies) = feed();
+        let mut predictions = Predictions::new(&trips, &frequencies, date(), &Utc);
+        predictions.apply(&message(
+            Incrementality::FullDataset,
+            vec![(
+                "1",
+                update(
+                    "T1",
+                    ScheduleRelationship::Scheduled,
+                    vec![
+                        stop_time_update("B", Stop::Scheduled, event(Some(120), None), None),
+                        stop_time_update("D", Stop::Scheduled, event(None, Some(at(8, 31))), None),
+                    ],
+                ),
+            )],
+        ));
+
+        let trip = predictions.trip("T1", None).unwrap();
+        let stop_times = &trip.stop_times;
+        assert_eq!(stop_times[0].arrival, None);
+        assert_eq!(stop_times[1].arrival, Some(at(8, 12)));
+        // The delay of the arrival applies to the departure
+        assert_eq!(stop_times[1].departure, Some(at(8, 13)));
+        assert_eq!(stop_times[2].arrival_delay(), Some(120));
+        assert_eq!(stop_times[3].arrival_delay(), Some(60));
+        assert_eq!(stop_times[3].departure, Some(at(8, 31)));
+    }
+
+    #[test]
+    fn skipped_and_canceled() {
+        let (trips, frequencies) = feed();
+        let mut predictions = Predictions::new(&trips, &frequencies, date(), &Utc);
+        predictions.apply(&message(
+            Incrementality::FullDataset,
+            vec![
+                (
+                    "1",
+                    update(
+                        "T1",
+                        ScheduleRelationship::Scheduled,
+                        vec![
+                            stop_time_update("A", Stop::Scheduled, None, event(Some(60), None)),
+                            stop_time_update("B", Stop::Skipped, None, None),
+                            stop_time_update("C", Stop::NoData, None, None),
+                        ],
+                    ),
+                ),
+                (
+                    "2",
+                    TripUpdate {
+                        trip: TripDescriptor {
+                            start_time: Some("06:30:00".to_owned()),
+                            ..update("F1", ScheduleRelationship::Canceled, vec![]).trip
+                        },
+                        ..Default::default()
+                    },
+                ),
+            ],
+        ));
+
+        let stop_times = predictions.trip("T1", None).unwrap().stop_times;
+        assert_eq!(stop_times[1].status, StopTimeStatus::Skipped);
+        assert_eq!(stop_times[1].arrival, None);
+        assert_eq!(stop_times[2].status, StopTimeStatus::NoData);
+        assert_eq!(stop_times[3].arrival, None);
+
+        let canceled = predictions.trip("F1", Some(6 * 3600 + 1800)).unwrap();
+        assert_eq!(canceled.status, TripStatus::Canceled);
+        assert_eq!(canceled.stop_times[0].scheduled_departure, Some(at(6, 30)));
+        let scheduled = predictions.trip("F1", Some(6 * 3600)).unwrap();
+        assert_eq!(scheduled.status, TripStatus::Scheduled);
+    }
+
+    #[test]
+    fn added_and_duplicated_trips() {
+        let (trips, frequencies) = feed();
+        let mut predictions = Predictions::new(&trips, &frequencies, date(), &Utc);
+        let mut duplicated = update("T1", ScheduleRelationship::Duplicated, vec![]);
+        duplicated.trip_properties = Some(TripProperties {
+            trip_id: Some("T1-bis".to_owned()),
+            start_time: Some("10:00:00".to_owned()),
+            ..Default::default()
+        });
+        let mut added = update(
+            "X1",
+            ScheduleRelationship::Added,
+            vec![
+                stop_time_update("C", Stop::Scheduled, None, event(None, Some(at(7, 0)))),
+                stop_time_update("D", Stop::Scheduled, event(None, Some(at(7, 10))), None),
+            ],
+        );
+        added.trip.route_id = Some("R1".to_owned());
+        predictions.apply(&message(
+            Incrementality::FullDataset,
+            vec![("1", duplicated), ("2", added)],
+        ));
+
+        let services = ["WD".to_owned()].iter().cloned().collect();
+        let day = predictions.trips(&services);
+        let ids = day
+            .iter()
+            .map(|trip| (trip.trip_id.as_str(), trip.start_time))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ids,
+            vec![
+                ("F1", Some(6 * 3600)),
+                ("F1", Some(6 * 3600 + 1800)),
+                ("X1", None),
+                ("T1", None),
+                ("T1-bis", Some(10 * 3600)),
+            ]
+        );
+
+        assert_eq!(day[2].status, TripStatus::Added);
+        assert_eq!(day[2].stop_times[1].departure, Some(at(7, 10)));
+        assert_eq!(day[4].status, TripStatus::Duplicated);
+        assert_eq!(day[4].stop_times[3].scheduled_arrival, Some(at(10, 30)));
+    }
+
+    #[test]
+    fn incremental_updates() {
+        let (trips, frequencies) = feed();
+        let mut predictions = Predictions::new(&trips, &frequencies, date(), &Utc);
+        let delayed = |delay| {
+            update(
+                "T1",
+                ScheduleRelationship::Scheduled,
+                vec![stop_time_update(
+                    "A",
+                    Stop::Scheduled,
+                    None,
+                    event(Some(delay), None),
+                )],
+            )
+        };
+
+        predictions.apply(&message(
+            Incrementality::FullDataset,
+            vec![("1", delayed(60))],
+        ));
+        predictions.apply(&message(
+            Incrementality::Differential,
+            vec![("1", delayed(300))],
+        ));
+        let trip = predictions.trip("T1", None).unwrap();
+        assert_eq!(trip.stop_times[3].arrival_delay(), Some(300));
+
+        let mut deletion = message(Incrementality::Differential, vec![("1", delayed(0))]);
+        deletion.entity[0].is_deleted = Some(true);
+        predictions.apply(&deletion);
+        assert!(predictions.updated_trips().is_empty());
+
+        // Updates of other service dates are ignored
+        let mut other_date = delayed(60);
+        other_date.trip.start_date = Some("20240305".to_owned());
+        predictions.apply(&message(
+            Incrementality::FullDataset,
+            vec![("1", other_date)],
+        ));
+        assert!(predictions.updated_trips().is_empty());
+    }
+}
@@ -0,0 +1,890 @@
+//! Messages of the GTFS-Realtime protocol, as defined by `gtfs-realtime.proto`
+//! (https://gtfs.org/realtime/reference/).
+//!
+//! The layout follows what prost-build generates: nested messages and enums live in a
+//! module named after their parent message.
+//!
+//! Protobuf extensions are not supported by prost, the most common ones are declared as
+//! regular fields under their extension tag instead.
+
+use serde::{Serialize, Serializer};
+
+/// The contents of a feed message.
+#[derive(Clone, PartialEq, ::prost::Message, Serialize)]
+pub struct FeedMessage {
+    /// Metadata about this feed and feed message.
+    #[prost(message, required, tag = "1")]
+    pub header: FeedHeader,
+    /// Contents of the feed.
+    #[prost(message, repeated, tag = "2")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub entity: Vec<FeedEntity>,
+}
+
+/// Metadata about a feed, included in feed messages.
+#[derive(Clone, PartialEq, ::prost::Message, Serialize)]
+pub struct FeedHeader {
+    /// Version of the feed specification, e.g. "2.0".
+    #[prost(string, required, tag = "1")]
+    pub gtfs_realtime_version: String,
+    #[prost(
+        enumeration = "feed_header::Incrementality",
+        optional,
+        tag = "2",
+        default = "FullDataset"
+    )]
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "crate::realtime::proto::enumeration::<feed_header::Incrementality, _>"
+    )]
+    pub incrementality: Option<i32>,
+    /// Moment when the content of this feed has been created, in POSIX time.
+    #[prost(uint64, optional, tag = "3")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+    #[prost(string, optional, tag = "4")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub feed_version: Option<String>,
+    /// New York City Transit extension.
+    #[prost(message, optional, tag = "1001")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nyct_feed_header: Option<NyctFeedHeader>,
+}
+
+pub mod feed_header {
+    use serde::Serialize;
+
+    /// Determines whether the current fetch is incremental.
+    #[derive(
+        Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration, Serialize,
+    )]
+    #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+    #[repr(i32)]
+    pub enum Incrementality {
+        FullDataset = 0,
+        Differential = 1,
+    }
+}
+
+/// A definition (or update) of an entity in the transit feed.
+#[derive(Clone, PartialEq, ::prost::Message, Serialize)]
+pub struct FeedEntity {
+    /// The ids are used only to provide incrementality support.
+    #[prost(string, required, tag = "1")]
+    pub id: String,
+    /// Whether this entity is to be deleted. Relevant only for incremental fetches.
+    #[prost(bool, optional, tag = "2", default = "false")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_deleted: Option<bool>,
+    #[prost(message, optional, tag = "3")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trip_update: Option<TripUpdate>,
+    #[prost(message, optional, tag = "4")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vehicle: Option<VehiclePosition>,
+    #[prost(message, optional, tag = "5")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alert: Option<Alert>,
+}
+
+/// Realtime update of the progress of a vehicle along a trip.
+#[derive(Clone, PartialEq, ::prost::Message, Serialize)]
+pub struct TripUpdate {
+    /// The trip that this message applies to.
+    #[prost(message, required, tag = "1")]
+    pub trip: TripDescriptor,
+    /// Additional information on the vehicle that is serving this trip.
+    #[prost(message, optional, tag = "3")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vehicle: Option<VehicleDescriptor>,
+    /// Updates to StopTimes for the trip, ordered by stop_sequence.
+    #[prost(message, repeated, tag = "2")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stop_time_update: Vec<trip_update::StopTimeUpdate>,
+    /// The most recent moment at which the vehicle's realtime progress was measured.
+    #[prost(uint64, optional, tag = "4")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+    /// The current schedule deviation for the trip, in seconds.
+    #[prost(int32, optional, tag = "5")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delay: Option<i32>,
+    #[prost(message, optional, tag = "6")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trip_properties: Option<trip_update::TripProperties>,
+}
+
+pub mod trip_update {
+    use serde::Serialize;
+
+    /// Timing information for a single predicted event (either arrival or departure).
+    #[derive(Clone, PartialEq, ::prost::Message, Serialize)]
+    pub struct StopTimeEvent {
+        /// Delay (in seconds) relative to the scheduled time.
+        #[prost(int32, optional, tag = "1")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub delay: Option<i32>,
+        /// Event as absolute time, in POSIX time.
+        #[prost(int64, optional, tag = "2")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub time: Option<i64>,
+        /// Expected error of the prediction, in seconds.
+        #[prost(int32, optional, tag = "3")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub uncertainty: Option<i32>,
+        #[prost(int64, optional, tag = "4")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub scheduled_time: Option<i64>,
+    }
+
+    /// Realtime update for arrival and/or departure events for a given stop on a trip.
+    #[derive(Clone, PartialEq, ::prost::Message, Serialize)]
+    pub struct StopTimeUpdate {
+        /// Must be the same as in stop_times.txt in the corresponding GTFS feed.
+        #[prost(uint32, optional, tag = "1")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub stop_sequence: Option<u32>,
+        /// Must be the same as in stops.txt in the corresponding GTFS feed.
+        #[prost(string, optional, tag = "4")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub stop_id: Option<String>,
+        #[prost(message, optional, tag = "2")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub arrival: Option<StopTimeEvent>,
+        #[prost(message, optional, tag = "3")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub departure: Option<StopTimeEvent>,
+        /// Expected occupancy after departure from the given stop.
+        #[prost(
+            enumeration = "super::vehicle_position::OccupancyStatus",
+            optional,
+            tag = "7"
+        )]
+        #[serde(
+            skip_serializing_if = "Option::is_none",
+            serialize_with = "crate::realtime::proto::enumeration::<super::vehicle_position::OccupancyStatus, _>"
+        )]
+        pub departure_occupancy_status: Option<i32>,
+        #[prost(
+            enumeration = "stop_time_update::ScheduleRelationship",
+            optional,
+            tag = "5",
+            default = "Scheduled"
+        )]
+        #[serde(
+            skip_serializing_if = "Option::is_none",
+            serialize_with = "crate::realtime::proto::enumeration::<stop_time_update::ScheduleRelationship, _>"
+        )]
+        pub schedule_relationship: Option<i32>,
+        #[prost(message, optional, tag = "6")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub stop_time_properties: Option<stop_time_update::StopTimeProperties>,
+        /// New York City Transit extension.
+        #[prost(message, optional, tag = "1001")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub nyct_stop_time_update: Option<super::NyctStopTimeUpdate>,
+    }
+
+    pub mod stop_time_update {
+        use serde::Serialize;
+
+        /// The relation between the StopTimeEvents and the static schedule.
+        #[derive(
+            Clone,
+            Copy,
+            Debug,
+            PartialEq,
+            Eq,
+            Hash,
+            PartialOrd,
+            Ord,
+            ::prost::Enumeration,
+            Serialize,
+        )]
+        #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+        #[repr(i32)]
+        pub enum ScheduleRelationship {
+            Scheduled = 0,
+            Skipped = 1,
+            NoData = 2,
+            Unscheduled = 3,
+        }
+
+        /// Realtime updates for certain properties defined within GTFS stop_times.txt.
+        #[derive(Clone, PartialEq, ::prost::Message, Serialize)]
+        pub struct StopTimeProperties {
+            /// Supports real-time stop assignments.
+            #[prost(string, optional, tag = "1")]
+            #[serde(skip_serializing_if = "Option::is_none")]
+            pub assigned_stop_id: Option<String>,
+        }
+    }
+
+    /// Defines updated properties of the trip.
+    #[derive(Clone, PartialEq, ::prost::Message, Serialize)]
+    pub struct TripProperties {
+        /// Defines the identifier of a new trip which is a duplicate of an existing one.
+        #[prost(string, optional, tag = "1")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub trip_id: Option<String>,
+        /// Service date on which the duplicated trip will be run.
+        #[prost(string, optional, tag = "2")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub start_date: Option<String>,
+        /// Defines the departure start time of the trip when it's duplicated.
+        #[prost(string, optional, tag = "3")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub start_time: Option<String>,
+        /// Specifies the shape of the vehicle travel path when the trip shape differs
+        /// from the shape specified in the static feed.
+        #[prost(string, optional, tag = "4")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub shape_id: Option<String>,
+    }
+}
+
+/// Realtime positioning information for a given vehicle.
+#[derive(Clone, PartialEq, ::prost::Message, Serialize)]
+pub struct VehiclePosition {
+    /// The trip that this vehicle is serving.
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trip: Option<TripDescriptor>,
+    #[prost(message, optional, tag = "8")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vehicle: Option<VehicleDescriptor>,
+    /// Current position of this vehicle.
+    #[prost(message, optional, tag = "2")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position: Option<Position>,
+    /// The stop sequence index of the current stop.
+    #[prost(uint32, optional, tag = "3")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current_stop_sequence: Option<u32>,
+    /// Identifies the current stop.
+    #[prost(string, optional, tag = "7")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_id: Option<String>,
+    /// The exact status of the vehicle with respect to the current stop.
+    #[prost(
+        enumeration = "vehicle_position::VehicleStopStatus",
+        optional,
+        tag = "4",
+        default = "InTransitTo"
+    )]
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "crate::realtime::proto::enumeration::<vehicle_position::VehicleStopStatus, _>"
+    )]
+    pub current_status: Option<i32>,
+    /// Moment at which the vehicle's position was measured, in POSIX time.
+    #[prost(uint64, optional, tag = "5")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+    #[prost(enumeration = "vehicle_position::CongestionLevel", optional, tag = "6")]
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "crate::realtime::proto::enumeration::<vehicle_position::CongestionLevel, _>"
+    )]
+    pub congestion_level: Option<i32>,
+    #[prost(enumeration = "vehicle_position::OccupancyStatus", optional, tag = "9")]
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "crate::realtime::proto::enumeration::<vehicle_position::OccupancyStatus, _>"
+    )]
+    pub occupancy_status: Option<i32>,
+    /// Percentage of the vehicle's capacity which is occupied.
+    #[prost(uint32, optional, tag = "10")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub occupancy_percentage: Option<u32>,
+    /// Details of the multiple carriages of this given vehicle.
+    #[prost(message, repeated, tag = "11")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub multi_carriage_details: Vec<vehicle_position::CarriageDetails>,
+}
+
+pub mod vehicle_position {
+    use serde::Serialize;
+
+    #[derive(
+        Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration, Serialize,
+    )]
+    #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+    #[repr(i32)]
+    pub enum VehicleStopStatus {
+        /// The vehicle is just about to arrive at the stop.
+        IncomingAt = 0,
+        /// The vehicle is standing at the stop.
+        StoppedAt = 1,
+        /// The vehicle has departed and is in transit to the next stop.
+        InTransitTo = 2,
+    }
+
+    /// Congestion level that is affecting this vehicle.
+    #[derive(
+        Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration, Serialize,
+    )]
+    #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+    #[repr(i32)]
+    pub enum CongestionLevel {
+        UnknownCongestionLevel = 0,
+        RunningSmoothly = 1,
+        StopAndGo = 2,
+        Congestion = 3,
+        SevereCongestion = 4,
+    }
+
+    /// The state of passenger occupancy for the vehicle or carriage.
+    #[derive(
+        Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration, Serialize,
+    )]
+    #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+    #[repr(i32)]
+    pub enum OccupancyStatus {
+        Empty = 0,
+        ManySeatsAvailable = 1,
+        FewSeatsAvailable = 2,
+        StandingRoomOnly = 3,
+        CrushedStandingRoomOnly = 4,
+        Full = 5,
+        NotAcceptingPassengers = 6,
+        NoDataAvailable = 7,
+        NotBoardable = 8,
+    }
+
+    /// Carriage specific details, used for vehicles composed of several carriages.
+    #[derive(Clone, PartialEq, ::prost::Message, Serialize)]
+    pub struct CarriageDetails {
+        #[prost(string, optional, tag = "1")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub id: Option<String>,
+        /// User visible label that may be shown to the passenger.
+        #[prost(string, optional, tag = "2")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub label: Option<String>,
+        #[prost(
+            enumeration = "OccupancyStatus",
+            optional,
+            tag = "3",
+            default = "NoDataAvailable"
+        )]
+        #[serde(
+            skip_serializing_if = "Option::is_none",
+            serialize_with = "crate::realtime::proto::enumeration::<OccupancyStatus, _>"
+        )]
+        pub occupancy_status: Option<i32>,
+        #[prost(int32, optional, tag = "4", default = "-1")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub occupancy_percentage: Option<i32>,
+        /// Identifies the order of this carriage with respect to the other carriages.
+        #[prost(uint32, optional, tag = "5")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub carriage_sequence: Option<u32>,
+    }
+}
+
+/// An alert, indicating some sort of incident in the public transit network.
+#[derive(Clone, PartialEq, ::prost::Message, Serialize)]
+pub struct Alert {
+    /// Time when the alert should be shown to the user.
+    #[prost(message, repeated, tag = "1")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub active_period: Vec<TimeRange>,
+    /// Entities whose users we should notify of this alert.
+    #[prost(message, repeated, tag = "5")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub informed_entity: Vec<EntitySelector>,
+    #[prost(
+        enumeration = "alert::Cause",
+        optional,
+        tag = "6",
+        default = "UnknownCause"
+    )]
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "crate::realtime::proto::enumeration::<alert::Cause, _>"
+    )]
+    pub cause: Option<i32>,
+    #[prost(
+        enumeration = "alert::Effect",
+        optional,
+        tag = "7",
+        default = "UnknownEffect"
+    )]
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "crate::realtime::proto::enumeration::<alert::Effect, _>"
+    )]
+    pub effect: Option<i32>,
+    /// The URL which provides additional information about the alert.
+    #[prost(message, optional, tag = "8")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<TranslatedString>,
+    /// Alert header. Contains a short summary of the alert text as plain-text.
+    #[prost(message, optional, tag = "10")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub header_text: Option<TranslatedString>,
+    /// Full description for the alert as plain-text.
+    #[prost(message, optional, tag = "11")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description_text: Option<TranslatedString>,
+    /// Text for alert header to be used in text-to-speech implementations.
+    #[prost(message, optional, tag = "12")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tts_header_text: Option<TranslatedString>,
+    /// Text for full description for the alert to be used in text-to-speech implementations.
+    #[prost(message, optional, tag = "13")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tts_description_text: Option<TranslatedString>,
+    #[prost(
+        enumeration = "alert::SeverityLevel",
+        optional,
+        tag = "14",
+        default = "UnknownSeverity"
+    )]
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "crate::realtime::proto::enumeration::<alert::SeverityLevel, _>"
+    )]
+    pub severity_level: Option<i32>,
+    /// TranslatedImage to be displayed along the alert text.
+    #[prost(message, optional, tag = "15")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<TranslatedImage>,
+    /// Text describing the appearance of the linked image in the image field.
+    #[prost(message, optional, tag = "16")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_alternative_text: Option<TranslatedString>,
+    /// Description of the cause of the alert that allows for agency-specific language.
+    #[prost(message, optional, tag = "17")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cause_detail: Option<TranslatedString>,
+    /// Description of the effect of the alert that allows for agency-specific language.
+    #[prost(message, optional, tag = "18")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub effect_detail: Option<TranslatedString>,
+}
+
+pub mod alert {
+    use serde::Serialize;
+
+    /// Cause of this alert.
+    #[derive(
+        Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration, Serialize,
+    )]
+    #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+    #[repr(i32)]
+    pub enum Cause {
+        UnknownCause = 1,
+        OtherCause = 2,
+        TechnicalProblem = 3,
+        Strike = 4,
+        Demonstration = 5,
+        Accident = 6,
+        Holiday = 7,
+        Weather = 8,
+        Maintenance = 9,
+        Construction = 10,
+        PoliceActivity = 11,
+        MedicalEmergency = 12,
+    }
+
+    /// What is the effect of this problem on the affected entity.
+    #[derive(
+        Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration, Serialize,
+    )]
+    #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+    #[repr(i32)]
+    pub enum Effect {
+        NoService = 1,
+        ReducedService = 2,
+        SignificantDelays = 3,
+        Detour = 4,
+        AdditionalService = 5,
+        ModifiedService = 6,
+        OtherEffect = 7,
+        UnknownEffect = 8,
+        StopMoved = 9,
+        NoEffect = 10,
+        AccessibilityIssue = 11,
+    }
+
+    /// The severity of the alert.
+    #[derive(
+        Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration, Serialize,
+    )]
+    #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+    #[repr(i32)]
+    pub enum SeverityLevel {
+        UnknownSeverity = 1,
+        Info = 2,
+        Warning = 3,
+        Severe = 4,
+    }
+}
+
+/// A time interval. The interval is considered active at time t if t is greater than or
+/// equal to the start time and less than the end time.
+#[derive(Clone, PartialEq, ::prost::Message, Serialize)]
+pub struct TimeRange {
+    /// Start time, in POSIX time. If missing, the interval starts at minus infinity.
+    #[prost(uint64, optional, tag = "1")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start: Option<u64>,
+    /// End time, in POSIX time. If missing, the interval ends at plus infinity.
+    #[prost(uint64, optional, tag = "2")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end: Option<u64>,
+}
+
+/// A position.
+#[derive(Clone, PartialEq, ::prost::Message, Serialize)]
+pub struct Position {
+    /// Degrees North, in the WGS-84 coordinate system.
+    #[prost(float, required, tag = "1")]
+    pub latitude: f32,
+    /// Degrees East, in the WGS-84 coordinate system.
+    #[prost(float, required, tag = "2")]
+    pub longitude: f32,
+    /// Bearing, in degrees, clockwise from North.
+    #[prost(float, optional, tag = "3")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bearing: Option<f32>,
+    /// Odometer value, in meters.
+    #[prost(double, optional, tag = "4")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub odometer: Option<f64>,
+    /// Momentary speed measured by the vehicle, in meters per second.
+    #[prost(float, optional, tag = "5")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speed: Option<f32>,
+}
+
+/// A descriptor that identifies an instance of a GTFS trip, or all instances of a trip
+/// along a route.
+#[derive(Clone, PartialEq, ::prost::Message, Serialize)]
+pub struct TripDescriptor {
+    /// The trip_id from the GTFS feed that this selector refers to.
+    #[prost(string, optional, tag = "1")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trip_id: Option<String>,
+    /// The route_id from the GTFS that this selector refers to.
+    #[prost(string, optional, tag = "5")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub route_id: Option<String>,
+    /// The direction_id from the GTFS feed trips.txt file.
+    #[prost(uint32, optional, tag = "6")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub direction_id: Option<u32>,
+    /// The initially scheduled start time of this trip instance, e.g. "25:15:00".
+    #[prost(string, optional, tag = "2")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_time: Option<String>,
+    /// The scheduled start date of this trip instance, in YYYYMMDD format.
+    #[prost(string, optional, tag = "3")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_date: Option<String>,
+    #[prost(
+        enumeration = "trip_descriptor::ScheduleRelationship",
+        optional,
+        tag = "4"
+    )]
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "crate::realtime::proto::enumeration::<trip_descriptor::ScheduleRelationship, _>"
+    )]
+    pub schedule_relationship: Option<i32>,
+    #[prost(message, optional, tag = "7")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modified_trip: Option<trip_descriptor::ModifiedTripSelector>,
+    /// New York City Transit extension.
+    #[prost(message, optional, tag = "1001")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nyct_trip_descriptor: Option<NyctTripDescriptor>,
+}
+
+pub mod trip_descriptor {
+    use serde::Serialize;
+
+    /// The relation between this trip and the static schedule.
+    #[derive(
+        Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration, Serialize,
+    )]
+    #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+    #[repr(i32)]
+    pub enum ScheduleRelationship {
+        /// Trip that is running in accordance with its GTFS schedule.
+        Scheduled = 0,
+        /// An extra trip that was added in addition to a running schedule.
+        Added = 1,
+        /// A trip that is running with no schedule associated to it.
+        Unscheduled = 2,
+        /// A trip that existed in the schedule but was removed.
+        Canceled = 3,
+        /// A trip that replaces an existing trip in the schedule.
+        Replacement = 5,
+        /// An extra trip that was added in addition to a running schedule, for example,
+        /// to replace a broken vehicle.
+        Duplicated = 6,
+        /// A trip that existed in the schedule but was removed and must not be shown to users.
+        Deleted = 7,
+        /// A new trip that is unrelated to any existing trip.
+        New = 8,
+    }
+
+    /// Selects a trip modified by a TripModifications entity.
+    #[derive(Clone, PartialEq, ::prost::Message, Serialize)]
+    pub struct ModifiedTripSelector {
+        #[prost(string, optional, tag = "1")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub modifications_id: Option<String>,
+        #[prost(string, optional, tag = "2")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub affected_trip_id: Option<String>,
+        #[prost(string, optional, tag = "3")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub start_time: Option<String>,
+        #[prost(string, optional, tag = "4")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub start_date: Option<String>,
+    }
+}
+
+/// Identification information for the vehicle performing the trip.
+#[derive(Clone, PartialEq, ::prost::Message, Serialize)]
+pub struct VehicleDescriptor {
+    /// Internal system identification of the vehicle.
+    #[prost(string, optional, tag = "1")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// User visible label.
+    #[prost(string, optional, tag = "2")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// The license plate of the vehicle.
+    #[prost(string, optional, tag = "3")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license_plate: Option<String>,
+    #[prost(
+        enumeration = "vehicle_descriptor::WheelchairAccessible",
+        optional,
+        tag = "4",
+        default = "NoValue"
+    )]
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "crate::realtime::proto::enumeration::<vehicle_descriptor::WheelchairAccessible, _>"
+    )]
+    pub wheelchair_accessible: Option<i32>,
+}
+
+pub mod vehicle_descriptor {
+    use serde::Serialize;
+
+    #[derive(
+        Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration, Serialize,
+    )]
+    #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+    #[repr(i32)]
+    pub enum WheelchairAccessible {
+        NoValue = 0,
+        Unknown = 1,
+        WheelchairAccessible = 2,
+        WheelchairInaccessible = 3,
+    }
+}
+
+/// A selector for an entity in a GTFS feed.
+#[derive(Clone, PartialEq, ::prost::Message, Serialize)]
+pub struct EntitySelector {
+    #[prost(string, optional, tag = "1")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agency_id: Option<String>,
+    #[prost(string, optional, tag = "2")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub route_id: Option<String>,
+    /// Corresponds to route_type in GTFS.
+    #[prost(int32, optional, tag = "3")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub route_type: Option<i32>,
+    #[prost(message, optional, tag = "4")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trip: Option<TripDescriptor>,
+    #[prost(string, optional, tag = "5")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_id: Option<String>,
+    /// Corresponds to trip direction_id in GTFS trips.txt.
+    #[prost(uint32, optional, tag = "6")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub direction_id: Option<u32>,
+}
+
+/// An internationalized message containing per-language versions of a snippet of text
+/// or a URL.
+#[derive(Clone, PartialEq, ::prost::Message, Serialize)]
+pub struct TranslatedString {
+    /// At least one translation must be provided.
+    #[prost(message, repeated, tag = "1")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub translation: Vec<translated_string::Translation>,
+}
+
+pub mod translated_string {
+    use serde::Serialize;
+
+    #[derive(Clone, PartialEq, ::prost::Message, Serialize)]
+    pub struct Translation {
+        /// A UTF-8 string containing the message.
+        #[prost(string, required, tag = "1")]
+        pub text: String,
+        /// BCP-47 language code.
+        #[prost(string, optional, tag = "2")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub language: Option<String>,
+    }
+}
+
+/// An internationalized image containing per-language versions of a URL linking to an image.
+#[derive(Clone, PartialEq, ::prost::Message, Serialize)]
+pub struct TranslatedImage {
+    /// At least one localized image must be provided.
+    #[prost(message, repeated, tag = "1")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub localized_image: Vec<translated_image::LocalizedImage>,
+}
+
+pub mod translated_image {
+    use serde::Serialize;
+
+    #[derive(Clone, PartialEq, ::prost::Message, Serialize)]
+    pub struct LocalizedImage {
+        /// String containing an URL linking to an image.
+        #[prost(string, required, tag = "1")]
+        pub url: String,
+        /// IANA media type.
+        #[prost(string, required, tag = "2")]
+        pub media_type: String,
+        /// BCP-47 language code.
+        #[prost(string, optional, tag = "3")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub language: Option<String>,
+    }
+}
+
+/// New York City Transit extension of the feed header (`nyct-subway.proto`).
+#[derive(Clone, PartialEq, ::prost::Message, Serialize)]
+pub struct NyctFeedHeader {
+    /// Version of the NYCT Subway extensions.
+    #[prost(string, required, tag = "1")]
+    pub nyct_subway_version: String,
+    /// Routes for which trips are replaced by the feed within the replacement period.
+    #[prost(message, repeated, tag = "2")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub trip_replacement_period: Vec<TripReplacementPeriod>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message, Serialize)]
+pub struct TripReplacementPeriod {
+    #[prost(string, optional, tag = "1")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub route_id: Option<String>,
+    /// The start time is omitted, the end time is the end of the replacement period.
+    #[prost(message, optional, tag = "2")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replacement_period: Option<TimeRange>,
+}
+
+/// New York City Transit extension of the trip descriptor.
+#[derive(Clone, PartialEq, ::prost::Message, Serialize)]
+pub struct NyctTripDescriptor {
+    /// The nyct train id, e.g. "06 0123+ PEL/BBR".
+    #[prost(string, optional, tag = "1")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub train_id: Option<String>,
+    /// Whether a train has been assigned to the trip.
+    #[prost(bool, optional, tag = "2")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_assigned: Option<bool>,
+    #[prost(enumeration = "nyct_trip_descriptor::Direction", optional, tag = "3")]
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "crate::realtime::proto::enumeration::<nyct_trip_descriptor::Direction, _>"
+    )]
+    pub direction: Option<i32>,
+}
+
+pub mod nyct_trip_descriptor {
+    use serde::Serialize;
+
+    #[derive(
+        Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration, Serialize,
+    )]
+    #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+    #[repr(i32)]
+    pub enum Direction {
+        North = 1,
+        East = 2,
+        South = 3,
+        West = 4,
+    }
+}
+
+/// New York City Transit extension of the stop time update.
+#[derive(Clone, PartialEq, ::prost::Message, Serialize)]
+pub struct NyctStopTimeUpdate {
+    /// The track the train is scheduled to use at the stop.
+    #[prost(string, optional, tag = "1")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scheduled_track: Option<String>,
+    /// The track the train is expected to actually use at the stop.
+    #[prost(string, optional, tag = "2")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actual_track: Option<String>,
+}
+
+/// Enumerations of the protocol, which prost stores as their number.
+pub(crate) trait Enumeration: Serialize + Sized {
+    fn from_value(value: i32) -> Option<Self>;
+}
+
+macro_rules! enumerations {
+    ($($enumeration:ty),*) => {
+        $(
+            impl Enumeration for $enumeration {
+                fn from_value(value: i32) -> Option<Self> {
+                    Self::from_i32(value)
+                }
+            }
+        )*
+    };
+}
+
+enumerations!(
+    feed_header::Incrementality,
+    trip_update::stop_time_update::ScheduleRelationship,
+    vehicle_position::VehicleStopStatus,
+    vehicle_position::CongestionLevel,
+    vehicle_position::OccupancyStatus,
+    alert::Cause,
+    alert::Effect,
+    alert::SeverityLevel,
+    trip_descriptor::ScheduleRelationship,
+    vehicle_descriptor::WheelchairAccessible,
+    nyct_trip_descriptor::Direction
+);
+
+/// Serializes an enumeration field by the name of its value, or by its number when the
+/// value is not known.
+pub(crate) fn enumeration<E, S>(value: &Option<i32>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    E: Enumeration,
+    S: Serializer,
+{
+    match value {
+        Some(value) => match E::from_value(*value) {
+            Some(known) => known.serialize(serializer),
+            None => serializer.serialize_i32(*value),
+        },
+        None => serializer.serialize_none(),
+    }
+}
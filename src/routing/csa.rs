@@ -0,0 +1,470 @@
+//! Connection Scan Algorithm (Dibbelt et al., 2013).
+//!
+//! The runs of a timetable are split into elementary connections between consecutive stops,
+//! sorted by departure. Earliest arrivals are found by scanning the connections forwards
+//! from the departure time, profiles by scanning them backwards from the end of the day.
+
+use std::{collections::HashSet, io};
+
+use super::{RoutingOptions, Timetable};
+use crate::error::Error;
+
+/// A run going from a stop of its pattern to the next one.
+struct Connection {
+    run: usize,
+    /// Position of the departure stop in the pattern of the run
+    position: usize,
+    from: usize,
+    to: usize,
+    departure: i64,
+    arrival: i64,
+}
+
+/// A Pareto-optimal journey of a profile: no other journey departs later and arrives
+/// earlier. Times are POSIX.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ProfileEntry {
+    pub departure: i64,
+    pub arrival: i64,
+}
+
+impl ProfileEntry {
+    /// Travel time, in seconds.
+    pub fn travel_time(&self) -> i64 {
+        self.arrival - self.departure
+    }
+}
+
+/// The connections of a timetable, sorted by departure.
+///
+/// Unlike [raptor](Timetable::raptor), the number of transfers is not limited.
+///
+/// ```
+/// use chrono::{NaiveDate, TimeZone, Utc};
+/// use gtfs_parser::{
+///     routing::{csa::ConnectionScan, RoutingOptions, Schedule, Timetable},
+///     GtfsReader,
+/// };
+///
+/// let mut gtfs = GtfsReader::from_path("./resources/zips/gtfs.zip").unwrap();
+/// let schedule = Schedule::from_reader(&mut gtfs).unwrap();
+/// let date = NaiveDate::from_ymd_opt(2006, 7, 3).unwrap();
+/// let timetable = Timetable::new(&schedule, date, &Utc);
+/// let scan = ConnectionScan::new(&timetable);
+///
+/// let departure = Utc.with_ymd_and_hms(2006, 7, 3, 6, 0, 0).unwrap().timestamp();
+/// let stops = ["stop1", "stop2", "stop3"];
+/// let matrix = scan.travel_time_matrix(&stops, departure, &RoutingOptions::default());
+/// let mut csv = Vec::new();
+/// matrix.write_csv(&mut csv).unwrap();
+/// ```
+pub struct ConnectionScan<'t, 's> {
+    pub(super) timetable: &'t Timetable<'s>,
+    connections: Vec<Connection>,
+}
+
+impl<'t, 's> ConnectionScan<'t, 's> {
+    pub fn new(timetable: &'t Timetable<'s>) -> Self {
+        let mut connections = Vec::new();
+        for pattern in &timetable.patterns {
+            for run in &pattern.runs {
+                let times = &timetable.runs[*run].times;
+                for position in 1..pattern.stops.len() {
+                    connections.push(Connection {
+                        run: *run,
+                        position: position - 1,
+                        from: pattern.stops[position - 1],
+                        to: pattern.stops[position],
+                        departure: times[position - 1].1,
+                        arrival: times[position].0,
+                    });
+                }
+            }
+        }
+        // Connections of a run without travel time must be scanned in the order of the run
+        connections.sort_by_key(|c| (c.departure, c.arrival, c.run, c.position));
+
+        Self {
+            timetable,
+            connections,
+        }
+    }
+
+    /// Earliest arrival at a stop or station, as a POSIX time, departing from another at or
+    /// after a POSIX time.
+    pub fn earliest_arrival(
+        &self,
+        from: &str,
+        to: &str,
+        departure: i64,
+        options: &RoutingOptions,
+    ) -> Option<i64> {
+        let timetable = self.timetable;
+        let targets = timetable.resolve(to);
+        let sources: Vec<(usize, i64)> = timetable
+            .resolve(from)
+            .into_iter()
+            .map(|source| (source, departure - timetable.day_start))
+            .collect();
+        let arrivals = self.scan(&sources, &targets, options);
+        targets
+            .iter()
+            .map(|target| arrivals[*target])
+            .min()
+            .filter(|arrival| *arrival != i64::MAX)
+            .map(|arrival| arrival + timetable.day_start)
+    }
+
+    /// Journeys from a stop or station to another departing between two POSIX times, which
+    /// no other journey departs later and arrives earlier than. They are sorted by departure.
+    pub fn profile(
+        &self,
+        from: &str,
+        to: &str,
+        earliest_departure: i64,
+        latest_departure: i64,
+        options: &RoutingOptions,
+    ) -> Vec<ProfileEntry> {
+        let timetable = self.timetable;
+        let earliest_departure = earliest_departure - timetable.day_start;
+        let latest_departure = latest_departure - timetable.day_start;
+        let targets: HashSet<usize> = timetable.resolve(to).into_iter().collect();
+
+        // Time to walk from each stop to the closest target
+        let mut to_target = vec![i64::MAX; timetable.stops.len()];
+        for (stop, footpaths) in timetable.footpaths.iter().enumerate() {
+            if targets.contains(&stop) {
+                to_target[stop] = 0;
+            }
+            for (to, duration) in footpaths {
+                if targets.contains(to) {
+                    to_target[stop] = to_target[stop].min(*duration);
+                }
+            }
+        }
+
+        let mut profiles: Vec<Vec<(i64, i64)>> = vec![Vec::new(); timetable.stops.len()];
+        let mut in_run = vec![i64::MAX; timetable.runs.len()];
+        let first = self
+            .connections
+            .partition_point(|c| c.departure < earliest_departure);
+        for c in self.connections[first..].iter().rev() {
+            let run = &timetable.runs[c.run];
+            if !run.allowed(options) {
+                continue;
+            }
+
+            let mut arrival = in_run[c.run];
+            if run.drop_off(c.position + 1) && timetable.accessible(c.to, options) {
+                if to_target[c.to] != i64::MAX {
+                    arrival = arrival.min(c.arrival + to_target[c.to]);
+                }
+                if let Some(change_time) = timetable.change_time(c.to, options) {
+                    arrival = arrival.min(earliest(&profiles[c.to], c.arrival + change_time));
+                }
+                for (to, duration) in &timetable.footpaths[c.to] {
+                    arrival = arrival.min(earliest(&profiles[*to], c.arrival + duration));
+                }
+            }
+            if arrival == i64::MAX {
+                continue;
+            }
+
+            in_run[c.run] = arrival;
+            if run.pickup(c.position) && timetable.accessible(c.from, options) {
+                insert(&mut profiles[c.from], c.departure, arrival);
+            }
+        }
+
+        // Journeys from the origin, either boarding there or walking to another stop first
+        let mut profile = Vec::new();
+        for source in timetable.resolve(from) {
+            let walks = std::iter::once((source, 0)).chain(timetable.footpaths[source].clone());
+            for (stop, duration) in walks {
+                for (departure, arrival) in &profiles[stop] {
+                    let departure = departure - duration;
+                    if (earliest_departure..=latest_departure).contains(&departure) {
+                        insert(&mut profile, departure, *arrival);
+                    }
+                }
+            }
+        }
+
+        profile
+            .into_iter()
+            .rev()
+            .map(|(departure, arrival)| ProfileEntry {
+                departure: departure + timetable.day_start,
+                arrival: arrival + timetable.day_start,
+            })
+            .collect()
+    }
+
+    /// Travel times between every pair of stops or stations, departing at a POSIX time.
+    pub fn travel_time_matrix(
+        &self,
+        stops: &[&str],
+        departure: i64,
+        options: &RoutingOptions,
+    ) -> TravelTimeMatrix {
+        let timetable = self.timetable;
+        let resolved: Vec<Vec<usize>> = stops.iter().map(|stop| timetable.resolve(stop)).collect();
+        let times = resolved
+            .iter()
+            .map(|sources| {
+                let sources: Vec<(usize, i64)> = sources
+                    .iter()
+                    .map(|source| (*source, departure - timetable.day_start))
+                    .collect();
+                let arrivals = self.scan(&sources, &[], options);
+                resolved
+                    .iter()
+                    .map(|targets| {
+                        targets
+                            .iter()
+                            .map(|target| arrivals[*target])
+                            .min()
+                            .filter(|arrival| *arrival != i64::MAX)
+                            .map(|arrival| arrival + timetable.day_start - departure)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        TravelTimeMatrix {
+            stops: stops.iter().map(|stop| stop.to_string()).collect(),
+            departure,
+            times,
+        }
+    }
+
+    /// Earliest arrival at every stop from the sources, left at their own time, `i64::MAX`
+    /// for the stops which cannot be reached. The scan stops once the connections depart
+    /// after the earliest arrival at a target, if any.
+    pub(super) fn scan(
+        &self,
+        sources: &[(usize, i64)],
+        targets: &[usize],
+        options: &RoutingOptions,
+    ) -> Vec<i64> {
+        let timetable = self.timetable;
+        let mut arrivals = vec![i64::MAX; timetable.stops.len()];
+        // Whether the earliest arrival at each stop is by a vehicle, to change from
+        let mut by_vehicle = vec![false; timetable.stops.len()];
+        let mut boarded = vec![false; timetable.runs.len()];
+        for (source, departure) in sources {
+            arrivals[*source] = arrivals[*source].min(*departure);
+            for (to, duration) in &timetable.footpaths[*source] {
+                arrivals[*to] = arrivals[*to].min(departure + duration);
+            }
+        }
+
+        let departure = match sources.iter().map(|(_, departure)| *departure).min() {
+            Some(departure) => departure,
+            None => return arrivals,
+        };
+        let first = self
+            .connections
+            .partition_point(|c| c.departure < departure);
+        for c in &self.connections[first..] {
+            let best_target = targets.iter().map(|target| arrivals[*target]).min();
+            if let Some(best) = best_target {
+                if c.departure >= best {
+                    break;
+                }
+            }
+
+            let run = &timetable.runs[c.run];
+            if !boarded[c.run] {
+                let ready = match (arrivals[c.from], by_vehicle[c.from]) {
+                    (i64::MAX, _) => continue,
+                    (arrival, true) => match timetable.change_time(c.from, options) {
+                        Some(change_time) => arrival + change_time,
+                        None => continue,
+                    },
+                    (arrival, false) => arrival,
+                };
+                if ready > c.departure
+                    || !run.pickup(c.position)
+                    || !run.allowed(options)
+                    || !timetable.accessible(c.from, options)
+                {
+                    continue;
+                }
+                boarded[c.run] = true;
+            }
+
+            if c.arrival < arrivals[c.to]
+                && run.drop_off(c.position + 1)
+                && timetable.accessible(c.to, options)
+            {
+                arrivals[c.to] = c.arrival;
+                by_vehicle[c.to] = true;
+                for (to, duration) in &timetable.footpaths[c.to] {
+                    if c.arrival + duration < arrivals[*to] {
+                        arrivals[*to] = c.arrival + duration;
+                        by_vehicle[*to] = false;
+                    }
+                }
+            }
+        }
+
+        arrivals
+    }
+}
+
+/// Earliest arrival of a profile, sorted by decreasing departure, departing at or after a
+/// time.
+fn earliest(profile: &[(i64, i64)], ready: i64) -> i64 {
+    match profile.partition_point(|(departure, _)| *departure >= ready) {
+        0 => i64::MAX,
+        count => profile[count - 1].1,
+    }
+}
+
+/// Inserts a journey in a profile sorted by decreasing departure, unless another departs
+/// later and arrives earlier, removing those it is better than.
+fn insert(profile: &mut Vec<(i64, i64)>, departure: i64, arrival: i64) {
+    if earliest(profile, departure) <= arrival {
+        return;
+    }
+    let position = profile.partition_point(|(other, _)| *other > departure);
+    let dominated = profile[position..]
+        .iter()
+        .take_while(|(_, other)| *other >= arrival)
+        .count();
+    profile.splice(position..position + dominated, [(departure, arrival)]);
+}
+
+/// Travel times between stops, in seconds, departing at the same time.
+#[derive(Debug, Clone)]
+pub struct TravelTimeMatrix {
+    pub stops: Vec<String>,
+    /// POSIX time of the departures
+    pub departure: i64,
+    /// Travel time from each stop to each other, none when it cannot be reached
+    pub times: Vec<Vec<Option<i64>>>,
+}
+
+impl TravelTimeMatrix {
+    pub fn travel_time(&self, from: &str, to: &str) -> Option<i64> {
+        let from = self.stops.iter().position(|stop| stop == from)?;
+        let to = self.stops.iter().position(|stop| stop == to)?;
+        self.times[from][to]
+    }
+
+    /// Writes the matrix as csv, with a `from_stop_id,to_stop_id,travel_time` row per pair
+    /// of stops. The travel time is empty when the stop cannot be reached.
+    pub fn write_csv<W: io::Write>(&self, writer: W) -> Result<(), Error> {
+        let mut writer = csv::Writer::from_writer(writer);
+        writer.write_record(["from_stop_id", "to_stop_id", "travel_time"])?;
+        for (from, times) in self.stops.iter().zip(&self.times) {
+            for (to, time) in self.stops.iter().zip(times) {
+                let time = time.map(|time| time.to_string()).unwrap_or_default();
+                writer.write_record([from, to, &time])?;
+            }
+        }
+        writer.flush()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::routing::test::{at, date, feed, WALK_B_C};
+    use chrono::Utc;
+
+    #[test]
+    fn earliest_arrival() {
+        let schedule = feed(WALK_B_C);
+        let timetable = Timetable::new(&schedule, date(), &Utc);
+        let scan = ConnectionScan::new(&timetable);
+        let options = RoutingOptions::default();
+
+        assert_eq!(
+            scan.earliest_arrival("A", "D", at(7, 50), &options),
+            Some(at(8, 40))
+        );
+        assert_eq!(
+            scan.earliest_arrival("A", "D", at(8, 6), &options),
+            Some(at(25, 0))
+        );
+        assert_eq!(
+            scan.earliest_arrival("A", "E", at(7, 50), &options),
+            Some(at(8, 50))
+        );
+        let wheelchair = RoutingOptions {
+            wheelchair: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            scan.earliest_arrival("A", "E", at(7, 50), &wheelchair),
+            None
+        );
+        assert_eq!(scan.earliest_arrival("D", "A", at(7, 50), &options), None);
+    }
+
+    #[test]
+    fn profile() {
+        let schedule = feed(WALK_B_C);
+        let timetable = Timetable::new(&schedule, date(), &Utc);
+        let scan = ConnectionScan::new(&timetable);
+        let options = RoutingOptions::default();
+
+        // The direct line leaves earlier and arrives later than the change at B
+        let profile = scan.profile("A", "D", at(7, 0), at(26, 0), &options);
+        assert_eq!(
+            profile,
+            [
+                ProfileEntry {
+                    departure: at(8, 5),
+                    arrival: at(8, 40)
+                },
+                ProfileEntry {
+                    departure: at(24, 30),
+                    arrival: at(25, 0)
+                },
+            ]
+        );
+        assert_eq!(profile[0].travel_time(), 35 * 60);
+
+        let bikes = RoutingOptions {
+            bikes: true,
+            ..Default::default()
+        };
+        let profile = scan.profile("A", "D", at(7, 0), at(9, 0), &bikes);
+        assert_eq!(
+            profile,
+            [ProfileEntry {
+                departure: at(8, 0),
+                arrival: at(9, 0)
+            }]
+        );
+
+        // Walking from B to C first
+        let profile = scan.profile("B", "E", at(7, 0), at(9, 0), &options);
+        assert_eq!(profile[0].departure, at(8, 23));
+    }
+
+    #[test]
+    fn travel_time_matrix() {
+        let schedule = feed(WALK_B_C);
+        let timetable = Timetable::new(&schedule, date(), &Utc);
+        let scan = ConnectionScan::new(&timetable);
+        let matrix = scan.travel_time_matrix(&["A", "B", "D"], at(7, 50), &Default::default());
+
+        assert_eq!(matrix.travel_time("A", "A"), Some(0));
+        assert_eq!(matrix.travel_time("A", "B"), Some(30 * 60));
+        assert_eq!(matrix.travel_time("A", "D"), Some(50 * 60));
+        assert_eq!(matrix.travel_time("B", "D"), Some(50 * 60));
+        assert_eq!(matrix.travel_time("D", "A"), None);
+
+        let mut csv = Vec::new();
+        matrix.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.starts_with("from_stop_id,to_stop_id,travel_time\nA,A,0\nA,B,1800\n"));
+        assert!(csv.contains("\nD,A,\n"));
+    }
+}
@@ -0,0 +1,202 @@
+//! Stops and areas reachable from a location within a travel time, walking to nearby stops
+//! and riding the vehicles of the timetable.
+
+use serde_json::{json, Value};
+
+use super::{csa::ConnectionScan, RoutingOptions};
+use crate::{geometry::Point, structures::stops::Stop};
+
+/// Number of sides of the polygons drawn around reachable points.
+const CIRCLE_SIDES: usize = 32;
+
+/// How isochrones are computed.
+#[derive(Debug, Clone)]
+pub struct IsochroneOptions {
+    pub routing: RoutingOptions,
+    /// Walking speed, in meters per second
+    pub walking_speed: f64,
+    /// Longest walk from the origin to a stop, and from a stop to the reachable area, in meters
+    pub max_walking_distance: f64,
+}
+
+impl Default for IsochroneOptions {
+    fn default() -> Self {
+        Self {
+            routing: RoutingOptions::default(),
+            walking_speed: 1.4,
+            max_walking_distance: 800.0,
+        }
+    }
+}
+
+/// A stop reachable from the origin of an isochrone.
+#[derive(Debug, Clone)]
+pub struct ReachableStop<'s> {
+    pub stop: &'s Stop,
+    /// Earliest arrival, as a POSIX time
+    pub arrival: i64,
+}
+
+impl<'t, 's> ConnectionScan<'t, 's> {
+    /// Stops reachable from a location within `max_duration` seconds of a POSIX time, by
+    /// earliest arrival.
+    pub fn reachable_stops(
+        &self,
+        origin: &Point,
+        departure: i64,
+        max_duration: i64,
+        options: &IsochroneOptions,
+    ) -> Vec<ReachableStop<'s>> {
+        let timetable = self.timetable;
+        let sources: Vec<(usize, i64)> = timetable
+            .stops
+            .iter()
+            .enumerate()
+            .filter_map(|(index, stop)| {
+                let distance = origin.distance(&stop.point()?);
+                if distance > options.max_walking_distance {
+                    return None;
+                }
+                let walk = (distance / options.walking_speed).ceil() as i64;
+                Some((index, departure - timetable.day_start + walk))
+            })
+            .collect();
+
+        let latest = departure - timetable.day_start + max_duration;
+        let mut reachable: Vec<ReachableStop> = self
+            .scan(&sources, &[], &options.routing)
+            .into_iter()
+            .enumerate()
+            .filter(|(_, arrival)| *arrival <= latest)
+            .map(|(index, arrival)| ReachableStop {
+                stop: timetable.stops[index],
+                arrival: arrival + timetable.day_start,
+            })
+            .collect();
+        reachable.sort_by(|a, b| a.arrival.cmp(&b.arrival).then(a.stop.id.cmp(&b.stop.id)));
+        reachable
+    }
+
+    /// Areas reachable from a location within each band of seconds after a POSIX time, as a
+    /// GeoJSON FeatureCollection with a feature per band.
+    ///
+    /// An area is a GeometryCollection of the polygons around the origin and the reachable
+    /// stops, as far as can be walked in the time left. The polygons are not merged and
+    /// usually overlap, which a MultiPolygon does not allow: they have to be unioned, e.g.
+    /// by a GIS, where a single outline of the area is needed.
+    pub fn isochrones(
+        &self,
+        origin: &Point,
+        departure: i64,
+        bands: &[i64],
+        options: &IsochroneOptions,
+    ) -> Value {
+        let max_duration = bands.iter().copied().max().unwrap_or_default();
+        let reachable = self.reachable_stops(origin, departure, max_duration, options);
+
+        let features: Vec<Value> = bands
+            .iter()
+            .map(|band| {
+                let points =
+                    std::iter::once((*origin, departure)).chain(reachable.iter().filter_map(
+                        |reachable| Some((reachable.stop.point()?, reachable.arrival)),
+                    ));
+                let geometries: Vec<Value> = points
+                    .filter(|(_, arrival)| *arrival < departure + band)
+                    .map(|(point, arrival)| {
+                        let radius = ((departure + band - arrival) as f64 * options.walking_speed)
+                            .min(options.max_walking_distance);
+                        json!({
+                            "type": "Polygon",
+                            "coordinates": circle(&point, radius),
+                        })
+                    })
+                    .collect();
+
+                json!({
+                    "type": "Feature",
+                    "geometry": {
+                        "type": "GeometryCollection",
+                        "geometries": geometries,
+                    },
+                    "properties": {
+                        "time": band,
+                    },
+                })
+            })
+            .collect();
+
+        json!({ "type": "FeatureCollection", "features": features })
+    }
+}
+
+/// Coordinates of a polygon approximating a circle, in counterclockwise order.
+fn circle(center: &Point, radius: f64) -> Value {
+    let ring: Vec<Value> = (0..=CIRCLE_SIDES)
+        .map(|side| {
+            let bearing = 360.0 - 360.0 * (side % CIRCLE_SIDES) as f64 / CIRCLE_SIDES as f64;
+            let point = center.destination(bearing, radius);
+            json!([point.longitude, point.latitude])
+        })
+        .collect();
+    json!([ring])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::routing::{
+        test::{at, date, feed, WALK_B_C},
+        Timetable,
+    };
+    use chrono::Utc;
+
+    #[test]
+    fn isochrones() {
+        let schedule = feed(WALK_B_C);
+        let timetable = Timetable::new(&schedule, date(), &Utc);
+        let scan = ConnectionScan::new(&timetable);
+        let options = IsochroneOptions::default();
+        // About 560m west of A
+        let origin = Point::new(0.0, -0.005);
+
+        let reachable = scan.reachable_stops(&origin, at(7, 50), 55 * 60, &options);
+        let stops: Vec<&str> = reachable.iter().map(|r| r.stop.id.as_str()).collect();
+        assert_eq!(stops, ["A", "B", "C", "D"]);
+        assert_eq!(reachable[1].arrival, at(8, 20));
+        assert_eq!(reachable[2].arrival, at(8, 22));
+        assert_eq!(reachable[3].arrival, at(8, 40));
+
+        // Too far to walk to A
+        let far = Point::new(0.0, -0.01);
+        assert!(scan
+            .reachable_stops(&far, at(7, 50), 55 * 60, &options)
+            .is_empty());
+
+        let geojson = scan.isochrones(&origin, at(7, 50), &[15 * 60, 60 * 60], &options);
+        let features = geojson["features"].as_array().unwrap();
+        assert_eq!(features.len(), 2);
+        assert_eq!(features[0]["properties"]["time"], 900);
+        // The origin and A within 15 minutes, every stop but E within an hour
+        assert_eq!(
+            features[0]["geometry"]["geometries"]
+                .as_array()
+                .unwrap()
+                .len(),
+            2
+        );
+        assert_eq!(
+            features[1]["geometry"]["geometries"]
+                .as_array()
+                .unwrap()
+                .len(),
+            5
+        );
+
+        let polygon = &features[0]["geometry"]["geometries"][0];
+        assert_eq!(polygon["type"], "Polygon");
+        let ring = polygon["coordinates"][0].as_array().unwrap();
+        assert_eq!(ring.len(), CIRCLE_SIDES + 1);
+        assert_eq!(ring.first(), ring.last());
+    }
+}
@@ -0,0 +1,508 @@
+//! Journey planning over the static timetable of a service date.
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::{Duration, NaiveDate, TimeZone};
+
+use crate::{
+    error::Error,
+    structures::{
+        calendar::{self, service_day_start, Calendar},
+        calendar_dates::CalendarDate,
+        frequencies::Frequency,
+        routes::Route,
+        stop_times::PickupDropOffType,
+        stops::{Stop, WheelchairBoardingAvailable},
+        transfers::{Transfer, TransferType},
+        trips::{BikesAllowed, Trip},
+    },
+    GtfsReader,
+};
+
+pub mod csa;
+pub mod isochrones;
+pub mod raptor;
+
+/// The files of a feed needed to plan journeys.
+#[derive(Debug, Default)]
+pub struct Schedule {
+    pub routes: Vec<Route>,
+    pub trips: HashMap<String, Trip>,
+    pub stops: Vec<Stop>,
+    pub transfers: Vec<Transfer>,
+    pub frequencies: Vec<Frequency>,
+    pub calendar: Vec<Calendar>,
+    pub calendar_dates: Vec<CalendarDate>,
+}
+
+impl Schedule {
+    /// Reads the schedule of a feed, whose optional files may be missing.
+    pub fn from_reader(gtfs: &mut GtfsReader) -> Result<Schedule, Error> {
+        Ok(Schedule {
+            routes: gtfs.routes()?,
+            trips: gtfs.trips()?,
+            stops: gtfs.stops()?,
+            transfers: gtfs.optional_file("transfers.txt", GtfsReader::transfers)?,
+            frequencies: gtfs.optional_file("frequencies.txt", GtfsReader::frequencies)?,
+            calendar: gtfs.optional_file("calendar.txt", GtfsReader::calendar)?,
+            calendar_dates: gtfs.optional_file("calendar_dates.txt", GtfsReader::calendar_dates)?,
+        })
+    }
+
+    /// Ids of the services running on the given date.
+    pub fn services_on(&self, date: NaiveDate) -> HashSet<String> {
+        calendar::services_on(&self.calendar, &self.calendar_dates, date)
+    }
+}
+
+/// Constraints on the journeys.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoutingOptions {
+    /// Only use wheelchair accessible trips, boarded and left at wheelchair accessible stops.
+    pub wheelchair: bool,
+    /// Only use trips allowing bikes.
+    pub bikes: bool,
+    pub max_transfers: usize,
+    /// Time needed to change vehicles at a stop, in seconds, unless transfers.txt tells otherwise.
+    pub min_change_time: u64,
+}
+
+impl Default for RoutingOptions {
+    fn default() -> Self {
+        Self {
+            wheelchair: false,
+            bikes: false,
+            max_transfers: 5,
+            min_change_time: 0,
+        }
+    }
+}
+
+/// A part of a journey.
+#[derive(Debug, Clone)]
+pub enum Leg<'s> {
+    Ride {
+        trip: &'s Trip,
+        /// Start time of frequency-based trips, in seconds since the start of the service day.
+        start_time: Option<u64>,
+        service_date: NaiveDate,
+        from: &'s Stop,
+        to: &'s Stop,
+        /// POSIX time of the departure from `from`.
+        departure: i64,
+        /// POSIX time of the arrival at `to`.
+        arrival: i64,
+    },
+    Walk {
+        from: &'s Stop,
+        to: &'s Stop,
+        departure: i64,
+        arrival: i64,
+    },
+}
+
+impl<'s> Leg<'s> {
+    pub fn departure(&self) -> i64 {
+        match self {
+            Leg::Ride { departure, .. } | Leg::Walk { departure, .. } => *departure,
+        }
+    }
+
+    pub fn arrival(&self) -> i64 {
+        match self {
+            Leg::Ride { arrival, .. } | Leg::Walk { arrival, .. } => *arrival,
+        }
+    }
+}
+
+/// A journey between two stops.
+#[derive(Debug, Clone)]
+pub struct Journey<'s> {
+    pub legs: Vec<Leg<'s>>,
+}
+
+impl<'s> Journey<'s> {
+    /// POSIX time of the departure of the first leg.
+    pub fn departure(&self) -> i64 {
+        self.legs.first().map_or(0, Leg::departure)
+    }
+
+    /// POSIX time of the arrival of the last leg.
+    pub fn arrival(&self) -> i64 {
+        self.legs.last().map_or(0, Leg::arrival)
+    }
+
+    /// Number of changes between vehicles.
+    pub fn transfers(&self) -> usize {
+        let rides = self
+            .legs
+            .iter()
+            .filter(|leg| matches!(leg, Leg::Ride { .. }))
+            .count();
+        rides.saturating_sub(1)
+    }
+}
+
+/// A vehicle run of a trip on a service date.
+struct Run<'s> {
+    trip: &'s Trip,
+    start_time: Option<u64>,
+    service_date: NaiveDate,
+    /// Index in the trip of the stop times with a time, which make up the pattern of the run
+    stop_times: Vec<usize>,
+    /// Arrival and departure at each stop of the pattern, in seconds since the start of the
+    /// service day of the timetable
+    times: Vec<(i64, i64)>,
+}
+
+impl<'s> Run<'s> {
+    fn pickup(&self, position: usize) -> bool {
+        let stop_time = &self.trip.stop_times[self.stop_times[position]];
+        stop_time.pickup_type != PickupDropOffType::NotAvailable
+    }
+
+    fn drop_off(&self, position: usize) -> bool {
+        let stop_time = &self.trip.stop_times[self.stop_times[position]];
+        stop_time.drop_off_type != PickupDropOffType::NotAvailable
+    }
+
+    fn allowed(&self, options: &RoutingOptions) -> bool {
+        (!options.wheelchair
+            || self.trip.wheelchair_accessible == WheelchairBoardingAvailable::Available)
+            && (!options.bikes || self.trip.bikes_allowed == BikesAllowed::AtLeastOneBike)
+    }
+}
+
+/// Runs serving the same stops in the same order, without overtaking each other.
+struct Pattern {
+    stops: Vec<usize>,
+    /// Runs sorted by departure, which is the same at every stop
+    runs: Vec<usize>,
+}
+
+/// The vehicle runs and footpaths of a service date, as used by the routing algorithms.
+///
+/// Times are relative to the start of the service day. The trips of the previous service
+/// day are included, as they may still run after midnight, and so are those of the next one,
+/// so that journeys starting late can go on the next morning.
+///
+/// ```
+/// use chrono::{NaiveDate, TimeZone, Utc};
+/// use gtfs_parser::{routing::{RoutingOptions, Schedule, Timetable}, GtfsReader};
+///
+/// let mut gtfs = GtfsReader::from_path("./resources/zips/gtfs.zip").unwrap();
+/// let schedule = Schedule::from_reader(&mut gtfs).unwrap();
+/// let date = NaiveDate::from_ymd_opt(2006, 7, 3).unwrap();
+/// let timetable = Timetable::new(&schedule, date, &Utc);
+///
+/// let departure = Utc.with_ymd_and_hms(2006, 7, 3, 6, 0, 0).unwrap().timestamp();
+/// let journeys = timetable.raptor("stop1", "stop3", departure, &RoutingOptions::default());
+/// assert!(journeys.iter().all(|journey| journey.arrival() > departure));
+/// ```
+pub struct Timetable<'s> {
+    stops: Vec<&'s Stop>,
+    stop_index: HashMap<&'s str, usize>,
+    /// Stops of each station
+    children: HashMap<usize, Vec<usize>>,
+    wheelchair_accessible: Vec<bool>,
+    runs: Vec<Run<'s>>,
+    patterns: Vec<Pattern>,
+    /// Patterns serving each stop, with the position of the stop in the pattern
+    stop_patterns: Vec<Vec<(usize, usize)>>,
+    /// Footpaths from each stop, with their duration
+    footpaths: Vec<Vec<(usize, i64)>>,
+    /// Time needed to change vehicles at stops, none when it is not possible
+    change_times: HashMap<usize, Option<i64>>,
+    day_start: i64,
+}
+
+impl<'s> Timetable<'s> {
+    /// Timetable of `date` in the time zone of the agency.
+    pub fn new<Tz: TimeZone>(schedule: &'s Schedule, date: NaiveDate, timezone: &Tz) -> Self {
+        let stops: Vec<&Stop> = schedule.stops.iter().collect();
+        let stop_index: HashMap<&str, usize> = stops
+            .iter()
+            .enumerate()
+            .map(|(index, stop)| (stop.id.as_str(), index))
+            .collect();
+
+        let mut children: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (index, stop) in stops.iter().enumerate() {
+            if let Some(parent) = stop
+                .parent_station
+                .as_deref()
+                .and_then(|p| stop_index.get(p))
+            {
+                children.entry(*parent).or_default().push(index);
+            }
+        }
+        let wheelchair_accessible = stops
+            .iter()
+            .map(|stop| match stop.wheelchair_boarding {
+                WheelchairBoardingAvailable::Available => true,
+                WheelchairBoardingAvailable::InformationNotAvailable => {
+                    stop.parent_station
+                        .as_deref()
+                        .and_then(|parent| stop_index.get(parent))
+                        .map(|parent| stops[*parent].wheelchair_boarding)
+                        == Some(WheelchairBoardingAvailable::Available)
+                }
+                _ => false,
+            })
+            .collect();
+
+        let mut footpaths = vec![Vec::new(); stops.len()];
+        let mut change_times = HashMap::new();
+        for transfer in &schedule.transfers {
+            let (from, to) = match (
+                stop_index.get(transfer.from_stop_id.as_str()),
+                stop_index.get(transfer.to_stop_id.as_str()),
+            ) {
+                (Some(from), Some(to)) => (*from, *to),
+                _ => continue,
+            };
+            let duration = transfer.min_transfer_time.map(|time| time as i64);
+            match (from == to, transfer.transfer_type) {
+                (true, TransferType::NotPossible) => {
+                    change_times.insert(from, None);
+                }
+                (true, _) => {
+                    change_times.insert(from, Some(duration.unwrap_or(0)));
+                }
+                (false, TransferType::NotPossible) => (),
+                (false, _) => footpaths[from].push((to, duration.unwrap_or(0))),
+            }
+        }
+
+        let mut frequencies: HashMap<&str, Vec<&Frequency>> = HashMap::new();
+        for frequency in &schedule.frequencies {
+            frequencies
+                .entry(frequency.trip_id.as_str())
+                .or_default()
+                .push(frequency);
+        }
+
+        let day_start = service_day_start(date, timezone);
+        let mut runs = Vec::new();
+        for service_date in [date - Duration::days(1), date, date + Duration::days(1)] {
+            let services = schedule.services_on(service_date);
+            let shift = service_day_start(service_date, timezone) - day_start;
+            for trip in schedule.trips.values() {
+                if services.contains(&trip.service_id) {
+                    let frequencies = frequencies.get(trip.id.as_str()).map(Vec::as_slice);
+                    push_runs(&mut runs, trip, frequencies, service_date, shift);
+                }
+            }
+        }
+
+        let mut timetable = Self {
+            stops,
+            stop_index,
+            children,
+            wheelchair_accessible,
+            runs,
+            patterns: Vec::new(),
+            stop_patterns: Vec::new(),
+            footpaths,
+            change_times,
+            day_start,
+        };
+        timetable.build_patterns();
+        timetable
+    }
+
+    /// Groups the runs into patterns, splitting those where runs overtake each other.
+    fn build_patterns(&mut self) {
+        let mut order: Vec<usize> = (0..self.runs.len()).collect();
+        order.sort_by_key(|run| self.runs[*run].times[0].1);
+
+        let mut by_stops: HashMap<Vec<usize>, Vec<usize>> = HashMap::new();
+        for run in order {
+            let stops: Vec<usize> = self.runs[run]
+                .stop_times
+                .iter()
+                .map(|index| {
+                    let stop = &self.runs[run].trip.stop_times[*index].stop;
+                    self.stop_index[stop.id.as_str()]
+                })
+                .collect();
+            let times = &self.runs[run].times;
+            let candidates = by_stops.entry(stops.clone()).or_default();
+            let pattern = candidates.iter().copied().find(|pattern: &usize| {
+                let last = &self.runs[*self.patterns[*pattern]
+                    .runs
+                    .last()
+                    .expect("patterns have runs")];
+                last.times
+                    .iter()
+                    .zip(times)
+                    .all(|(last, time)| last.0 <= time.0 && last.1 <= time.1)
+            });
+            match pattern {
+                Some(pattern) => self.patterns[pattern].runs.push(run),
+                None => {
+                    candidates.push(self.patterns.len());
+                    self.patterns.push(Pattern {
+                        stops,
+                        runs: vec![run],
+                    });
+                }
+            }
+        }
+
+        self.stop_patterns = vec![Vec::new(); self.stops.len()];
+        for (index, pattern) in self.patterns.iter().enumerate() {
+            for (position, stop) in pattern.stops.iter().enumerate() {
+                self.stop_patterns[*stop].push((index, position));
+            }
+        }
+    }
+
+    /// Indexes of a stop and, for a station, of its stops.
+    fn resolve(&self, stop_id: &str) -> Vec<usize> {
+        let mut stops: Vec<usize> = self.stop_index.get(stop_id).copied().into_iter().collect();
+        if let Some(children) = stops.first().and_then(|stop| self.children.get(stop)) {
+            stops.extend(children);
+        }
+        stops
+    }
+
+    /// Time needed to change vehicles at a stop, none when it is not possible.
+    fn change_time(&self, stop: usize, options: &RoutingOptions) -> Option<i64> {
+        match self.change_times.get(&stop) {
+            Some(change_time) => *change_time,
+            None => Some(options.min_change_time as i64),
+        }
+    }
+
+    /// Whether a stop can be used to board or leave vehicles.
+    fn accessible(&self, stop: usize, options: &RoutingOptions) -> bool {
+        !options.wheelchair || self.wheelchair_accessible[stop]
+    }
+}
+
+/// Pushes the runs of a trip on a service date, one per start time of its frequencies if
+/// any. The service date starts `shift` seconds after the one of the timetable.
+fn push_runs<'s>(
+    runs: &mut Vec<Run<'s>>,
+    trip: &'s Trip,
+    frequencies: Option<&[&Frequency]>,
+    service_date: NaiveDate,
+    shift: i64,
+) {
+    let (stop_times, times): (Vec<usize>, Vec<(i64, i64)>) = trip
+        .stop_times
+        .iter()
+        .enumerate()
+        .filter_map(|(index, stop_time)| {
+            let arrival = stop_time.arrival_time.or(stop_time.departure_time)? as i64;
+            let departure = stop_time.departure_time.or(stop_time.arrival_time)? as i64;
+            Some((index, (arrival, departure)))
+        })
+        .unzip();
+    let first = match times.first() {
+        Some((_, departure)) => *departure,
+        None => return,
+    };
+
+    let runs_times: Vec<(Option<u64>, i64)> = match frequencies {
+        Some(frequencies) => frequencies
+            .iter()
+            .flat_map(|frequency| frequency.start_times())
+            .map(|start| (Some(start), start as i64 - first))
+            .collect(),
+        None => vec![(None, 0)],
+    };
+
+    for (start_time, offset) in runs_times {
+        runs.push(Run {
+            trip,
+            start_time,
+            service_date,
+            stop_times: stop_times.clone(),
+            times: times
+                .iter()
+                .map(|(arrival, departure)| (arrival + offset + shift, departure + offset + shift))
+                .collect(),
+        });
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use crate::test::reader_from_files;
+    use chrono::Utc;
+
+    pub fn date() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, 4).unwrap()
+    }
+
+    pub fn at(hours: i64, minutes: i64) -> i64 {
+        service_day_start(date(), &Utc) + hours * 3600 + minutes * 60
+    }
+
+    /// A slow direct line L1 from A to D, and a faster way changing from L2 to L3 between
+    /// B and C, 2 minutes apart on foot.
+    pub fn feed(transfers: &str) -> Schedule {
+        let mut gtfs = reader_from_files(&[
+            (
+                "calendar.txt",
+                "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\n\
+                 WD,1,1,1,1,1,0,0,20240101,20241231\n",
+            ),
+            (
+                "routes.txt",
+                "route_id,route_short_name,route_long_name,route_type\nL1,1,,3\nL2,2,,3\nL3,3,,3\n",
+            ),
+            (
+                "stops.txt",
+                "stop_id,stop_name,stop_lat,stop_lon,wheelchair_boarding\n\
+                 A,A,0,0,1\nB,B,0,0.1,1\nC,C,0,0.101,1\nD,D,0,0.2,1\nE,E,0,0.3,2\n",
+            ),
+            (
+                "trips.txt",
+                "route_id,service_id,trip_id,wheelchair_accessible,bikes_allowed\n\
+                 L1,WD,slow,1,1\nL2,WD,fast,1,2\nL3,WD,link,1,2\nL3,WD,night,1,2\n",
+            ),
+            (
+                "stop_times.txt",
+                "trip_id,arrival_time,departure_time,stop_id,stop_sequence\n\
+                 slow,08:00:00,08:00:00,A,1\nslow,09:00:00,09:00:00,D,2\n\
+                 fast,08:05:00,08:05:00,A,1\nfast,08:20:00,08:20:00,B,2\n\
+                 link,08:25:00,08:25:00,C,1\nlink,08:40:00,08:40:00,D,2\nlink,08:50:00,08:50:00,E,3\n\
+                 night,24:30:00,24:30:00,A,1\nnight,25:00:00,25:00:00,D,2\n",
+            ),
+            ("transfers.txt", transfers),
+        ]);
+        Schedule::from_reader(&mut gtfs).unwrap()
+    }
+
+    pub const WALK_B_C: &str =
+        "from_stop_id,to_stop_id,transfer_type,min_transfer_time\nB,C,2,120\n";
+
+    #[test]
+    fn next_service_day() {
+        let schedule = feed(WALK_B_C);
+        let timetable = Timetable::new(&schedule, date(), &Utc);
+        let options = RoutingOptions::default();
+
+        // E is only served in the morning
+        let journeys = timetable.raptor("A", "E", at(23, 0), &options);
+        assert_eq!(journeys.len(), 1);
+        assert_eq!(journeys[0].departure(), at(24 + 8, 5));
+        assert_eq!(journeys[0].arrival(), at(24 + 8, 50));
+        match &journeys[0].legs[0] {
+            Leg::Ride { service_date, .. } => {
+                assert_eq!(*service_date, date() + Duration::days(1))
+            }
+            leg => panic!("unexpected leg {:?}", leg),
+        }
+
+        let scan = csa::ConnectionScan::new(&timetable);
+        assert_eq!(
+            scan.earliest_arrival("A", "E", at(23, 0), &options),
+            Some(at(24 + 8, 50))
+        );
+    }
+}
@@ -0,0 +1,432 @@
+//! Round-based public transit routing (RAPTOR, Delling et al., 2012).
+//!
+//! Each round k finds the earliest arrival at every stop with k vehicle rides, by scanning
+//! the patterns serving the stops improved in the previous round, then walking the
+//! footpaths from the stops improved by a ride.
+
+use std::collections::{HashMap, HashSet};
+
+use super::{Journey, Leg, RoutingOptions, Timetable};
+
+/// How a stop was reached within a round.
+#[derive(Debug, Copy, Clone)]
+enum Parent {
+    Origin,
+    Ride {
+        run: usize,
+        pattern: usize,
+        board: usize,
+        alight: usize,
+    },
+    Walk {
+        from: usize,
+    },
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Label {
+    arrival: i64,
+    parent: Parent,
+}
+
+impl<'s> Timetable<'s> {
+    /// Pareto-optimal journeys from a stop or station to another, departing at or after a
+    /// POSIX time: the earliest arrival for each number of transfers which arrives earlier
+    /// than with fewer transfers.
+    pub fn raptor(
+        &self,
+        from: &str,
+        to: &str,
+        departure: i64,
+        options: &RoutingOptions,
+    ) -> Vec<Journey<'s>> {
+        let sources = self.resolve(from);
+        let targets: HashSet<usize> = self.resolve(to).into_iter().collect();
+        let departure = departure - self.day_start;
+
+        let mut best = vec![i64::MAX; self.stops.len()];
+        let mut rounds: Vec<Vec<Option<Label>>> = vec![vec![None; self.stops.len()]];
+        let mut marked = HashSet::new();
+        for source in sources {
+            rounds[0][source] = Some(Label {
+                arrival: departure,
+                parent: Parent::Origin,
+            });
+            best[source] = departure;
+            marked.insert(source);
+        }
+        self.walk(&mut rounds[0], &mut best, &mut marked, i64::MAX);
+
+        let mut journeys = Vec::new();
+        let mut best_target = i64::MAX;
+        self.push_journey(&rounds, 0, &targets, &mut best_target, &mut journeys);
+
+        for round in 1..=options.max_transfers + 1 {
+            let previous = &rounds[round - 1];
+            let mut labels = previous.clone();
+
+            // Earliest position in each pattern of the stops improved in the previous round
+            let mut queue: HashMap<usize, usize> = HashMap::new();
+            for stop in marked.drain() {
+                for (pattern, position) in &self.stop_patterns[stop] {
+                    let earliest = queue.entry(*pattern).or_insert(*position);
+                    *earliest = (*earliest).min(*position);
+                }
+            }
+
+            for (pattern_index, start) in queue {
+                let pattern = &self.patterns[pattern_index];
+                let mut current: Option<(usize, usize)> = None;
+                for (position, stop) in pattern.stops.iter().enumerate().skip(start) {
+                    let stop = *stop;
+                    if let Some((run, board)) = current {
+                        let arrival = self.runs[run].times[position].0;
+                        let bound = best[stop].min(best_target);
+                        if arrival < bound
+                            && self.runs[run].drop_off(position)
+                            && self.accessible(stop, options)
+                        {
+                            labels[stop] = Some(Label {
+                                arrival,
+                                parent: Parent::Ride {
+                                    run,
+                                    pattern: pattern_index,
+                                    board,
+                                    alight: position,
+                                },
+                            });
+                            best[stop] = arrival;
+                            marked.insert(stop);
+                        }
+                    }
+
+                    // Catch an earlier run if the stop was reached earlier in the previous round
+                    let ready = match &previous[stop] {
+                        Some(label) => match label.parent {
+                            Parent::Ride { .. } => match self.change_time(stop, options) {
+                                Some(change_time) => label.arrival + change_time,
+                                None => continue,
+                            },
+                            _ => label.arrival,
+                        },
+                        None => continue,
+                    };
+                    let catchable = match current {
+                        Some((run, _)) => ready <= self.runs[run].times[position].1,
+                        None => true,
+                    };
+                    if catchable {
+                        if let Some(run) =
+                            self.earliest_run(pattern_index, position, ready, options)
+                        {
+                            current = match current {
+                                Some((current, board)) if current == run => Some((run, board)),
+                                _ => Some((run, position)),
+                            };
+                        }
+                    }
+                }
+            }
+
+            self.walk(&mut labels, &mut best, &mut marked, best_target);
+            rounds.push(labels);
+            self.push_journey(&rounds, round, &targets, &mut best_target, &mut journeys);
+            if marked.is_empty() {
+                break;
+            }
+        }
+
+        journeys
+    }
+
+    /// Earliest run of a pattern which can be boarded at a position from a time.
+    fn earliest_run(
+        &self,
+        pattern: usize,
+        position: usize,
+        ready: i64,
+        options: &RoutingOptions,
+    ) -> Option<usize> {
+        if !self.accessible(self.patterns[pattern].stops[position], options) {
+            return None;
+        }
+        let runs = &self.patterns[pattern].runs;
+        let first = runs.partition_point(|run| self.runs[*run].times[position].1 < ready);
+        runs[first..]
+            .iter()
+            .copied()
+            .find(|run| self.runs[*run].pickup(position) && self.runs[*run].allowed(options))
+    }
+
+    /// Walks the footpaths from the stops marked by a ride in the round, or from the origin.
+    /// A walk replaces the label of a stop reached later by a ride, but walks are not taken
+    /// from the stops reached by a walk, so that every walk follows a ride or the origin.
+    fn walk(
+        &self,
+        labels: &mut [Option<Label>],
+        best: &mut [i64],
+        marked: &mut HashSet<usize>,
+        best_target: i64,
+    ) {
+        // Earliest walk to each stop
+        let mut walks: HashMap<usize, Label> = HashMap::new();
+        for from in marked.iter() {
+            let arrival = match &labels[*from] {
+                Some(label) => label.arrival,
+                None => continue,
+            };
+            for (to, duration) in &self.footpaths[*from] {
+                let arrival = arrival + duration;
+                let earlier = match walks.get(to) {
+                    Some(walk) => arrival < walk.arrival,
+                    None => arrival < best[*to].min(best_target),
+                };
+                if earlier {
+                    let label = Label {
+                        arrival,
+                        parent: Parent::Walk { from: *from },
+                    };
+                    walks.insert(*to, label);
+                }
+            }
+        }
+
+        // A walk from a stop whose label is replaced by a walk would follow that walk
+        let walked: HashSet<usize> = walks.keys().copied().collect();
+        for (to, label) in walks {
+            if let Parent::Walk { from } = label.parent {
+                if walked.contains(&from) {
+                    continue;
+                }
+            }
+            best[to] = label.arrival;
+            labels[to] = Some(label);
+            marked.insert(to);
+        }
+    }
+
+    /// Pushes the journey to the best target of a round, if it arrives earlier than those
+    /// with fewer rides.
+    fn push_journey(
+        &self,
+        rounds: &[Vec<Option<Label>>],
+        round: usize,
+        targets: &HashSet<usize>,
+        best_target: &mut i64,
+        journeys: &mut Vec<Journey<'s>>,
+    ) {
+        let target = targets
+            .iter()
+            .filter_map(|target| Some((*target, rounds[round][*target]?)))
+            .min_by_key(|(_, label)| label.arrival);
+        let (target, label) = match target {
+            Some(target) => target,
+            None => return,
+        };
+        if label.arrival >= *best_target {
+            return;
+        }
+        *best_target = label.arrival;
+
+        let legs = self.legs(rounds, round, target);
+        if !legs.is_empty() {
+            journeys.push(Journey { legs });
+        }
+    }
+
+    /// Legs of the journey to a stop in a round, following the labels back to the origin.
+    fn legs(
+        &self,
+        rounds: &[Vec<Option<Label>>],
+        mut round: usize,
+        mut stop: usize,
+    ) -> Vec<Leg<'s>> {
+        let mut legs = Vec::new();
+        while let Some(label) = rounds[round][stop] {
+            match label.parent {
+                Parent::Origin => break,
+                Parent::Walk { from } => {
+                    let departure = rounds[round][from].map_or(label.arrival, |from| from.arrival);
+                    legs.push(Leg::Walk {
+                        from: self.stops[from],
+                        to: self.stops[stop],
+                        departure: departure + self.day_start,
+                        arrival: label.arrival + self.day_start,
+                    });
+                    stop = from;
+                }
+                Parent::Ride {
+                    run,
+                    pattern,
+                    board,
+                    alight,
+                } => {
+                    let vehicle = &self.runs[run];
+                    let from = self.patterns[pattern].stops[board];
+                    legs.push(Leg::Ride {
+                        trip: vehicle.trip,
+                        start_time: vehicle.start_time,
+                        service_date: vehicle.service_date,
+                        from: self.stops[from],
+                        to: self.stops[stop],
+                        departure: vehicle.times[board].1 + self.day_start,
+                        arrival: vehicle.times[alight].0 + self.day_start,
+                    });
+                    stop = from;
+                    round -= 1;
+                }
+            }
+        }
+
+        legs.reverse();
+        legs
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        routing::{
+            csa::ConnectionScan,
+            test::{at, date, feed, WALK_B_C},
+            Schedule,
+        },
+        test::reader_from_files,
+    };
+    use chrono::Utc;
+
+    fn summary(journey: &Journey) -> Vec<String> {
+        journey
+            .legs
+            .iter()
+            .map(|leg| match leg {
+                Leg::Ride { trip, from, to, .. } => format!("{} {}-{}", trip.id, from.id, to.id),
+                Leg::Walk { from, to, .. } => format!("walk {}-{}", from.id, to.id),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn pareto_journeys() {
+        let schedule = feed(WALK_B_C);
+        let timetable = Timetable::new(&schedule, date(), &Utc);
+        let journeys = timetable.raptor("A", "D", at(7, 50), &RoutingOptions::default());
+
+        assert_eq!(journeys.len(), 2);
+        assert_eq!(summary(&journeys[0]), ["slow A-D"]);
+        assert_eq!(journeys[0].arrival(), at(9, 0));
+        assert_eq!(journeys[0].transfers(), 0);
+        assert_eq!(summary(&journeys[1]), ["fast A-B", "walk B-C", "link C-D"]);
+        assert_eq!(journeys[1].departure(), at(8, 5));
+        assert_eq!(journeys[1].arrival(), at(8, 40));
+        assert_eq!(journeys[1].transfers(), 1);
+
+        // Once the direct line has left, the night one is the only one without transfers
+        let journeys = timetable.raptor("A", "D", at(8, 1), &RoutingOptions::default());
+        assert_eq!(journeys.len(), 2);
+        assert_eq!(summary(&journeys[0]), ["night A-D"]);
+        assert_eq!(journeys[1].arrival(), at(8, 40));
+    }
+
+    #[test]
+    fn transfers_and_filters() {
+        let schedule = feed("from_stop_id,to_stop_id,transfer_type,min_transfer_time\nB,C,3,\n");
+        let timetable = Timetable::new(&schedule, date(), &Utc);
+        let journeys = timetable.raptor("A", "D", at(7, 50), &RoutingOptions::default());
+        assert_eq!(journeys.len(), 1);
+
+        let schedule = feed(WALK_B_C);
+        let timetable = Timetable::new(&schedule, date(), &Utc);
+        let bikes = RoutingOptions {
+            bikes: true,
+            ..Default::default()
+        };
+        let journeys = timetable.raptor("A", "D", at(7, 50), &bikes);
+        assert_eq!(journeys.len(), 1);
+        assert_eq!(summary(&journeys[0]), ["slow A-D"]);
+
+        // E is not wheelchair accessible
+        let wheelchair = RoutingOptions {
+            wheelchair: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            timetable
+                .raptor("A", "E", at(7, 50), &RoutingOptions::default())
+                .len(),
+            1
+        );
+        assert!(timetable
+            .raptor("A", "E", at(7, 50), &wheelchair)
+            .is_empty());
+
+        let direct = RoutingOptions {
+            max_transfers: 0,
+            ..Default::default()
+        };
+        assert_eq!(timetable.raptor("A", "E", at(7, 50), &direct).len(), 0);
+    }
+
+    #[test]
+    fn walk_earlier_than_ride() {
+        // C is reached by a ride at 09:00, and earlier on foot from B
+        let mut gtfs = reader_from_files(&[
+            (
+                "calendar.txt",
+                "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\n\
+                 WD,1,1,1,1,1,0,0,20240101,20241231\n",
+            ),
+            (
+                "routes.txt",
+                "route_id,route_short_name,route_long_name,route_type\nL1,1,,3\n",
+            ),
+            (
+                "stops.txt",
+                "stop_id,stop_name,stop_lat,stop_lon\nA,A,0,0\nB,B,0,0.1\nC,C,0,0.101\nD,D,0,0.2\n",
+            ),
+            (
+                "trips.txt",
+                "route_id,service_id,trip_id\nL1,WD,to_b\nL1,WD,to_c\nL1,WD,from_c\n",
+            ),
+            (
+                "stop_times.txt",
+                "trip_id,arrival_time,departure_time,stop_id,stop_sequence\n\
+                 to_b,08:00:00,08:00:00,A,1\nto_b,08:20:00,08:20:00,B,2\n\
+                 to_c,08:00:00,08:00:00,A,1\nto_c,09:00:00,09:00:00,C,2\n\
+                 from_c,08:25:00,08:25:00,C,1\nfrom_c,08:40:00,08:40:00,D,2\n",
+            ),
+            ("transfers.txt", WALK_B_C),
+        ]);
+        let schedule = Schedule::from_reader(&mut gtfs).unwrap();
+        let timetable = Timetable::new(&schedule, date(), &Utc);
+        let options = RoutingOptions::default();
+
+        let journeys = timetable.raptor("A", "D", at(7, 50), &options);
+        assert_eq!(journeys.len(), 1);
+        assert_eq!(
+            summary(&journeys[0]),
+            ["to_b A-B", "walk B-C", "from_c C-D"]
+        );
+        assert_eq!(journeys[0].arrival(), at(8, 40));
+        assert_eq!(
+            ConnectionScan::new(&timetable).earliest_arrival("A", "D", at(7, 50), &options),
+            Some(at(8, 40))
+        );
+    }
+
+    #[test]
+    fn after_midnight() {
+        let schedule = feed(WALK_B_C);
+        let tuesday = date().succ_opt().unwrap();
+        let timetable = Timetable::new(&schedule, tuesday, &Utc);
+
+        let journeys = timetable.raptor("A", "D", at(24, 10), &RoutingOptions::default());
+        assert_eq!(summary(&journeys[0]), ["night A-D"]);
+        assert_eq!(journeys[0].arrival(), at(25, 0));
+        match &journeys[0].legs[0] {
+            Leg::Ride { service_date, .. } => assert_eq!(*service_date, date()),
+            Leg::Walk { .. } => unreachable!(),
+        }
+    }
+}
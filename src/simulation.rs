@@ -0,0 +1,412 @@
+//! Simulation of vehicle positions from the schedule alone, e.g. for demos and load tests.
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::{NaiveDate, TimeZone};
+use serde_json::{json, Value};
+
+use crate::{
+    geometry::{Point, Polyline, TripPath},
+    gtfs_serde::format_time,
+    structures::{calendar::service_day_start, frequencies::Frequency, shapes::Shape, trips::Trip},
+};
+
+/// Whether a simulated vehicle is at a stop or on its way to it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SimulatedStatus {
+    StoppedAt,
+    InTransitTo,
+}
+
+/// The position of a vehicle running a trip according to the schedule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulatedVehicle {
+    pub trip_id: String,
+    pub route_id: String,
+    /// Start time of the run of a frequency-based trip, in seconds since the start of the
+    /// service day.
+    pub start_time: Option<u64>,
+    pub point: Point,
+    /// Heading of the vehicle, in degrees clockwise from North.
+    pub bearing: Option<f64>,
+    /// Distance travelled along the path of the trip, in meters.
+    pub distance: f64,
+    /// Stop the vehicle is at or heading to.
+    pub stop_id: String,
+    pub stop_sequence: u16,
+    pub status: SimulatedStatus,
+}
+
+impl SimulatedVehicle {
+    /// Identifier of the vehicle: the trip id, followed by the start time for the runs of
+    /// frequency-based trips.
+    pub fn id(&self) -> String {
+        match self.start_time {
+            Some(start_time) => format!("{}-{}", self.trip_id, format_time(start_time)),
+            None => self.trip_id.clone(),
+        }
+    }
+}
+
+/// A run of a trip, shifted from its schedule for frequency-based trips.
+struct Run<'t> {
+    trip: &'t Trip,
+    path: usize,
+    start_time: Option<u64>,
+    shift: i64,
+    /// Times, in seconds since the start of the service day, and distances along the path
+    /// at which the vehicle is scheduled, with the index of the stop time.
+    events: Vec<(i64, f64, usize)>,
+}
+
+/// Simulates the vehicles running the trips of a service date.
+///
+/// Vehicles move along the shape of their trip, or along straight lines between its stops,
+/// at constant speed between consecutive stops, and wait at the stops between their arrival
+/// and departure times. Frequency-based trips are run once per start time. Only the trips of
+/// the given date are simulated: those of the previous day still running after midnight are
+/// simulated with that day.
+///
+/// ```
+/// use chrono::{NaiveDate, Utc};
+/// use gtfs_parser::{simulation::Simulator, GtfsReader};
+///
+/// let mut gtfs = GtfsReader::from_path("./resources/zips/gtfs.zip").unwrap();
+/// let trips = gtfs.trips().unwrap();
+/// let date = NaiveDate::from_ymd_opt(2006, 7, 3).unwrap();
+/// let services = gtfs.services_on(date).unwrap();
+///
+/// let simulator = Simulator::new(&trips, &[], &gtfs.shapes().unwrap(), &services, date, &Utc);
+/// let noon = date.and_hms_opt(12, 0, 0).unwrap().and_utc().timestamp();
+/// let geojson = simulator.geojson_at(noon);
+/// assert_eq!(geojson["type"], "FeatureCollection");
+/// assert!(geojson["features"].is_array());
+/// ```
+pub struct Simulator<'t> {
+    runs: Vec<Run<'t>>,
+    paths: Vec<TripPath>,
+    date: NaiveDate,
+    day_start: i64,
+}
+
+impl<'t> Simulator<'t> {
+    /// Simulator of the trips of the running `services` of `date`, in the time zone of the agency.
+    pub fn new<Tz: TimeZone>(
+        trips: &'t HashMap<String, Trip>,
+        frequencies: &[Frequency],
+        shapes: &[Shape],
+        services: &HashSet<String>,
+        date: NaiveDate,
+        timezone: &Tz,
+    ) -> Self {
+        let shapes = Polyline::from_shapes(shapes);
+        let mut start_times: HashMap<&str, Vec<u64>> = HashMap::new();
+        for frequency in frequencies {
+            start_times
+                .entry(frequency.trip_id.as_str())
+                .or_default()
+                .extend(frequency.start_times());
+        }
+
+        let mut runs = Vec::new();
+        let mut paths = Vec::new();
+        for trip in trips.values() {
+            if !services.contains(&trip.service_id) {
+                continue;
+            }
+            let path = match TripPath::new(trip, &shapes) {
+                Some(path) => path,
+                None => continue,
+            };
+            let events: Vec<(i64, f64, usize)> = trip
+                .stop_times
+                .iter()
+                .enumerate()
+                .flat_map(|(index, stop_time)| {
+                    let distance = path.stop_distances[index];
+                    stop_time
+                        .arrival_time
+                        .into_iter()
+                        .chain(stop_time.departure_time)
+                        .map(move |time| (time as i64, distance, index))
+                })
+                .collect();
+            let first_time = match events.first() {
+                Some((time, _, _)) => *time,
+                None => continue,
+            };
+
+            match start_times.get(trip.id.as_str()) {
+                Some(starts) => runs.extend(starts.iter().map(|start| Run {
+                    trip,
+                    path: paths.len(),
+                    start_time: Some(*start),
+                    shift: *start as i64 - first_time,
+                    events: events.clone(),
+                })),
+                None => runs.push(Run {
+                    trip,
+                    path: paths.len(),
+                    start_time: None,
+                    shift: 0,
+                    events,
+                }),
+            }
+            paths.push(path);
+        }
+
+        Self {
+            runs,
+            paths,
+            date,
+            day_start: service_day_start(date, timezone),
+        }
+    }
+
+    /// Positions of the vehicles running at the given POSIX time, sorted by vehicle id.
+    pub fn positions_at(&self, time: i64) -> Vec<SimulatedVehicle> {
+        let mut vehicles: Vec<SimulatedVehicle> = self
+            .runs
+            .iter()
+            .filter_map(|run| self.simulate(run, time - self.day_start - run.shift))
+            .collect();
+        vehicles.sort_by_key(|vehicle| vehicle.id());
+        vehicles
+    }
+
+    /// Positions of the vehicles running at the given POSIX time, as a GeoJSON
+    /// FeatureCollection of points.
+    pub fn geojson_at(&self, time: i64) -> Value {
+        let features: Vec<Value> = self
+            .positions_at(time)
+            .into_iter()
+            .map(|vehicle| {
+                json!({
+                    "type": "Feature",
+                    "id": vehicle.id(),
+                    "geometry": {
+                        "type": "Point",
+                        "coordinates": [vehicle.point.longitude, vehicle.point.latitude],
+                    },
+                    "properties": {
+                        "trip_id": vehicle.trip_id,
+                        "route_id": vehicle.route_id,
+                        "start_date": self.date.format("%Y%m%d").to_string(),
+                        "start_time": vehicle.start_time.map(format_time),
+                        "bearing": vehicle.bearing,
+                        "stop_id": vehicle.stop_id,
+                        "stop_sequence": vehicle.stop_sequence,
+                        "status": match vehicle.status {
+                            SimulatedStatus::StoppedAt => "STOPPED_AT",
+                            SimulatedStatus::InTransitTo => "IN_TRANSIT_TO",
+                        },
+                    },
+                })
+            })
+            .collect();
+
+        json!({ "type": "FeatureCollection", "features": features })
+    }
+
+    /// Positions of the vehicles running at the given POSIX time, as a GTFS-Realtime feed
+    /// of VehiclePositions.
+    #[cfg(feature = "realtime")]
+    pub fn feed_at(&self, time: i64) -> crate::realtime::FeedMessage {
+        use crate::realtime::{
+            feed_header::Incrementality, trip_descriptor::ScheduleRelationship,
+            vehicle_position::VehicleStopStatus, FeedEntity, FeedHeader, FeedMessage, Position,
+            TripDescriptor, VehicleDescriptor, VehiclePosition,
+        };
+
+        let entity = self
+            .positions_at(time)
+            .into_iter()
+            .map(|vehicle| {
+                let id = vehicle.id();
+                let status = match vehicle.status {
+                    SimulatedStatus::StoppedAt => VehicleStopStatus::StoppedAt,
+                    SimulatedStatus::InTransitTo => VehicleStopStatus::InTransitTo,
+                };
+                FeedEntity {
+                    id: id.clone(),
+                    vehicle: Some(VehiclePosition {
+                        trip: Some(TripDescriptor {
+                            trip_id: Some(vehicle.trip_id),
+                            route_id: Some(vehicle.route_id),
+                            start_time: vehicle.start_time.map(format_time),
+                            start_date: Some(self.date.format("%Y%m%d").to_string()),
+                            schedule_relationship: Some(ScheduleRelationship::Scheduled as i32),
+                            ..Default::default()
+                        }),
+                        vehicle: Some(VehicleDescriptor {
+                            id: Some(id),
+                            ..Default::default()
+                        }),
+                        position: Some(Position {
+                            latitude: vehicle.point.latitude as f32,
+                            longitude: vehicle.point.longitude as f32,
+                            bearing: vehicle.bearing.map(|bearing| bearing as f32),
+                            ..Default::default()
+                        }),
+                        current_stop_sequence: Some(u32::from(vehicle.stop_sequence)),
+                        stop_id: Some(vehicle.stop_id),
+                        current_status: Some(status as i32),
+                        timestamp: Some(time as u64),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }
+            })
+            .collect();
+
+        FeedMessage {
+            header: FeedHeader {
+                gtfs_realtime_version: "2.0".to_owned(),
+                incrementality: Some(Incrementality::FullDataset as i32),
+                timestamp: Some(time as u64),
+                ..Default::default()
+            },
+            entity,
+        }
+    }
+
+    /// Position of a run at the given time, in seconds since the start of the service day
+    /// of its schedule.
+    fn simulate(&self, run: &Run, time: i64) -> Option<SimulatedVehicle> {
+        let (first, last) = (run.events.first()?, run.events.last()?);
+        if time < first.0 || time > last.0 {
+            return None;
+        }
+
+        // Last event at or before the time, and the one after it if the run is not over
+        let index = run.events.iter().rposition(|event| event.0 <= time)?;
+        let (from_time, from_distance, from_stop) = run.events[index];
+        let (distance, stop, status) = match run.events.get(index + 1) {
+            Some(&(_, to_distance, _)) if to_distance == from_distance => {
+                (from_distance, from_stop, SimulatedStatus::StoppedAt)
+            }
+            Some(&(to_time, to_distance, to_stop)) => {
+                let fraction = (time - from_time) as f64 / (to_time - from_time) as f64;
+                let distance = from_distance + (to_distance - from_distance) * fraction;
+                (distance, to_stop, SimulatedStatus::InTransitTo)
+            }
+            None => (from_distance, from_stop, SimulatedStatus::StoppedAt),
+        };
+
+        let path = &self.paths[run.path];
+        let stop_time = &run.trip.stop_times[stop];
+        Some(SimulatedVehicle {
+            trip_id: run.trip.id.clone(),
+            route_id: run.trip.route_id.clone(),
+            start_time: run.start_time,
+            point: path.line.point_at(distance)?,
+            bearing: path.line.bearing_at(distance),
+            distance,
+            stop_id: stop_time.stop.id.clone(),
+            stop_sequence: stop_time.stop_sequence,
+            status,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::reader_from_files;
+    use chrono::Utc;
+
+    fn date() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, 4).unwrap()
+    }
+
+    fn at(hours: i64, minutes: i64) -> i64 {
+        service_day_start(date(), &Utc) + hours * 3600 + minutes * 60
+    }
+
+    /// T1 goes east along the equator through stops A, B and C, about 1.1km apart. F1 runs
+    /// between B and C every 30 minutes.
+    fn feed() -> (HashMap<String, Trip>, Vec<Frequency>) {
+        let mut gtfs = reader_from_files(&[
+            (
+                "stops.txt",
+                "stop_id,stop_name,stop_lat,stop_lon\nA,A,0,0\nB,B,0,0.01\nC,C,0,0.02\n",
+            ),
+            (
+                "trips.txt",
+                "route_id,service_id,trip_id\nR1,WD,T1\nR2,WD,F1\nR1,WE,T2\n",
+            ),
+            (
+                "stop_times.txt",
+                "trip_id,arrival_time,departure_time,stop_id,stop_sequence\n\
+                 T1,08:00:00,08:00:00,A,1\nT1,08:10:00,08:12:00,B,2\nT1,08:22:00,08:22:00,C,3\n\
+                 F1,00:00:00,00:00:00,B,1\nF1,00:10:00,00:10:00,C,2\n\
+                 T2,08:00:00,08:00:00,A,1\nT2,08:10:00,08:10:00,B,2\n",
+            ),
+            (
+                "frequencies.txt",
+                "trip_id,start_time,end_time,headway_secs\nF1,08:00:00,09:00:00,1800\n",
+            ),
+        ]);
+        (gtfs.trips().unwrap(), gtfs.frequencies().unwrap())
+    }
+
+    #[test]
+    fn positions() {
+        let (trips, frequencies) = feed();
+        let services = ["WD".to_owned()].iter().cloned().collect();
+        let simulator = Simulator::new(&trips, &frequencies, &[], &services, date(), &Utc);
+
+        let vehicles = simulator.positions_at(at(8, 5));
+        let ids: Vec<String> = vehicles.iter().map(|vehicle| vehicle.id()).collect();
+        assert_eq!(ids, ["F1-08:00:00", "T1"]);
+
+        let t1 = &vehicles[1];
+        assert_eq!(t1.status, SimulatedStatus::InTransitTo);
+        assert_eq!(t1.stop_id, "B");
+        assert!((t1.point.longitude - 0.005).abs() < 1e-6);
+        assert!((t1.bearing.unwrap() - 90.0).abs() < 1e-6);
+
+        let f1 = &vehicles[0];
+        assert_eq!(f1.start_time, Some(8 * 3600));
+        assert!((f1.point.longitude - 0.015).abs() < 1e-6);
+
+        let t1 = &simulator.positions_at(at(8, 11))[0];
+        assert_eq!(
+            (t1.status, t1.stop_id.as_str()),
+            (SimulatedStatus::StoppedAt, "B")
+        );
+        assert!((t1.point.longitude - 0.01).abs() < 1e-6);
+
+        let ids: Vec<String> = simulator
+            .positions_at(at(8, 35))
+            .iter()
+            .map(|vehicle| vehicle.id())
+            .collect();
+        assert_eq!(ids, ["F1-08:30:00"]);
+        assert!(simulator.positions_at(at(10, 0)).is_empty());
+    }
+
+    #[test]
+    fn outputs() {
+        let (trips, frequencies) = feed();
+        let services = ["WD".to_owned()].iter().cloned().collect();
+        let simulator = Simulator::new(&trips, &frequencies, &[], &services, date(), &Utc);
+
+        let geojson = simulator.geojson_at(at(8, 5));
+        let feature = &geojson["features"][1];
+        assert_eq!(feature["id"], "T1");
+        assert_eq!(feature["properties"]["status"], "IN_TRANSIT_TO");
+        assert_eq!(feature["geometry"]["coordinates"][1], 0.0);
+
+        #[cfg(feature = "realtime")]
+        {
+            let feed = simulator.feed_at(at(8, 5));
+            let positions: Vec<_> = feed.vehicle_positions().collect();
+            assert_eq!(positions.len(), 2);
+            let trip = positions[0].trip.as_ref().unwrap();
+            assert_eq!(trip.start_time(), "08:00:00");
+            assert_eq!(trip.start_date(), "20240304");
+            assert_eq!(positions[1].stop_id(), "B");
+        }
+    }
+}
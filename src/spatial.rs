@@ -0,0 +1,419 @@
+//! Spatial indexes over stops and shape segments, for nearest, radius and bounding box
+//! queries.
+//!
+//! Coordinates are indexed as points of the unit sphere, where the straight-line distance
+//! between two points grows with their great-circle distance, so that the nearest points
+//! of the index are also the nearest on the Earth. Distances are returned in meters, using
+//! the haversine formula.
+
+use std::collections::{HashMap, HashSet};
+
+use rstar::{
+    primitives::{GeomWithData, Line},
+    RTree, AABB,
+};
+
+use crate::{
+    geometry::{Point, Polyline, EARTH_RADIUS},
+    structures::{
+        shapes::Shape,
+        stops::{Stop, StopLocationType},
+        trips::Trip,
+    },
+};
+
+/// Point of the unit sphere of a coordinate.
+fn cartesian(point: &Point) -> [f64; 3] {
+    let (latitude, longitude) = (point.latitude.to_radians(), point.longitude.to_radians());
+    [
+        latitude.cos() * longitude.cos(),
+        latitude.cos() * longitude.sin(),
+        latitude.sin(),
+    ]
+}
+
+fn geographic(point: &[f64; 3]) -> Point {
+    let norm = (point[0].powi(2) + point[1].powi(2) + point[2].powi(2)).sqrt();
+    Point::new(
+        (point[2] / norm).asin().to_degrees(),
+        point[1].atan2(point[0]).to_degrees(),
+    )
+}
+
+/// Squared straight-line distance on the unit sphere between points a distance apart.
+fn chord_2(distance: f64) -> f64 {
+    let angle = (distance / EARTH_RADIUS).min(std::f64::consts::PI);
+    (2.0 * (angle / 2.0).sin()).powi(2)
+}
+
+/// Envelope of the points of the unit sphere within a bounding box, which does not cross
+/// the antimeridian.
+fn envelope(min: &Point, max: &Point) -> AABB<[f64; 3]> {
+    let (south, north) = (min.latitude.to_radians(), max.latitude.to_radians());
+    let (west, east) = (min.longitude, max.longitude);
+    let contains = |angle: f64| west <= angle && angle <= east;
+    let bounds = |values: &[f64]| {
+        let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        (min, max)
+    };
+
+    let mut cos_latitude = vec![south.cos(), north.cos()];
+    if south <= 0.0 && 0.0 <= north {
+        cos_latitude.push(1.0);
+    }
+    let mut cos_longitude = vec![west.to_radians().cos(), east.to_radians().cos()];
+    let mut sin_longitude = vec![west.to_radians().sin(), east.to_radians().sin()];
+    for (angle, cos, sin) in [(0.0, 1.0, 0.0), (90.0, 0.0, 1.0), (-90.0, 0.0, -1.0)] {
+        if contains(angle) {
+            cos_longitude.push(cos);
+            sin_longitude.push(sin);
+        }
+    }
+    if contains(180.0) || contains(-180.0) {
+        cos_longitude.push(-1.0);
+    }
+
+    // The cosine of the latitude is positive, the extremes of the products are at the bounds
+    let (cos_latitude_min, cos_latitude_max) = bounds(&cos_latitude);
+    let products = |values: &[f64]| {
+        let (min, max) = bounds(values);
+        bounds(&[
+            cos_latitude_min * min,
+            cos_latitude_min * max,
+            cos_latitude_max * min,
+            cos_latitude_max * max,
+        ])
+    };
+    let (x_min, x_max) = products(&cos_longitude);
+    let (y_min, y_max) = products(&sin_longitude);
+    AABB::from_corners([x_min, y_min, south.sin()], [x_max, y_max, north.sin()])
+}
+
+fn in_bounds(point: &Point, min: &Point, max: &Point) -> bool {
+    (min.latitude..=max.latitude).contains(&point.latitude)
+        && (min.longitude..=max.longitude).contains(&point.longitude)
+}
+
+/// Which stops a query returns.
+#[derive(Debug, Clone, Default)]
+pub struct StopFilter {
+    /// Location types of the stops, any when empty
+    pub location_types: Vec<StopLocationType>,
+    /// Routes one of which must serve the stops, or the stops of the stations, any when empty
+    pub route_ids: Vec<String>,
+}
+
+/// Spatial index over the stops with coordinates.
+///
+/// ```
+/// use gtfs_parser::{geometry::Point, spatial::{StopFilter, StopIndex}, GtfsReader};
+///
+/// let mut gtfs = GtfsReader::from_path("./resources/zips/gtfs.zip").unwrap();
+/// let stops = gtfs.stops().unwrap();
+/// let trips = gtfs.trips().unwrap();
+/// let index = StopIndex::new(&stops, &trips);
+///
+/// let point = Point::new(48.796058, 2.449386);
+/// let nearest = index.nearest(&point, 3, &StopFilter::default());
+/// assert_eq!(nearest.len(), 3);
+/// assert!(nearest.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+/// ```
+pub struct StopIndex<'s> {
+    tree: RTree<GeomWithData<[f64; 3], &'s Stop>>,
+    /// Routes serving each stop, and the stops of each station
+    routes: HashMap<&'s str, HashSet<&'s str>>,
+}
+
+impl<'s> StopIndex<'s> {
+    /// Index of the stops, without the routes serving them: route filters match no stop.
+    pub fn from_stops(stops: &'s [Stop]) -> Self {
+        Self {
+            tree: Self::tree(stops),
+            routes: HashMap::new(),
+        }
+    }
+
+    pub fn new(stops: &'s [Stop], trips: &'s HashMap<String, Trip>) -> Self {
+        let parents: HashMap<&str, &str> = stops
+            .iter()
+            .filter_map(|stop| Some((stop.id.as_str(), stop.parent_station.as_deref()?)))
+            .collect();
+        let mut routes: HashMap<&str, HashSet<&str>> = HashMap::new();
+        for trip in trips.values() {
+            for stop_time in &trip.stop_times {
+                let stop = stop_time.stop.id.as_str();
+                routes.entry(stop).or_default().insert(&trip.route_id);
+                if let Some(parent) = parents.get(stop) {
+                    routes.entry(parent).or_default().insert(&trip.route_id);
+                }
+            }
+        }
+
+        Self {
+            tree: Self::tree(stops),
+            routes,
+        }
+    }
+
+    fn tree(stops: &'s [Stop]) -> RTree<GeomWithData<[f64; 3], &'s Stop>> {
+        RTree::bulk_load(
+            stops
+                .iter()
+                .filter_map(|stop| Some(GeomWithData::new(cartesian(&stop.point()?), stop)))
+                .collect(),
+        )
+    }
+
+    fn accepts(&self, stop: &Stop, filter: &StopFilter) -> bool {
+        let location_type =
+            filter.location_types.is_empty() || filter.location_types.contains(&stop.location_type);
+        let routes = filter.route_ids.is_empty()
+            || self.routes.get(stop.id.as_str()).map(|routes| {
+                filter
+                    .route_ids
+                    .iter()
+                    .any(|route| routes.contains(route.as_str()))
+            }) == Some(true);
+        location_type && routes
+    }
+
+    /// The `count` stops nearest to a point, with their distance, from the nearest.
+    pub fn nearest(
+        &self,
+        point: &Point,
+        count: usize,
+        filter: &StopFilter,
+    ) -> Vec<(&'s Stop, f64)> {
+        self.tree
+            .nearest_neighbor_iter(&cartesian(point))
+            .map(|indexed| indexed.data)
+            .filter(|stop| self.accepts(stop, filter))
+            .take(count)
+            .map(|stop| (stop, distance(point, stop)))
+            .collect()
+    }
+
+    /// The stops within a distance in meters of a point, with their distance, from the
+    /// nearest.
+    pub fn within(&self, point: &Point, radius: f64, filter: &StopFilter) -> Vec<(&'s Stop, f64)> {
+        let mut stops: Vec<(&Stop, f64)> = self
+            .tree
+            .locate_within_distance(cartesian(point), chord_2(radius))
+            .map(|indexed| indexed.data)
+            .filter(|stop| self.accepts(stop, filter))
+            .map(|stop| (stop, distance(point, stop)))
+            .filter(|(_, distance)| *distance <= radius)
+            .collect();
+        stops.sort_by(|a, b| a.1.total_cmp(&b.1));
+        stops
+    }
+
+    /// The stops within a bounding box given by its south-west and north-east corners.
+    pub fn in_bounds(&self, min: &Point, max: &Point, filter: &StopFilter) -> Vec<&'s Stop> {
+        self.tree
+            .locate_in_envelope_intersecting(&envelope(min, max))
+            .map(|indexed| indexed.data)
+            .filter(|stop| {
+                self.accepts(stop, filter)
+                    && stop.point().map(|point| in_bounds(&point, min, max)) == Some(true)
+            })
+            .collect()
+    }
+}
+
+fn distance(point: &Point, stop: &Stop) -> f64 {
+    stop.point()
+        .map_or(f64::INFINITY, |stop| point.distance(&stop))
+}
+
+/// The segment between two consecutive points of a shape.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShapeSegment {
+    pub shape_id: String,
+    /// Position of the segment in the shape
+    pub index: usize,
+    pub from: Point,
+    pub to: Point,
+}
+
+/// A shape segment near a point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SegmentMatch<'i> {
+    pub segment: &'i ShapeSegment,
+    /// Point of the segment nearest to the point
+    pub point: Point,
+    /// Distance to the point, in meters
+    pub distance: f64,
+}
+
+/// Spatial index over the segments of shapes.
+pub struct ShapeIndex {
+    tree: RTree<GeomWithData<Line<[f64; 3]>, ShapeSegment>>,
+}
+
+impl ShapeIndex {
+    pub fn new(shapes: &[Shape]) -> Self {
+        let mut segments = Vec::new();
+        for (shape_id, polyline) in Polyline::from_shapes(shapes) {
+            for (index, points) in polyline.points().windows(2).enumerate() {
+                let line = Line::new(cartesian(&points[0]), cartesian(&points[1]));
+                let segment = ShapeSegment {
+                    shape_id: shape_id.clone(),
+                    index,
+                    from: points[0],
+                    to: points[1],
+                };
+                segments.push(GeomWithData::new(line, segment));
+            }
+        }
+
+        Self {
+            tree: RTree::bulk_load(segments),
+        }
+    }
+
+    fn matched<'i>(
+        point: &Point,
+        indexed: &'i GeomWithData<Line<[f64; 3]>, ShapeSegment>,
+    ) -> SegmentMatch<'i> {
+        let nearest = geographic(&indexed.geom().nearest_point(&cartesian(point)));
+        SegmentMatch {
+            segment: &indexed.data,
+            point: nearest,
+            distance: point.distance(&nearest),
+        }
+    }
+
+    /// The `count` segments nearest to a point, from the nearest.
+    pub fn nearest(&self, point: &Point, count: usize) -> Vec<SegmentMatch<'_>> {
+        self.tree
+            .nearest_neighbor_iter(&cartesian(point))
+            .take(count)
+            .map(|indexed| Self::matched(point, indexed))
+            .collect()
+    }
+
+    /// The segments within a distance in meters of a point, from the nearest.
+    pub fn within(&self, point: &Point, radius: f64) -> Vec<SegmentMatch<'_>> {
+        let mut segments: Vec<SegmentMatch> = self
+            .tree
+            .locate_within_distance(cartesian(point), chord_2(radius))
+            .map(|indexed| Self::matched(point, indexed))
+            .filter(|segment| segment.distance <= radius)
+            .collect();
+        segments.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        segments
+    }
+
+    /// The segments with a point within a bounding box given by its south-west and
+    /// north-east corners.
+    pub fn in_bounds(&self, min: &Point, max: &Point) -> Vec<&ShapeSegment> {
+        self.tree
+            .locate_in_envelope_intersecting(&envelope(min, max))
+            .map(|indexed| &indexed.data)
+            .filter(|segment| {
+                in_bounds(&segment.from, min, max) || in_bounds(&segment.to, min, max)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::reader_from_files;
+
+    #[test]
+    fn stops() {
+        let mut gtfs = reader_from_files(&[
+            (
+                "stops.txt",
+                "stop_id,stop_name,stop_lat,stop_lon,location_type,parent_station\n\
+                 S,Station,48.8566,2.3522,1,\n\
+                 S1,Platform,48.8567,2.3523,0,S\n\
+                 N,North,48.8656,2.3522,0,\n\
+                 F,Far,48.9566,2.3522,0,\n\
+                 X,No coordinates,,,3,S\n",
+            ),
+            (
+                "routes.txt",
+                "route_id,route_short_name,route_long_name,route_type\nR,1,,3\n",
+            ),
+            ("trips.txt", "route_id,service_id,trip_id\nR,WD,T\n"),
+            (
+                "stop_times.txt",
+                "trip_id,arrival_time,departure_time,stop_id,stop_sequence\n\
+                 T,08:00:00,08:00:00,S1,1\nT,08:10:00,08:10:00,F,2\n",
+            ),
+        ]);
+        let stops = gtfs.stops().unwrap();
+        let trips = gtfs.trips().unwrap();
+        let index = StopIndex::new(&stops, &trips);
+        let center = Point::new(48.8566, 2.3522);
+        let ids = |stops: Vec<(&Stop, f64)>| -> Vec<String> {
+            stops.into_iter().map(|(stop, _)| stop.id.clone()).collect()
+        };
+
+        let nearest = index.nearest(&center, 3, &StopFilter::default());
+        assert_eq!(nearest[0].1, 0.0);
+        assert!((nearest[2].1 - 1000.0).abs() < 5.0);
+        assert_eq!(ids(nearest), ["S", "S1", "N"]);
+
+        assert_eq!(
+            ids(index.within(&center, 2000.0, &StopFilter::default())),
+            ["S", "S1", "N"]
+        );
+        let served = StopFilter {
+            route_ids: vec!["R".to_owned()],
+            ..Default::default()
+        };
+        assert_eq!(
+            ids(index.within(&center, 20_000.0, &served)),
+            ["S", "S1", "F"]
+        );
+        let stations = StopFilter {
+            location_types: vec![StopLocationType::StopArea],
+            ..Default::default()
+        };
+        assert_eq!(ids(index.nearest(&center, 5, &stations)), ["S"]);
+
+        let mut in_bounds: Vec<&str> = index
+            .in_bounds(
+                &Point::new(48.85, 2.35),
+                &Point::new(48.87, 2.36),
+                &StopFilter::default(),
+            )
+            .into_iter()
+            .map(|stop| stop.id.as_str())
+            .collect();
+        in_bounds.sort_unstable();
+        assert_eq!(in_bounds, ["N", "S", "S1"]);
+    }
+
+    #[test]
+    fn shapes() {
+        let mut gtfs = reader_from_files(&[(
+            "shapes.txt",
+            "shape_id,shape_pt_lat,shape_pt_lon,shape_pt_sequence\n\
+             A,0,0,1\nA,0,0.01,2\nA,0.01,0.01,3\nB,1,1,1\nB,1,1.01,2\n",
+        )]);
+        let index = ShapeIndex::new(&gtfs.shapes().unwrap());
+
+        // About 111m north of the middle of the first segment of A
+        let point = Point::new(0.001, 0.005);
+        let nearest = index.nearest(&point, 1);
+        assert_eq!(nearest[0].segment.shape_id, "A");
+        assert_eq!(nearest[0].segment.index, 0);
+        assert!((nearest[0].distance - 111.2).abs() < 1.0);
+        assert!(nearest[0].point.latitude.abs() < 1e-6);
+
+        let within = index.within(&point, 1000.0);
+        assert_eq!(within.len(), 2);
+        assert!(index.within(&point, 100.0).is_empty());
+
+        let in_bounds = index.in_bounds(&Point::new(0.5, 0.5), &Point::new(1.5, 1.5));
+        assert_eq!(in_bounds.len(), 1);
+        assert_eq!(in_bounds[0].shape_id, "B");
+    }
+}
@@ -0,0 +1,720 @@
+//! Navigation inside stations and audit of their modelling, over the pathways linking their
+//! entrances, platforms and other locations.
+
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, HashSet},
+};
+
+use serde::Serialize;
+
+use crate::{
+    error::Error,
+    structures::{
+        levels::Level,
+        pathways::{Pathway, PathwayMode},
+        stops::{Stop, StopLocationType},
+    },
+};
+
+/// Time to go through a pathway without traversal time, length or coordinates, in seconds.
+const DEFAULT_TRAVERSAL_TIME: u64 = 30;
+
+/// How routes through stations are found.
+#[derive(Debug, Clone)]
+pub struct NavigationOptions {
+    /// Avoids stairs, escalators and steep pathways
+    pub accessible: bool,
+    /// Steepest slope of the pathways of accessible routes, as a ratio
+    pub max_slope: f64,
+    /// Walking speed, in meters per second
+    pub walking_speed: f64,
+}
+
+impl Default for NavigationOptions {
+    fn default() -> Self {
+        Self {
+            accessible: false,
+            max_slope: 0.083,
+            walking_speed: 1.3,
+        }
+    }
+}
+
+impl NavigationOptions {
+    /// Speed along a pathway, in meters per second.
+    fn speed(&self, mode: PathwayMode) -> f64 {
+        match mode {
+            PathwayMode::Stairs | PathwayMode::Escalator => self.walking_speed * 0.4,
+            PathwayMode::Travelator => self.walking_speed * 1.5,
+            _ => self.walking_speed,
+        }
+    }
+}
+
+/// A pathway taken by a route, in one direction or the other.
+#[derive(Debug, Clone)]
+pub struct Step<'s> {
+    pub pathway: &'s Pathway,
+    pub from: &'s Stop,
+    pub to: &'s Stop,
+    /// Whether the pathway is taken from its `to_stop_id` to its `from_stop_id`
+    pub reversed: bool,
+    /// Time to go through the pathway, in seconds
+    pub duration: u64,
+    /// Directions for riders, such as "Take the stairs down to level Platforms, following
+    /// signs to Line 1"
+    pub instruction: String,
+}
+
+/// A route through a station.
+#[derive(Debug, Clone)]
+pub struct StationRoute<'s> {
+    pub steps: Vec<Step<'s>>,
+}
+
+impl<'s> StationRoute<'s> {
+    /// Time to follow the route, in seconds.
+    pub fn duration(&self) -> u64 {
+        self.steps.iter().map(|step| step.duration).sum()
+    }
+}
+
+/// Findings of the audit of a station linking its locations with pathways. Locations are
+/// given by id.
+#[derive(Debug, Clone, Serialize)]
+pub struct StationAudit {
+    pub station_id: String,
+    pub station_name: Option<String>,
+    /// Platforms which cannot be reached from any entrance
+    pub unreachable_platforms: Vec<String>,
+    /// Entrances from which no platform can be reached
+    pub dead_end_entrances: Vec<String>,
+    /// Locations without pathways, counting those of the boarding areas of platforms, and
+    /// generic nodes linked to a single other location
+    pub dangling_nodes: Vec<String>,
+    /// Elevators whose ends are on the same level, by pathway id
+    pub same_level_elevators: Vec<String>,
+    /// Platforms which cannot be reached from any entrance without stairs, escalators or
+    /// steep pathways
+    pub inaccessible_platforms: Vec<String>,
+    /// Whether a platform can be reached from an entrance without stairs, escalators or
+    /// steep pathways
+    pub has_accessible_path: bool,
+}
+
+impl StationAudit {
+    /// Descriptions of the issues found in the station.
+    pub fn issues(&self) -> Vec<String> {
+        let mut issues = Vec::new();
+        for platform in &self.unreachable_platforms {
+            issues.push(format!(
+                "platform {} is not reachable from any entrance",
+                platform
+            ));
+        }
+        for entrance in &self.dead_end_entrances {
+            issues.push(format!(
+                "entrance {} does not lead to any platform",
+                entrance
+            ));
+        }
+        for node in &self.dangling_nodes {
+            issues.push(format!("location {} is dangling", node));
+        }
+        for pathway in &self.same_level_elevators {
+            issues.push(format!(
+                "elevator {} links locations on the same level",
+                pathway
+            ));
+        }
+        if !self.has_accessible_path {
+            issues.push("no platform can be reached by an accessible path".to_owned());
+        }
+        issues
+    }
+}
+
+/// Audit of the stations of a feed, as returned by [StationGraph::audit].
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StationAuditReport {
+    pub stations: Vec<StationAudit>,
+}
+
+impl StationAuditReport {
+    pub fn to_json(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Human readable summary of the audit, listing the issues of each station.
+    pub fn to_text(&self) -> String {
+        let with_issues: Vec<(&StationAudit, Vec<String>)> = self
+            .stations
+            .iter()
+            .map(|station| (station, station.issues()))
+            .filter(|(_, issues)| !issues.is_empty())
+            .collect();
+        let mut text = format!(
+            "{} stations audited, {} with issues\n",
+            self.stations.len(),
+            with_issues.len()
+        );
+
+        for (station, issues) in with_issues {
+            match &station.station_name {
+                Some(name) => text.push_str(&format!("\n{} ({})\n", station.station_id, name)),
+                None => text.push_str(&format!("\n{}\n", station.station_id)),
+            }
+            for issue in issues {
+                text.push_str(&format!("    {}\n", issue));
+            }
+        }
+
+        text
+    }
+}
+
+/// A pathway usable in one direction, from the node it leaves.
+struct Edge<'s> {
+    pathway: &'s Pathway,
+    to: usize,
+    reversed: bool,
+}
+
+/// The graph of the locations of stations linked by pathways.
+///
+/// ```
+/// use gtfs_parser::{stations::{NavigationOptions, StationGraph}, GtfsReader};
+///
+/// let mut gtfs = GtfsReader::from_path("./resources/zips/gtfs.zip").unwrap();
+/// let stops = gtfs.stops().unwrap();
+/// let pathways = gtfs.pathways().unwrap_or_default();
+/// let levels = gtfs.levels().unwrap_or_default();
+///
+/// let graph = StationGraph::new(&stops, &pathways, &levels);
+/// if let Some(route) = graph.route("stop1", "stop2", &NavigationOptions::default()) {
+///     assert!(route.steps.iter().all(|step| !step.instruction.is_empty()));
+/// }
+/// ```
+pub struct StationGraph<'s> {
+    stops: Vec<&'s Stop>,
+    stop_index: HashMap<&'s str, usize>,
+    levels: HashMap<&'s str, &'s Level>,
+    edges: Vec<Vec<Edge<'s>>>,
+}
+
+impl<'s> StationGraph<'s> {
+    pub fn new(stops: &'s [Stop], pathways: &'s [Pathway], levels: &'s [Level]) -> Self {
+        let stop_index: HashMap<&str, usize> = stops
+            .iter()
+            .enumerate()
+            .map(|(index, stop)| (stop.id.as_str(), index))
+            .collect();
+
+        let mut edges: Vec<Vec<Edge>> = stops.iter().map(|_| Vec::new()).collect();
+        for pathway in pathways {
+            let (from, to) = match (
+                stop_index.get(pathway.from_stop_id.as_str()),
+                stop_index.get(pathway.to_stop_id.as_str()),
+            ) {
+                (Some(from), Some(to)) => (*from, *to),
+                _ => continue,
+            };
+            edges[from].push(Edge {
+                pathway,
+                to,
+                reversed: false,
+            });
+            if pathway.is_bidirectional {
+                edges[to].push(Edge {
+                    pathway,
+                    to: from,
+                    reversed: true,
+                });
+            }
+        }
+
+        Self {
+            stops: stops.iter().collect(),
+            stop_index,
+            levels: levels
+                .iter()
+                .map(|level| (level.id.as_str(), level))
+                .collect(),
+            edges,
+        }
+    }
+
+    /// Quickest route from a location to another. Routes from a station start at one of
+    /// its entrances, and routes to a station end at one of them.
+    pub fn route(
+        &self,
+        from: &str,
+        to: &str,
+        options: &NavigationOptions,
+    ) -> Option<StationRoute<'s>> {
+        let sources = self.resolve(from);
+        let targets = self.resolve(to);
+
+        let mut durations = vec![u64::MAX; self.stops.len()];
+        let mut previous: Vec<Option<(usize, &Edge)>> = self.stops.iter().map(|_| None).collect();
+        let mut queue = BinaryHeap::new();
+        for source in sources {
+            durations[source] = 0;
+            queue.push(Reverse((0, source)));
+        }
+
+        let target = loop {
+            let Reverse((duration, node)) = queue.pop()?;
+            if duration > durations[node] {
+                continue;
+            }
+            if targets.contains(&node) {
+                break node;
+            }
+            for edge in &self.edges[node] {
+                if !self.usable(edge, options) {
+                    continue;
+                }
+                let arrival = duration + self.duration(node, edge, options);
+                if arrival < durations[edge.to] {
+                    durations[edge.to] = arrival;
+                    previous[edge.to] = Some((node, edge));
+                    queue.push(Reverse((arrival, edge.to)));
+                }
+            }
+        };
+
+        let mut steps = Vec::new();
+        let mut node = target;
+        while let Some((from, edge)) = previous[node] {
+            steps.push(Step {
+                pathway: edge.pathway,
+                from: self.stops[from],
+                to: self.stops[node],
+                reversed: edge.reversed,
+                duration: self.duration(from, edge, options),
+                instruction: self.instruction(from, edge),
+            });
+            node = from;
+        }
+        steps.reverse();
+
+        Some(StationRoute { steps })
+    }
+
+    /// A location or, for a station, its entrances.
+    fn resolve(&self, stop_id: &str) -> Vec<usize> {
+        let index = match self.stop_index.get(stop_id) {
+            Some(index) => *index,
+            None => return Vec::new(),
+        };
+        if self.stops[index].location_type != StopLocationType::StopArea {
+            return vec![index];
+        }
+        self.stops
+            .iter()
+            .enumerate()
+            .filter(|(_, stop)| {
+                stop.location_type == StopLocationType::StationEntrance
+                    && stop.parent_station.as_deref() == Some(stop_id)
+            })
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    fn usable(&self, edge: &Edge, options: &NavigationOptions) -> bool {
+        if !options.accessible {
+            return true;
+        }
+        let pathway = edge.pathway;
+        let steep = pathway
+            .max_slope
+            .map(|slope| slope.abs() > options.max_slope)
+            == Some(true);
+        let stairs = pathway.stair_count.unwrap_or(0) != 0;
+        !matches!(pathway.mode, PathwayMode::Stairs | PathwayMode::Escalator) && !steep && !stairs
+    }
+
+    /// Time to go through a pathway, from its traversal time or else its length.
+    fn duration(&self, from: usize, edge: &Edge, options: &NavigationOptions) -> u64 {
+        let pathway = edge.pathway;
+        if let Some(traversal_time) = pathway.traversal_time {
+            return traversal_time;
+        }
+        let length = pathway.length.or_else(|| {
+            let (from, to) = (self.stops[from].point()?, self.stops[edge.to].point()?);
+            Some(from.distance(&to))
+        });
+        match length {
+            Some(length) => (length / options.speed(pathway.mode)).ceil() as u64,
+            None => DEFAULT_TRAVERSAL_TIME,
+        }
+    }
+
+    /// Audits the stations whose locations are linked by pathways.
+    ///
+    /// The accessibility of the stations is checked with the `max_slope` of the options.
+    pub fn audit(&self, options: &NavigationOptions) -> StationAuditReport {
+        let index_of = |id: &str| self.stop_index.get(id).copied();
+        let mut locations: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (index, stop) in self.stops.iter().enumerate() {
+            let mut parent = stop.parent_station.as_deref().and_then(index_of);
+            // Boarding areas belong to the station of their platform
+            if let Some(platform) = parent {
+                if stop.location_type == StopLocationType::BoardingArea {
+                    parent = self.stops[platform]
+                        .parent_station
+                        .as_deref()
+                        .and_then(index_of);
+                }
+            }
+            if let Some(station) = parent {
+                locations.entry(station).or_default().push(index);
+            }
+        }
+
+        let mut neighbours: Vec<HashSet<usize>> = vec![HashSet::new(); self.stops.len()];
+        for (from, edges) in self.edges.iter().enumerate() {
+            for edge in edges {
+                neighbours[from].insert(edge.to);
+                neighbours[edge.to].insert(from);
+            }
+        }
+        let accessible = NavigationOptions {
+            accessible: true,
+            ..options.clone()
+        };
+
+        let mut stations: Vec<StationAudit> = locations
+            .into_iter()
+            .filter(|(_, locations)| {
+                locations
+                    .iter()
+                    .any(|location| !neighbours[*location].is_empty())
+            })
+            .map(|(station, locations)| {
+                self.audit_station(station, &locations, &neighbours, &accessible)
+            })
+            .collect();
+        stations.sort_by(|a, b| a.station_id.cmp(&b.station_id));
+
+        StationAuditReport { stations }
+    }
+
+    fn audit_station(
+        &self,
+        station: usize,
+        locations: &[usize],
+        neighbours: &[HashSet<usize>],
+        accessible: &NavigationOptions,
+    ) -> StationAudit {
+        let of_type = |location_type: StopLocationType| -> Vec<usize> {
+            locations
+                .iter()
+                .copied()
+                .filter(|location| self.stops[*location].location_type == location_type)
+                .collect()
+        };
+        let entrances = of_type(StopLocationType::StationEntrance);
+        let platforms = of_type(StopLocationType::StopPoint);
+        let ids = |locations: Vec<usize>| -> Vec<String> {
+            let mut ids: Vec<String> = locations
+                .into_iter()
+                .map(|location| self.stops[location].id.clone())
+                .collect();
+            ids.sort();
+            ids
+        };
+
+        // A platform is reached at one of its boarding areas, if it has any
+        let reaches_platform = |reached: &[bool], platform: usize| {
+            reached[platform]
+                || locations.iter().any(|location| {
+                    reached[*location]
+                        && self.stops[*location].parent_station.as_deref()
+                            == Some(self.stops[platform].id.as_str())
+                })
+        };
+        let from_entrances = self.reachable(&entrances, &NavigationOptions::default());
+        let accessibly = self.reachable(&entrances, accessible);
+
+        let unreachable_platforms = platforms
+            .iter()
+            .copied()
+            .filter(|platform| !reaches_platform(&from_entrances, *platform))
+            .collect();
+        let inaccessible_platforms = platforms
+            .iter()
+            .copied()
+            .filter(|platform| !reaches_platform(&accessibly, *platform))
+            .collect();
+        let dead_end_entrances = entrances
+            .iter()
+            .copied()
+            .filter(|entrance| {
+                let reached = self.reachable(&[*entrance], &NavigationOptions::default());
+                !platforms
+                    .iter()
+                    .any(|platform| reaches_platform(&reached, *platform))
+            })
+            .collect();
+        let linked: Vec<bool> = neighbours.iter().map(|n| !n.is_empty()).collect();
+        let dangling_nodes = locations
+            .iter()
+            .copied()
+            .filter(|location| match self.stops[*location].location_type {
+                StopLocationType::GenericNode => neighbours[*location].len() < 2,
+                StopLocationType::StopPoint => !reaches_platform(&linked, *location),
+                _ => neighbours[*location].is_empty(),
+            })
+            .collect();
+
+        let mut same_level_elevators: Vec<String> = locations
+            .iter()
+            .flat_map(|location| {
+                self.edges[*location]
+                    .iter()
+                    .map(move |edge| (location, edge))
+            })
+            .filter(|(location, edge)| {
+                let same_level = match (self.level(**location), self.level(edge.to)) {
+                    (Some(from), Some(to)) => from.id == to.id || from.index == to.index,
+                    _ => false,
+                };
+                !edge.reversed && edge.pathway.mode == PathwayMode::Elevator && same_level
+            })
+            .map(|(_, edge)| edge.pathway.id.clone())
+            .collect();
+        same_level_elevators.sort();
+
+        let stop = self.stops[station];
+        StationAudit {
+            station_id: stop.id.clone(),
+            station_name: stop.name.clone(),
+            unreachable_platforms: ids(unreachable_platforms),
+            dead_end_entrances: ids(dead_end_entrances),
+            dangling_nodes: ids(dangling_nodes),
+            same_level_elevators,
+            inaccessible_platforms: ids(inaccessible_platforms),
+            has_accessible_path: platforms
+                .iter()
+                .any(|platform| reaches_platform(&accessibly, *platform)),
+        }
+    }
+
+    /// Locations reachable from the sources.
+    fn reachable(&self, sources: &[usize], options: &NavigationOptions) -> Vec<bool> {
+        let mut reached = vec![false; self.stops.len()];
+        let mut stack = sources.to_vec();
+        while let Some(node) = stack.pop() {
+            if std::mem::replace(&mut reached[node], true) {
+                continue;
+            }
+            for edge in &self.edges[node] {
+                if !reached[edge.to] && self.usable(edge, options) {
+                    stack.push(edge.to);
+                }
+            }
+        }
+        reached
+    }
+
+    fn level(&self, stop: usize) -> Option<&'s Level> {
+        self.levels
+            .get(self.stops[stop].level_id.as_deref()?)
+            .copied()
+    }
+
+    fn instruction(&self, from: usize, edge: &Edge) -> String {
+        let pathway = edge.pathway;
+        let (from_level, to_level) = (self.level(from), self.level(edge.to));
+        let climb = match (from_level, to_level) {
+            (Some(from), Some(to)) if from.index != to.index => to.index - from.index,
+            _ => match pathway.stair_count {
+                Some(stairs) if edge.reversed => -stairs,
+                Some(stairs) => stairs,
+                None => 0,
+            },
+        };
+        let direction = match climb {
+            0 => "",
+            climb if climb > 0 => " up",
+            _ => " down",
+        };
+
+        let mut instruction = match pathway.mode {
+            PathwayMode::Walkway => "Walk".to_owned(),
+            PathwayMode::Stairs => format!("Take the stairs{}", direction),
+            PathwayMode::Travelator => "Take the moving walkway".to_owned(),
+            PathwayMode::Escalator => format!("Take the escalator{}", direction),
+            PathwayMode::Elevator => "Take the elevator".to_owned(),
+            PathwayMode::FareGate => "Go through the fare gate".to_owned(),
+            PathwayMode::ExitGate => "Go through the exit gate".to_owned(),
+            PathwayMode::Other(_) => "Continue".to_owned(),
+        };
+
+        let level_name = to_level.and_then(|level| level.name.as_deref());
+        let changes_level = match (from_level, to_level) {
+            (Some(from), Some(to)) => from.id != to.id,
+            _ => false,
+        };
+        match (changes_level, level_name, &self.stops[edge.to].name) {
+            (true, Some(level), _) => instruction.push_str(&format!(" to level {}", level)),
+            (_, _, Some(name)) => instruction.push_str(&format!(" to {}", name)),
+            _ => {}
+        }
+
+        let sign = match edge.reversed {
+            true => &pathway.reversed_signposted_as,
+            false => &pathway.signposted_as,
+        };
+        if let Some(sign) = sign {
+            instruction.push_str(&format!(", following signs to {}", sign));
+        }
+        instruction
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::reader_from_files;
+
+    #[test]
+    fn route() {
+        let mut gtfs = reader_from_files(&[
+            (
+                "stops.txt",
+                "stop_id,stop_name,location_type,parent_station,level_id\n\
+                 S,Station,1,,\n\
+                 E,Main entrance,2,S,L0\n\
+                 N,Hall,3,S,L1\n\
+                 P1,Platform 1,0,S,L2\n\
+                 P2,Platform 2,0,S,L2\n",
+            ),
+            (
+                "levels.txt",
+                "level_id,level_index,level_name\nL0,0,Street\nL1,-1,Mezzanine\nL2,-2,Platforms\n",
+            ),
+            (
+                "pathways.txt",
+                "pathway_id,from_stop_id,to_stop_id,pathway_mode,is_bidirectional,length,\
+                 traversal_time,stair_count,signposted_as,reversed_signposted_as\n\
+                 stairs,E,N,2,1,10,,-20,Metro,Exit\n\
+                 elevator,E,N,5,1,,60,,,\n\
+                 hall,N,P1,1,1,26,,,Line 1,Exit\n\
+                 platforms,P1,P2,1,1,13,,,,\n\
+                 escalator,N,P2,4,0,,20,,Line 2,\n",
+            ),
+        ]);
+        let stops = gtfs.stops().unwrap();
+        let pathways = gtfs.pathways().unwrap();
+        let levels = gtfs.levels().unwrap();
+        let graph = StationGraph::new(&stops, &pathways, &levels);
+        let ids = |route: &StationRoute| -> Vec<String> {
+            route
+                .steps
+                .iter()
+                .map(|step| step.pathway.id.clone())
+                .collect()
+        };
+        let default = NavigationOptions::default();
+        let accessible = NavigationOptions {
+            accessible: true,
+            ..Default::default()
+        };
+
+        let route = graph.route("S", "P1", &default).unwrap();
+        assert_eq!(ids(&route), ["stairs", "hall"]);
+        assert_eq!(route.duration(), 40);
+        assert_eq!(
+            route.steps[0].instruction,
+            "Take the stairs down to level Mezzanine, following signs to Metro"
+        );
+        assert_eq!(
+            route.steps[1].instruction,
+            "Walk to level Platforms, following signs to Line 1"
+        );
+
+        let route = graph.route("E", "P1", &accessible).unwrap();
+        assert_eq!(ids(&route), ["elevator", "hall"]);
+        assert_eq!(route.duration(), 80);
+        assert_eq!(
+            route.steps[0].instruction,
+            "Take the elevator to level Mezzanine"
+        );
+
+        // The escalator only goes down
+        assert_eq!(
+            ids(&graph.route("E", "P2", &default).unwrap()),
+            ["stairs", "escalator"]
+        );
+        let route = graph.route("P2", "S", &default).unwrap();
+        assert_eq!(ids(&route), ["platforms", "hall", "stairs"]);
+        assert!(route.steps[2].reversed);
+        assert_eq!(
+            route.steps[2].instruction,
+            "Take the stairs up to level Street, following signs to Exit"
+        );
+        assert_eq!(route.steps[0].instruction, "Walk to Platform 1");
+
+        assert!(graph.route("P1", "X", &default).is_none());
+    }
+
+    #[test]
+    fn audit() {
+        let mut gtfs = reader_from_files(&[
+            (
+                "stops.txt",
+                "stop_id,stop_name,location_type,parent_station,level_id\n\
+                 S,Station,1,,\n\
+                 E1,North entrance,2,S,L0\n\
+                 E2,South entrance,2,S,L0\n\
+                 N,Hall,3,S,L0\n\
+                 D,Dead end,3,S,L0\n\
+                 P1,Platform 1,0,S,L1\n\
+                 B1,Boarding area,4,P1,L1\n\
+                 P2,Platform 2,0,S,L1\n\
+                 T,Other station,1,,\n\
+                 T1,Platform,0,T,\n",
+            ),
+            (
+                "levels.txt",
+                "level_id,level_index,level_name\nL0,0,Street\nL1,-1,Platforms\nL2,0,Ground\n",
+            ),
+            (
+                "pathways.txt",
+                "pathway_id,from_stop_id,to_stop_id,pathway_mode,is_bidirectional\n\
+                 entrance,E1,N,1,1\n\
+                 stairs,N,B1,2,1\n\
+                 lift,E1,N,5,1\n\
+                 gate,E2,N,7,0\n\
+                 dead,N,D,1,1\n",
+            ),
+        ]);
+        let stops = gtfs.stops().unwrap();
+        let pathways = gtfs.pathways().unwrap();
+        let levels = gtfs.levels().unwrap();
+        let graph = StationGraph::new(&stops, &pathways, &levels);
+
+        let report = graph.audit(&NavigationOptions::default());
+        assert_eq!(report.stations.len(), 1);
+        let station = &report.stations[0];
+        assert_eq!(station.station_id, "S");
+        assert_eq!(station.unreachable_platforms, ["P2"]);
+        assert!(station.dead_end_entrances.is_empty());
+        assert_eq!(station.dangling_nodes, ["D", "P2"]);
+        assert_eq!(station.same_level_elevators, ["lift"]);
+        assert_eq!(station.inaccessible_platforms, ["P1", "P2"]);
+        assert!(!station.has_accessible_path);
+
+        let text = report.to_text();
+        assert!(text.starts_with("1 stations audited, 1 with issues\n\nS (Station)\n"));
+        assert!(text.contains("    platform P2 is not reachable from any entrance\n"));
+        assert!(report
+            .to_json()
+            .unwrap()
+            .contains("\"same_level_elevators\": [\n"));
+    }
+}
@@ -0,0 +1,563 @@
+//! Service-level statistics of routes and stops: trips, span of service, headways,
+//! vehicle-hours and vehicle-kilometers by service date.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    io,
+};
+
+use chrono::{Duration, NaiveDate};
+
+use crate::{
+    error::Error,
+    geometry::{Polyline, TripPath},
+    gtfs_serde::format_time,
+    structures::{
+        calendar::{self, Calendar},
+        calendar_dates::CalendarDate,
+        frequencies::Frequency,
+        shapes::Shape,
+        trips::{Direction, Trip},
+    },
+};
+
+/// A period of the service day over which headways are averaged.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeBand {
+    pub name: String,
+    /// Start of the band, in seconds since the start of the service day
+    pub start: u64,
+    /// End of the band, excluded
+    pub end: u64,
+}
+
+impl TimeBand {
+    pub fn new(name: &str, start_hour: u64, end_hour: u64) -> Self {
+        Self {
+            name: name.to_owned(),
+            start: start_hour * 3600,
+            end: end_hour * 3600,
+        }
+    }
+
+    fn contains(&self, time: u64) -> bool {
+        (self.start..self.end).contains(&time)
+    }
+}
+
+/// The default time bands: early morning, morning peak, midday, afternoon peak and evening,
+/// which runs until the end of the service day.
+pub fn default_time_bands() -> Vec<TimeBand> {
+    vec![
+        TimeBand::new("early", 0, 6),
+        TimeBand::new("am_peak", 6, 9),
+        TimeBand::new("midday", 9, 15),
+        TimeBand::new("pm_peak", 15, 19),
+        TimeBand::new("evening", 19, 48),
+    ]
+}
+
+/// Statistics of a route in a direction on a service date. Times are in seconds since the
+/// start of the service day.
+#[derive(Debug, Clone)]
+pub struct RouteStatistics<'t> {
+    pub date: NaiveDate,
+    pub route_id: &'t str,
+    pub direction_id: Option<Direction>,
+    /// Trips run, counting each run of frequency-based trips
+    pub trips: usize,
+    /// Departure of the first trip from its first stop
+    pub first_departure: u64,
+    /// Departure of the last trip from its first stop
+    pub last_departure: u64,
+    /// Arrival of the last trip to end at its last stop
+    pub last_arrival: u64,
+    pub vehicle_hours: f64,
+    /// Distance travelled by the vehicles, along the shapes of the trips when they have one
+    pub vehicle_km: f64,
+    /// Mean time between consecutive departures from the first stop of the trips, in
+    /// seconds, for each time band. None when less than two trips depart in the band.
+    pub headways: Vec<Option<f64>>,
+}
+
+/// Statistics of a stop on a service date, over all the trips calling at it. Times are in
+/// seconds since the start of the service day.
+#[derive(Debug, Clone)]
+pub struct StopStatistics<'t> {
+    pub date: NaiveDate,
+    pub stop_id: &'t str,
+    /// Trips departing from the stop, counting each run of frequency-based trips. Trips
+    /// ending at the stop are not counted.
+    pub trips: usize,
+    pub first_departure: u64,
+    pub last_departure: u64,
+    /// Mean time between consecutive departures from the stop, in seconds, for each time
+    /// band. None when less than two trips depart in the band.
+    pub headways: Vec<Option<f64>>,
+}
+
+impl<'t> RouteStatistics<'t> {
+    /// Span of service, from the first departure to the last arrival, in seconds.
+    pub fn span(&self) -> u64 {
+        self.last_arrival.saturating_sub(self.first_departure)
+    }
+}
+
+/// Statistics of every route and direction, and of every stop, over service dates.
+#[derive(Debug, Clone)]
+pub struct StatisticsTable<'t> {
+    pub time_bands: Vec<TimeBand>,
+    /// Rows sorted by date, route and direction
+    pub rows: Vec<RouteStatistics<'t>>,
+    /// Rows sorted by date and stop
+    pub stops: Vec<StopStatistics<'t>>,
+}
+
+impl<'t> StatisticsTable<'t> {
+    /// Writes the statistics of the routes as csv, with a `headway_<band>` column per time
+    /// band. Times are written as GTFS times and headways in seconds.
+    pub fn write_csv<W: io::Write>(&self, writer: W) -> Result<(), Error> {
+        let mut writer = csv::Writer::from_writer(writer);
+        let mut headers: Vec<String> = [
+            "date",
+            "route_id",
+            "direction_id",
+            "trips",
+            "first_departure",
+            "last_departure",
+            "last_arrival",
+            "span",
+            "vehicle_hours",
+            "vehicle_km",
+        ]
+        .iter()
+        .map(|header| header.to_string())
+        .collect();
+        headers.extend(
+            self.time_bands
+                .iter()
+                .map(|band| format!("headway_{}", band.name)),
+        );
+        writer.write_record(&headers)?;
+
+        for row in &self.rows {
+            let mut record = vec![
+                row.date.format("%Y%m%d").to_string(),
+                row.route_id.to_owned(),
+                match row.direction_id {
+                    Some(Direction::Outbound) => "0".to_owned(),
+                    Some(Direction::Inbound) => "1".to_owned(),
+                    None => String::new(),
+                },
+                row.trips.to_string(),
+                format_time(row.first_departure),
+                format_time(row.last_departure),
+                format_time(row.last_arrival),
+                format_time(row.span()),
+                format!("{:.2}", row.vehicle_hours),
+                format!("{:.2}", row.vehicle_km),
+            ];
+            record.extend(format_headways(&row.headways));
+            writer.write_record(&record)?;
+        }
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Writes the statistics of the stops as csv, like [write_csv](StatisticsTable::write_csv).
+    pub fn write_stops_csv<W: io::Write>(&self, writer: W) -> Result<(), Error> {
+        let mut writer = csv::Writer::from_writer(writer);
+        let mut headers: Vec<String> = [
+            "date",
+            "stop_id",
+            "trips",
+            "first_departure",
+            "last_departure",
+        ]
+        .iter()
+        .map(|header| header.to_string())
+        .collect();
+        headers.extend(
+            self.time_bands
+                .iter()
+                .map(|band| format!("headway_{}", band.name)),
+        );
+        writer.write_record(&headers)?;
+
+        for row in &self.stops {
+            let mut record = vec![
+                row.date.format("%Y%m%d").to_string(),
+                row.stop_id.to_owned(),
+                row.trips.to_string(),
+                format_time(row.first_departure),
+                format_time(row.last_departure),
+            ];
+            record.extend(format_headways(&row.headways));
+            writer.write_record(&record)?;
+        }
+        writer.flush()?;
+
+        Ok(())
+    }
+}
+
+fn format_headways(headways: &[Option<f64>]) -> impl Iterator<Item = String> + '_ {
+    headways
+        .iter()
+        .map(|headway| headway.map(|h| format!("{:.0}", h)).unwrap_or_default())
+}
+
+/// Mean time between consecutive departures, sorted, for each time band. Only the gaps
+/// between two departures of the band are counted.
+fn headways(time_bands: &[TimeBand], departures: &[u64]) -> Vec<Option<f64>> {
+    time_bands
+        .iter()
+        .map(|band| {
+            let gaps: Vec<u64> = departures
+                .windows(2)
+                .filter(|pair| band.contains(pair[0]) && band.contains(pair[1]))
+                .map(|pair| pair[1] - pair[0])
+                .collect();
+            match gaps.len() {
+                0 => None,
+                count => Some(gaps.iter().sum::<u64>() as f64 / count as f64),
+            }
+        })
+        .collect()
+}
+
+/// A vehicle run of a trip.
+struct Run<'t> {
+    departure: u64,
+    arrival: u64,
+    /// Distance travelled, in meters
+    distance: f64,
+    /// Departures from the stops of the trip, but its last one
+    calls: Vec<(&'t str, u64)>,
+}
+
+/// Computes the service-level statistics of the routes of a feed.
+///
+/// ```
+/// use chrono::NaiveDate;
+/// use gtfs_parser::{statistics::ServiceStatistics, GtfsReader};
+///
+/// let mut gtfs = GtfsReader::from_path("./resources/zips/gtfs.zip").unwrap();
+/// let trips = gtfs.trips().unwrap();
+/// let frequencies = gtfs.frequencies().unwrap_or_default();
+/// let calendar = gtfs.calendar().unwrap_or_default();
+/// let calendar_dates = gtfs.calendar_dates().unwrap_or_default();
+/// let shapes = gtfs.shapes().unwrap_or_default();
+///
+/// let statistics =
+///     ServiceStatistics::new(&trips, &frequencies, &calendar, &calendar_dates, &shapes);
+/// let date = NaiveDate::from_ymd_opt(2006, 7, 3).unwrap();
+/// let mut csv = Vec::new();
+/// statistics.on(date).write_csv(&mut csv).unwrap();
+/// ```
+pub struct ServiceStatistics<'t> {
+    trips: &'t HashMap<String, Trip>,
+    frequencies: HashMap<&'t str, Vec<&'t Frequency>>,
+    calendar: &'t [Calendar],
+    calendar_dates: &'t [CalendarDate],
+    shapes: HashMap<String, Polyline>,
+    time_bands: Vec<TimeBand>,
+}
+
+impl<'t> ServiceStatistics<'t> {
+    pub fn new(
+        trips: &'t HashMap<String, Trip>,
+        frequencies: &'t [Frequency],
+        calendar: &'t [Calendar],
+        calendar_dates: &'t [CalendarDate],
+        shapes: &[Shape],
+    ) -> Self {
+        let mut by_trip: HashMap<&str, Vec<&Frequency>> = HashMap::new();
+        for frequency in frequencies {
+            by_trip
+                .entry(&frequency.trip_id)
+                .or_default()
+                .push(frequency);
+        }
+
+        Self {
+            trips,
+            frequencies: by_trip,
+            calendar,
+            calendar_dates,
+            shapes: Polyline::from_shapes(shapes),
+            time_bands: default_time_bands(),
+        }
+    }
+
+    /// Time bands over which headways are averaged. Defaults to [default_time_bands].
+    pub fn time_bands(mut self, time_bands: Vec<TimeBand>) -> Self {
+        self.time_bands = time_bands;
+        self
+    }
+
+    /// Statistics of the routes running on a service date.
+    pub fn on(&self, date: NaiveDate) -> StatisticsTable<'t> {
+        self.between(date, date)
+    }
+
+    /// Statistics of the routes running on each service date of a range, both included.
+    pub fn between(&self, start: NaiveDate, end: NaiveDate) -> StatisticsTable<'t> {
+        let mut rows = Vec::new();
+        let mut stops = Vec::new();
+        let mut date = start;
+        while date <= end {
+            let (routes_on, stops_on) = self.rows_on(date);
+            rows.extend(routes_on);
+            stops.extend(stops_on);
+            date += Duration::days(1);
+        }
+
+        StatisticsTable {
+            time_bands: self.time_bands.clone(),
+            rows,
+            stops,
+        }
+    }
+
+    fn rows_on(&self, date: NaiveDate) -> (Vec<RouteStatistics<'t>>, Vec<StopStatistics<'t>>) {
+        let services = calendar::services_on(self.calendar, self.calendar_dates, date);
+        let mut groups: BTreeMap<(&str, Option<u8>), Vec<Run>> = BTreeMap::new();
+        for trip in self.trips.values() {
+            if !services.contains(&trip.service_id) {
+                continue;
+            }
+            let direction = trip.direction_id.map(|direction| match direction {
+                Direction::Outbound => 0,
+                Direction::Inbound => 1,
+            });
+            groups
+                .entry((trip.route_id.as_str(), direction))
+                .or_default()
+                .extend(self.runs(trip));
+        }
+
+        let mut stop_departures: BTreeMap<&str, Vec<u64>> = BTreeMap::new();
+        for run in groups.values().flatten() {
+            for (stop_id, departure) in &run.calls {
+                stop_departures.entry(stop_id).or_default().push(*departure);
+            }
+        }
+        let stops = stop_departures
+            .into_iter()
+            .map(|(stop_id, mut departures)| {
+                departures.sort_unstable();
+                StopStatistics {
+                    date,
+                    stop_id,
+                    trips: departures.len(),
+                    first_departure: departures[0],
+                    last_departure: departures[departures.len() - 1],
+                    headways: headways(&self.time_bands, &departures),
+                }
+            })
+            .collect();
+
+        let routes = groups
+            .into_iter()
+            .filter(|(_, runs)| !runs.is_empty())
+            .map(|((route_id, direction), mut runs)| {
+                runs.sort_by_key(|run| run.departure);
+                let departures: Vec<u64> = runs.iter().map(|run| run.departure).collect();
+                let headways = headways(&self.time_bands, &departures);
+
+                RouteStatistics {
+                    date,
+                    route_id,
+                    direction_id: direction.map(|direction| match direction {
+                        0 => Direction::Outbound,
+                        _ => Direction::Inbound,
+                    }),
+                    trips: runs.len(),
+                    first_departure: departures[0],
+                    last_departure: departures[departures.len() - 1],
+                    last_arrival: runs.iter().map(|run| run.arrival).max().unwrap_or(0),
+                    vehicle_hours: runs
+                        .iter()
+                        .map(|run| (run.arrival - run.departure) as f64 / 3600.0)
+                        .sum(),
+                    vehicle_km: runs.iter().map(|run| run.distance / 1000.0).sum(),
+                    headways,
+                }
+            })
+            .collect();
+
+        (routes, stops)
+    }
+
+    /// The runs of a trip, one per start time of its frequencies if any.
+    fn runs(&self, trip: &'t Trip) -> Vec<Run<'t>> {
+        let timed: Vec<(usize, u64, u64)> = trip
+            .stop_times
+            .iter()
+            .enumerate()
+            .filter_map(|(index, stop_time)| {
+                let arrival = stop_time.arrival_time.or(stop_time.departure_time)?;
+                let departure = stop_time.departure_time.or(stop_time.arrival_time)?;
+                Some((index, arrival, departure))
+            })
+            .collect();
+        let (first, last) = match (timed.first(), timed.last()) {
+            (Some(first), Some(last)) => (*first, *last),
+            _ => return Vec::new(),
+        };
+        let distance = TripPath::new(trip, &self.shapes).map_or(0.0, |path| {
+            path.stop_distances[last.0] - path.stop_distances[first.0]
+        });
+        let (departure, duration) = (first.2, last.1.saturating_sub(first.2));
+
+        let starts: Vec<u64> = match self.frequencies.get(trip.id.as_str()) {
+            Some(frequencies) => frequencies
+                .iter()
+                .flat_map(|frequency| frequency.start_times())
+                .collect(),
+            None => vec![departure],
+        };
+        starts
+            .into_iter()
+            .map(|start| Run {
+                departure: start,
+                arrival: start + duration,
+                distance,
+                calls: timed[..timed.len() - 1]
+                    .iter()
+                    .map(|(index, _, stop_departure)| {
+                        let stop_id = trip.stop_times[*index].stop.id.as_str();
+                        // Times going back along the trip are not shifted before its start
+                        (stop_id, start + stop_departure.saturating_sub(departure))
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::reader_from_files;
+
+    #[test]
+    fn statistics() {
+        let mut gtfs = reader_from_files(&[
+            (
+                "calendar.txt",
+                "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\n\
+                 WD,1,1,1,1,1,0,0,20240101,20241231\n",
+            ),
+            (
+                "stops.txt",
+                "stop_id,stop_name,stop_lat,stop_lon\nA,A,0,0\nB,B,0,0.1\n",
+            ),
+            (
+                "trips.txt",
+                "route_id,service_id,trip_id,direction_id\n\
+                 R,WD,out1,0\nR,WD,out2,0\nR,WD,in,1\nR,WD,shuttle,0\n",
+            ),
+            (
+                "stop_times.txt",
+                "trip_id,arrival_time,departure_time,stop_id,stop_sequence\n\
+                 out1,07:00:00,07:00:00,A,1\nout1,07:30:00,07:30:00,B,2\n\
+                 out2,07:20:00,07:20:00,A,1\nout2,07:50:00,07:50:00,B,2\n\
+                 in,17:00:00,17:00:00,B,1\nin,17:30:00,17:30:00,A,2\n\
+                 shuttle,10:00:00,10:00:00,A,1\nshuttle,10:30:00,10:30:00,B,2\n",
+            ),
+            (
+                "frequencies.txt",
+                "trip_id,start_time,end_time,headway_secs\nshuttle,10:00:00,11:00:00,1800\n",
+            ),
+        ]);
+        let trips = gtfs.trips().unwrap();
+        let frequencies = gtfs.frequencies().unwrap();
+        let calendar = gtfs.calendar().unwrap();
+        let statistics = ServiceStatistics::new(&trips, &frequencies, &calendar, &[], &[]);
+
+        // Monday to Sunday
+        let monday = NaiveDate::from_ymd_opt(2024, 3, 4).unwrap();
+        let table = statistics.between(monday, monday + Duration::days(6));
+        assert_eq!(table.rows.len(), 10);
+
+        let outbound = &table.rows[0];
+        assert_eq!(outbound.route_id, "R");
+        assert_eq!(outbound.direction_id, Some(Direction::Outbound));
+        assert_eq!(outbound.trips, 4);
+        assert_eq!(outbound.first_departure, 7 * 3600);
+        assert_eq!(outbound.last_departure, 10 * 3600 + 1800);
+        assert_eq!(outbound.span(), 4 * 3600);
+        assert_eq!(outbound.vehicle_hours, 2.0);
+        assert!((outbound.vehicle_km - 4.0 * 11.12).abs() < 0.1);
+        assert_eq!(
+            outbound.headways,
+            [None, Some(1200.0), Some(1800.0), None, None]
+        );
+        assert_eq!(table.rows[1].direction_id, Some(Direction::Inbound));
+        assert_eq!(table.rows[1].trips, 1);
+
+        let mut csv = Vec::new();
+        statistics.on(monday).write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.starts_with(
+            "date,route_id,direction_id,trips,first_departure,last_departure,last_arrival,span,\
+             vehicle_hours,vehicle_km,headway_early,headway_am_peak,headway_midday,\
+             headway_pm_peak,headway_evening\n\
+             20240304,R,0,4,07:00:00,10:30:00,11:00:00,04:00:00,2.00,44.48,,1200,1800,,\n"
+        ));
+
+        // Each run of the shuttle departs from A, the inbound trip only ends there
+        assert_eq!(table.stops.len(), 10);
+        let stop = &table.stops[0];
+        assert_eq!(stop.stop_id, "A");
+        assert_eq!(stop.trips, 4);
+        assert_eq!(stop.first_departure, 7 * 3600);
+        assert_eq!(stop.last_departure, 10 * 3600 + 1800);
+        assert_eq!(
+            stop.headways,
+            [None, Some(1200.0), Some(1800.0), None, None]
+        );
+
+        let mut csv = Vec::new();
+        statistics.on(monday).write_stops_csv(&mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "date,stop_id,trips,first_departure,last_departure,headway_early,headway_am_peak,\
+             headway_midday,headway_pm_peak,headway_evening\n\
+             20240304,A,4,07:00:00,10:30:00,,1200,1800,,\n\
+             20240304,B,1,17:00:00,17:00:00,,,,,\n"
+        );
+    }
+
+    #[test]
+    fn non_monotonic_trip() {
+        let mut gtfs = reader_from_files(&[
+            (
+                "calendar.txt",
+                "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\n\
+                 WD,1,1,1,1,1,0,0,20240101,20241231\n",
+            ),
+            ("stops.txt", "stop_id,stop_name\nA,A\nB,B\nC,C\n"),
+            ("trips.txt", "route_id,service_id,trip_id\nR,WD,T\n"),
+            (
+                "stop_times.txt",
+                "trip_id,arrival_time,departure_time,stop_id,stop_sequence\n\
+                 T,08:00:00,08:00:00,A,1\nT,07:50:00,07:50:00,B,2\nT,08:30:00,08:30:00,C,3\n",
+            ),
+        ]);
+        let trips = gtfs.trips().unwrap();
+        let calendar = gtfs.calendar().unwrap();
+        let statistics = ServiceStatistics::new(&trips, &[], &calendar, &[], &[]);
+
+        let table = statistics.on(NaiveDate::from_ymd_opt(2024, 3, 4).unwrap());
+        let departures: Vec<(&str, u64)> = table
+            .stops
+            .iter()
+            .map(|stop| (stop.stop_id, stop.first_departure))
+            .collect();
+        assert_eq!(departures, [("A", 8 * 3600), ("B", 8 * 3600)]);
+    }
+}
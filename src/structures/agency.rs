@@ -2,6 +2,8 @@ use serde::{Deserialize, Serialize};
 
 use crate::Id;
 
+use super::{Extras, Record};
+
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct Agency {
     /// Identifies a transit brandwhich is often synonymous with a transit agency.
@@ -46,6 +48,20 @@ pub struct Agency {
     /// This email address should be a direct contact point where transit riders can reach a customer service representative at the agency.
     #[serde(rename = "agency_email")]
     pub email: Option<String>,
+
+    /// Columns not defined by the GTFS reference, kept when the reader is asked to.
+    #[serde(skip)]
+    pub extras: Extras,
+}
+
+impl Record for Agency {
+    fn extras(&self) -> &Extras {
+        &self.extras
+    }
+
+    fn extras_mut(&mut self) -> &mut Extras {
+        &mut self.extras
+    }
 }
 
 impl Id for Agency {
@@ -3,6 +3,8 @@ use serde::{Deserialize, Serialize};
 
 use crate::Id;
 
+use super::{Extras, Record};
+
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct Attribution {
     /// Identifies an attribution for the dataset, or a subset of it.
@@ -63,6 +65,20 @@ pub struct Attribution {
     /// The phone number of the organization.
     #[serde(rename = "attribution_phone")]
     pub phone: Option<String>,
+
+    /// Columns not defined by the GTFS reference, kept when the reader is asked to.
+    #[serde(skip)]
+    pub extras: Extras,
+}
+
+impl Record for Attribution {
+    fn extras(&self) -> &Extras {
+        &self.extras
+    }
+
+    fn extras_mut(&mut self) -> &mut Extras {
+        &mut self.extras
+    }
 }
 
 impl Id for Attribution {
@@ -2,8 +2,14 @@ use crate::{
     gtfs_serde::{deserialize_bool, deserialize_date, serialize_bool, serialize_date},
     Id,
 };
-use chrono::NaiveDate;
+use chrono::{Datelike, NaiveDate, TimeZone, Utc, Weekday};
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use super::{
+    calendar_dates::{CalendarDate, Exception},
+    Extras, Record,
+};
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Calendar {
@@ -73,6 +79,20 @@ pub struct Calendar {
         serialize_with = "serialize_date"
     )]
     pub end_date: NaiveDate,
+
+    /// Columns not defined by the GTFS reference, kept when the reader is asked to.
+    #[serde(skip)]
+    pub extras: Extras,
+}
+
+impl Record for Calendar {
+    fn extras(&self) -> &Extras {
+        &self.extras
+    }
+
+    fn extras_mut(&mut self) -> &mut Extras {
+        &mut self.extras
+    }
 }
 
 impl Id for Calendar {
@@ -80,3 +100,66 @@ impl Id for Calendar {
         &self.id
     }
 }
+
+impl Calendar {
+    /// Whether the service runs on the given date, leaving the exceptions of calendar_dates.txt aside.
+    pub fn runs_on(&self, date: NaiveDate) -> bool {
+        if date < self.start_date || date > self.end_date {
+            return false;
+        }
+
+        match date.weekday() {
+            Weekday::Mon => self.monday,
+            Weekday::Tue => self.tuesday,
+            Weekday::Wed => self.wednesday,
+            Weekday::Thu => self.thursday,
+            Weekday::Fri => self.friday,
+            Weekday::Sat => self.saturday,
+            Weekday::Sun => self.sunday,
+        }
+    }
+}
+
+/// Ids of the services running on the given date, from calendar.txt and the exceptions
+/// of calendar_dates.txt.
+pub fn services_on(
+    calendar: &[Calendar],
+    calendar_dates: &[CalendarDate],
+    date: NaiveDate,
+) -> HashSet<String> {
+    let mut services: HashSet<String> = calendar
+        .iter()
+        .filter(|service| service.runs_on(date))
+        .map(|service| service.id.clone())
+        .collect();
+
+    for exception in calendar_dates
+        .iter()
+        .filter(|exception| exception.date == date)
+    {
+        match exception.exception_type {
+            Exception::Added => {
+                services.insert(exception.service_id.clone());
+            }
+            Exception::Deleted => {
+                services.remove(&exception.service_id);
+            }
+        }
+    }
+
+    services
+}
+
+/// POSIX time of the start of a service day, which is noon minus 12 hours in local time.
+/// GTFS times are relative to that instant, which differs from midnight on the days
+/// daylight saving time starts or ends.
+pub fn service_day_start<Tz: TimeZone>(date: NaiveDate, timezone: &Tz) -> i64 {
+    let noon = date.and_hms_opt(12, 0, 0).expect("noon is a valid time");
+    let noon = timezone
+        .from_local_datetime(&noon)
+        .earliest()
+        .map(|noon| noon.timestamp())
+        .unwrap_or_else(|| Utc.from_utc_datetime(&noon).timestamp());
+
+    noon - 12 * 3600
+}
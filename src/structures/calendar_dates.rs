@@ -2,6 +2,8 @@ use crate::gtfs_serde::{deserialize_date, serialize_date};
 use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 
+use super::{Extras, Record};
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct CalendarDate {
     /// Identifies a set of dates when a service exception occurs for one or more routes.
@@ -18,6 +20,20 @@ pub struct CalendarDate {
 
     /// Indicates whether service is available on the date specified in the date field.
     pub exception_type: Exception,
+
+    /// Columns not defined by the GTFS reference, kept when the reader is asked to.
+    #[serde(skip)]
+    pub extras: Extras,
+}
+
+impl Record for CalendarDate {
+    fn extras(&self) -> &Extras {
+        &self.extras
+    }
+
+    fn extras_mut(&mut self) -> &mut Extras {
+        &mut self.extras
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, Copy)]
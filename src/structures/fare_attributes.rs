@@ -3,6 +3,8 @@ use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use crate::Id;
 use derivative::Derivative;
 
+use super::{unknown_codes, Code, Extras, Record};
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FareAttribute {
     /// Identifies a fare class.
@@ -30,6 +32,10 @@ pub struct FareAttribute {
     /// Length of time in seconds before a transfer expires.
     /// When transfers=0 this field can be used to indicate how long a ticket is valid for or it can can be left empty.
     pub transfer_duration: Option<usize>,
+
+    /// Columns not defined by the GTFS reference, kept when the reader is asked to.
+    #[serde(skip)]
+    pub extras: Extras,
 }
 impl Id for FareAttribute {
     fn id(&self) -> &str {
@@ -37,6 +43,20 @@ impl Id for FareAttribute {
     }
 }
 
+impl Record for FareAttribute {
+    fn unknown_codes(&self) -> Vec<(&'static str, u16)> {
+        unknown_codes(&[("transfers", &self.transfers)])
+    }
+
+    fn extras(&self) -> &Extras {
+        &self.extras
+    }
+
+    fn extras_mut(&mut self) -> &mut Extras {
+        &mut self.extras
+    }
+}
+
 #[non_exhaustive]
 #[derive(Debug, Deserialize, Serialize, Copy, Clone, PartialEq)]
 pub enum PaymentMethod {
@@ -99,3 +119,12 @@ impl Serialize for Transfers {
         }
     }
 }
+
+impl Code for Transfers {
+    fn unknown(&self) -> Option<u16> {
+        match self {
+            Transfers::Other(i) => Some(*i),
+            _ => None,
+        }
+    }
+}
@@ -2,6 +2,8 @@ use serde::{Deserialize, Serialize};
 
 use crate::Id;
 
+use super::{Extras, Record};
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FareRule {
     /// Identifies a fare class.
@@ -23,6 +25,20 @@ pub struct FareRule {
     /// Identifies the zones that a rider will enter while using a given fare class.
     /// Used in some systems to calculate correct fare class.
     pub contains_id: Option<String>,
+
+    /// Columns not defined by the GTFS reference, kept when the reader is asked to.
+    #[serde(skip)]
+    pub extras: Extras,
+}
+
+impl Record for FareRule {
+    fn extras(&self) -> &Extras {
+        &self.extras
+    }
+
+    fn extras_mut(&mut self) -> &mut Extras {
+        &mut self.extras
+    }
 }
 
 impl Id for FareRule {
@@ -1,6 +1,9 @@
 use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 
+use super::{Extras, Record};
+use crate::gtfs_serde::{deserialize_option_date, serialize_option_date};
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FeedInfo {
     /// Full name of the organization that publishes the dataset.
@@ -33,11 +36,21 @@ pub struct FeedInfo {
     ///
     /// If feed_start_date or feed_end_date extend beyond the active calendar dates defined in calendar.txt and calendar_dates.txt,
     /// the dataset is making an explicit assertion that there is no service for dates within the feed_start_date to feed_end_date range but not included in the active calendar dates.
-    #[serde(rename = "feed_start_date", default)]
+    #[serde(
+        rename = "feed_start_date",
+        default,
+        deserialize_with = "deserialize_option_date",
+        serialize_with = "serialize_option_date"
+    )]
     pub start_date: Option<NaiveDate>,
 
-    #[serde(rename = "feed_end_date", default)]
     /// Refer to feed_start_date
+    #[serde(
+        rename = "feed_end_date",
+        default,
+        deserialize_with = "deserialize_option_date",
+        serialize_with = "serialize_option_date"
+    )]
     pub end_date: Option<NaiveDate>,
 
     /// String that indicates the current version of their GTFS dataset.
@@ -57,4 +70,18 @@ pub struct FeedInfo {
     /// information through agency.txt.
     #[serde(rename = "feed_contact_url")]
     pub contact_url: Option<String>,
+
+    /// Columns not defined by the GTFS reference, kept when the reader is asked to.
+    #[serde(skip)]
+    pub extras: Extras,
+}
+
+impl Record for FeedInfo {
+    fn extras(&self) -> &Extras {
+        &self.extras
+    }
+
+    fn extras_mut(&mut self) -> &mut Extras {
+        &mut self.extras
+    }
 }
@@ -2,6 +2,8 @@ use crate::gtfs_serde::{deserialize_time, serialize_time};
 use derivative::Derivative;
 use serde::{Deserialize, Serialize};
 
+use super::{Extras, Record};
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Frequency {
     /// Identifies a trip to which the specified headway of service applies.
@@ -29,6 +31,33 @@ pub struct Frequency {
     /// Indicates the type of service
     #[serde(default)]
     pub exact_times: ServiceType,
+
+    /// Columns not defined by the GTFS reference, kept when the reader is asked to.
+    #[serde(skip)]
+    pub extras: Extras,
+}
+
+impl Record for Frequency {
+    fn extras(&self) -> &Extras {
+        &self.extras
+    }
+
+    fn extras_mut(&mut self) -> &mut Extras {
+        &mut self.extras
+    }
+}
+
+impl Frequency {
+    /// Start times of the trips run with this headway, in seconds since the start of the service day.
+    ///
+    /// A headway of 0 is invalid, only the first trip is then considered to be run.
+    pub fn start_times(&self) -> impl Iterator<Item = u64> {
+        let step = match self.headway_secs {
+            0 => usize::MAX,
+            headway => headway as usize,
+        };
+        (self.start_time..self.end_time).step_by(step)
+    }
 }
 
 #[non_exhaustive]
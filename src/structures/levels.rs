@@ -2,6 +2,8 @@ use serde::{Deserialize, Serialize};
 
 use crate::Id;
 
+use super::{Extras, Record};
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Level {
     /// Id of the level that can be referenced from stops.txt.
@@ -20,7 +22,22 @@ pub struct Level {
     /// Is useful for elevator routing (e.g. “take the elevator to level “Mezzanine” or “Platforms” or “-1”).
     #[serde(rename = "level_name")]
     pub name: Option<String>,
+
+    /// Columns not defined by the GTFS reference, kept when the reader is asked to.
+    #[serde(skip)]
+    pub extras: Extras,
+}
+
+impl Record for Level {
+    fn extras(&self) -> &Extras {
+        &self.extras
+    }
+
+    fn extras_mut(&mut self) -> &mut Extras {
+        &mut self.extras
+    }
 }
+
 impl Id for Level {
     fn id(&self) -> &str {
         &self.id
@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 pub mod agency;
 pub mod attributions;
 pub mod calendar;
@@ -14,3 +16,32 @@ pub mod stop_times;
 pub mod stops;
 pub mod transfers;
 pub mod trips;
+
+/// Values of the columns not defined by the GTFS reference, by column name.
+pub type Extras = BTreeMap<String, String>;
+
+/// Behaviour shared by the records of the files defined by the GTFS reference.
+pub trait Record {
+    /// Columns not defined by the GTFS reference.
+    fn extras(&self) -> &Extras;
+
+    fn extras_mut(&mut self) -> &mut Extras;
+
+    /// Coded fields holding a value not defined by the GTFS reference, as (column name, value).
+    fn unknown_codes(&self) -> Vec<(&'static str, u16)> {
+        vec![]
+    }
+}
+
+/// Coded values, which can hold values not defined by the GTFS reference.
+pub(crate) trait Code {
+    /// The value, when it is not defined by the GTFS reference.
+    fn unknown(&self) -> Option<u16>;
+}
+
+pub(crate) fn unknown_codes(fields: &[(&'static str, &dyn Code)]) -> Vec<(&'static str, u16)> {
+    fields
+        .iter()
+        .filter_map(|(name, code)| Some((*name, code.unknown()?)))
+        .collect()
+}
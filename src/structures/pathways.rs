@@ -4,6 +4,8 @@ use crate::{
 };
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
+use super::{unknown_codes, Code, Extras, Record};
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Pathway {
     /// The pathway_id field contains an ID that uniquely identifies the pathway.
@@ -67,6 +69,10 @@ pub struct Pathway {
 
     /// Same than the signposted_as field, but when the pathways is used backward, i.e. from the to_stop_id to the from_stop_id.
     pub reversed_signposted_as: Option<String>,
+
+    /// Columns not defined by the GTFS reference, kept when the reader is asked to.
+    #[serde(skip)]
+    pub extras: Extras,
 }
 
 impl Id for Pathway {
@@ -75,6 +81,20 @@ impl Id for Pathway {
     }
 }
 
+impl Record for Pathway {
+    fn unknown_codes(&self) -> Vec<(&'static str, u16)> {
+        unknown_codes(&[("pathway_mode", &self.mode)])
+    }
+
+    fn extras(&self) -> &Extras {
+        &self.extras
+    }
+
+    fn extras_mut(&mut self) -> &mut Extras {
+        &mut self.extras
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 #[non_exhaustive]
 pub enum PathwayMode {
@@ -131,3 +151,12 @@ impl Serialize for PathwayMode {
         }
     }
 }
+
+impl Code for PathwayMode {
+    fn unknown(&self) -> Option<u16> {
+        match self {
+            PathwayMode::Other(i) => Some(*i),
+            _ => None,
+        }
+    }
+}
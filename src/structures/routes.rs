@@ -1,10 +1,13 @@
 use crate::{
-    gtfs_serde::{deserialize_option_color, serialize_option_color},
+    gtfs_serde::{deserialize_code, deserialize_option_color, serialize_option_color},
     Id,
 };
 use derivative::Derivative;
 use rgb::RGB8;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashSet;
+
+use super::{unknown_codes, Code, Extras, Record};
 
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct Route {
@@ -75,6 +78,10 @@ pub struct Route {
     /// The path is described by shapes.txt on every trip of the route.
     #[serde(default)]
     pub continuous_drop_off: ContinuousPickupDropOff,
+
+    /// Columns not defined by the GTFS reference, kept when the reader is asked to.
+    #[serde(skip)]
+    pub extras: Extras,
 }
 
 const fn black_rgb() -> Option<RGB8> {
@@ -95,6 +102,24 @@ impl Id for Route {
     }
 }
 
+impl Record for Route {
+    fn unknown_codes(&self) -> Vec<(&'static str, u16)> {
+        unknown_codes(&[
+            ("route_type", &self.route_type),
+            ("continuous_pickup", &self.continuous_pickup),
+            ("continuous_drop_off", &self.continuous_drop_off),
+        ])
+    }
+
+    fn extras(&self) -> &Extras {
+        &self.extras
+    }
+
+    fn extras_mut(&mut self) -> &mut Extras {
+        &mut self.extras
+    }
+}
+
 #[non_exhaustive]
 #[derive(Derivative)]
 #[derivative(Default)]
@@ -118,25 +143,24 @@ pub enum RouteType {
 }
 
 #[non_exhaustive]
-#[derive(Derivative, Debug, Serialize, Copy, Clone, PartialEq)]
+#[derive(Derivative, Debug, Copy, Clone, PartialEq)]
 #[derivative(Default)]
 pub enum ContinuousPickupDropOff {
-    #[serde(rename = "0")]
     /// Continuous stopping pickup.
     Continuous,
 
-    #[serde(rename = "1")]
     #[derivative(Default)]
     /// No continuous stopping pickup.
     NotAvailable,
 
-    #[serde(rename = "2")]
     /// Must phone an agency to arrange continuous pickup.
     ArrangeByPhone,
 
-    #[serde(rename = "3")]
     /// Must coordinate with a driver to arrange continuous stopping pickup.
     CoordinateWithDriver,
+
+    /// Value not defined by the GTFS reference.
+    Other(u16),
 }
 
 impl<'de> Deserialize<'de> for ContinuousPickupDropOff {
@@ -144,27 +168,54 @@ impl<'de> Deserialize<'de> for ContinuousPickupDropOff {
     where
         D: serde::Deserializer<'de>,
     {
-        let s: String = String::deserialize(deserializer)?;
-
-        Ok(match s.as_str() {
-            "0" => ContinuousPickupDropOff::Continuous,
-            "1" => ContinuousPickupDropOff::NotAvailable,
-            "2" => ContinuousPickupDropOff::ArrangeByPhone,
-            "3" => ContinuousPickupDropOff::CoordinateWithDriver,
-            _ => ContinuousPickupDropOff::NotAvailable,
+        Ok(match deserialize_code(deserializer)? {
+            Some(0) => ContinuousPickupDropOff::Continuous,
+            None | Some(1) => ContinuousPickupDropOff::NotAvailable,
+            Some(2) => ContinuousPickupDropOff::ArrangeByPhone,
+            Some(3) => ContinuousPickupDropOff::CoordinateWithDriver,
+            Some(other) => ContinuousPickupDropOff::Other(other),
         })
     }
 }
 
-impl<'de> Deserialize<'de> for RouteType {
-    fn deserialize<D>(deserializer: D) -> Result<RouteType, D::Error>
+impl Serialize for ContinuousPickupDropOff {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
-        D: Deserializer<'de>,
+        S: Serializer,
     {
-        let i = u16::deserialize(deserializer)?;
+        serializer.serialize_u16(match self {
+            ContinuousPickupDropOff::Continuous => 0,
+            ContinuousPickupDropOff::NotAvailable => 1,
+            ContinuousPickupDropOff::ArrangeByPhone => 2,
+            ContinuousPickupDropOff::CoordinateWithDriver => 3,
+            ContinuousPickupDropOff::Other(i) => *i,
+        })
+    }
+}
+
+impl Code for ContinuousPickupDropOff {
+    fn unknown(&self) -> Option<u16> {
+        match self {
+            ContinuousPickupDropOff::Other(i) => Some(*i),
+            _ => None,
+        }
+    }
+}
+
+impl Code for RouteType {
+    fn unknown(&self) -> Option<u16> {
+        match self {
+            RouteType::Other(i) => Some(*i),
+            _ => None,
+        }
+    }
+}
 
+impl From<u16> for RouteType {
+    /// Route type of a code, extended route types being mapped to their basic type.
+    fn from(i: u16) -> Self {
         let hundreds = i / 100;
-        Ok(match (i, hundreds) {
+        match (i, hundreds) {
             (0, _) | (_, 9) => RouteType::Tramway,
             (1, _) | (_, 4) => RouteType::Subway,
             (2, _) | (_, 1) => RouteType::Rail,
@@ -177,7 +228,16 @@ impl<'de> Deserialize<'de> for RouteType {
             (_, 11) => RouteType::Air,
             (_, 15) => RouteType::Taxi,
             _ => RouteType::Other(i),
-        })
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for RouteType {
+    fn deserialize<D>(deserializer: D) -> Result<RouteType, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        u16::deserialize(deserializer).map(RouteType::from)
     }
 }
 
@@ -203,3 +263,13 @@ impl Serialize for RouteType {
         })
     }
 }
+
+/// Agency of a route. As `agency_id` may only be left out when the feed has a single agency,
+/// a route without one belongs to the only agency of `agency_ids`, if there is exactly one.
+pub(crate) fn route_agency<'a>(route: &'a Route, agency_ids: &HashSet<&'a str>) -> Option<&'a str> {
+    match &route.agency_id {
+        Some(agency_id) => Some(agency_id),
+        None if agency_ids.len() == 1 => agency_ids.iter().next().copied(),
+        None => None,
+    }
+}
@@ -2,6 +2,8 @@ use serde::{Deserialize, Serialize};
 
 use crate::Id;
 
+use super::{Extras, Record};
+
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct Shape {
     /// Identifies a shape.
@@ -28,6 +30,20 @@ pub struct Shape {
     /// Distance units must be consistent with those used in stop_times.txt.
     #[serde(rename = "shape_dist_traveled")]
     pub dist_traveled: Option<f32>,
+
+    /// Columns not defined by the GTFS reference, kept when the reader is asked to.
+    #[serde(skip)]
+    pub extras: Extras,
+}
+
+impl Record for Shape {
+    fn extras(&self) -> &Extras {
+        &self.extras
+    }
+
+    fn extras_mut(&mut self) -> &mut Extras {
+        &mut self.extras
+    }
 }
 
 impl Id for Shape {
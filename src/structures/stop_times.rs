@@ -1,12 +1,13 @@
 use std::sync::Arc;
 
 use crate::gtfs_serde::{
-    deserialize_bool, deserialize_option_time, serialize_bool, serialize_option_time,
+    deserialize_bool, deserialize_code, deserialize_option_time, serialize_bool,
+    serialize_option_time,
 };
 use derivative::Derivative;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
 
-use super::{routes::ContinuousPickupDropOff, stops::Stop};
+use super::{routes::ContinuousPickupDropOff, stops::Stop, unknown_codes, Code, Extras, Record};
 
 #[derive(Debug, Serialize, Deserialize, Default, Derivative)]
 pub struct RawStopTime {
@@ -72,6 +73,29 @@ pub struct RawStopTime {
     )]
     /// Indicates if arrival and departure times for a stop are strictly adhered to by the vehicle or if they are instead approximate and/or interpolated times.
     pub timepoint: bool,
+
+    /// Columns not defined by the GTFS reference, kept when the reader is asked to.
+    #[serde(skip)]
+    pub extras: Extras,
+}
+
+impl Record for RawStopTime {
+    fn unknown_codes(&self) -> Vec<(&'static str, u16)> {
+        unknown_codes(&[
+            ("pickup_type", &self.pickup_type),
+            ("drop_off_type", &self.drop_off_type),
+            ("continuous_pickup", &self.continuous_pickup),
+            ("continuous_drop_off", &self.continuous_drop_off),
+        ])
+    }
+
+    fn extras(&self) -> &Extras {
+        &self.extras
+    }
+
+    fn extras_mut(&mut self) -> &mut Extras {
+        &mut self.extras
+    }
 }
 
 fn default_timepoint() -> bool {
@@ -91,6 +115,8 @@ pub struct StopTime {
     pub continuous_drop_off: ContinuousPickupDropOff,
     pub shape_dist_traveled: Option<f32>,
     pub timepoint: bool,
+    /// Columns of stop_times.txt not defined by the GTFS reference.
+    pub extras: Extras,
 }
 
 impl StopTime {
@@ -107,30 +133,30 @@ impl StopTime {
             continuous_drop_off: stop_time_gtfs.continuous_drop_off,
             shape_dist_traveled: stop_time_gtfs.shape_dist_traveled,
             timepoint: stop_time_gtfs.timepoint,
+            extras: stop_time_gtfs.extras.clone(),
         }
     }
 }
 
 #[non_exhaustive]
-#[derive(Derivative, Debug, Serialize, Copy, Clone, PartialEq)]
+#[derive(Derivative, Debug, Copy, Clone, PartialEq)]
 #[derivative(Default(bound = ""))]
 pub enum PickupDropOffType {
     #[derivative(Default)]
-    #[serde(rename = "0")]
     /// Regularly scheduled pickup/dropoff.
     Regular,
 
-    #[serde(rename = "1")]
     ///  No pickup/dropoff available.
     NotAvailable,
 
-    #[serde(rename = "2")]
     /// Must phone agency to arrange pickup/dropoff.
     ArrangeByPhone,
 
-    #[serde(rename = "3")]
     /// Must coordinate with driver to arrange pickup/dropoff.
     CoordinateWithDriver,
+
+    /// Value not defined by the GTFS reference.
+    Other(u16),
 }
 
 impl<'de> Deserialize<'de> for PickupDropOffType {
@@ -138,14 +164,36 @@ impl<'de> Deserialize<'de> for PickupDropOffType {
     where
         D: serde::Deserializer<'de>,
     {
-        let s: String = String::deserialize(deserializer)?;
-
-        Ok(match s.as_str() {
-            "0" => PickupDropOffType::Regular,
-            "1" => PickupDropOffType::NotAvailable,
-            "2" => PickupDropOffType::ArrangeByPhone,
-            "3" => PickupDropOffType::CoordinateWithDriver,
-            _ => PickupDropOffType::Regular,
+        Ok(match deserialize_code(deserializer)? {
+            None | Some(0) => PickupDropOffType::Regular,
+            Some(1) => PickupDropOffType::NotAvailable,
+            Some(2) => PickupDropOffType::ArrangeByPhone,
+            Some(3) => PickupDropOffType::CoordinateWithDriver,
+            Some(other) => PickupDropOffType::Other(other),
         })
     }
 }
+
+impl Serialize for PickupDropOffType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_u16(match self {
+            PickupDropOffType::Regular => 0,
+            PickupDropOffType::NotAvailable => 1,
+            PickupDropOffType::ArrangeByPhone => 2,
+            PickupDropOffType::CoordinateWithDriver => 3,
+            PickupDropOffType::Other(i) => *i,
+        })
+    }
+}
+
+impl Code for PickupDropOffType {
+    fn unknown(&self) -> Option<u16> {
+        match self {
+            PickupDropOffType::Other(i) => Some(*i),
+            _ => None,
+        }
+    }
+}
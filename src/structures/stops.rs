@@ -1,7 +1,10 @@
 use derivative::Derivative;
-use serde::{Deserialize, Deserializer, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{geometry::Point, gtfs_serde::deserialize_code, Id};
+
+use super::{unknown_codes, Code, Extras, Record};
 
-use crate::Id;
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct Stop {
     /// Identifies a stop, station, or station entrance.
@@ -62,6 +65,17 @@ pub struct Stop {
     /// This should be just the platform identifier (eg. G or 3).
     /// Words like "platform" or "track" (or the feed’s language-specific equivalent) should not be included.
     pub platform_code: Option<String>,
+
+    /// Columns not defined by the GTFS reference, kept when the reader is asked to.
+    #[serde(skip)]
+    pub extras: Extras,
+}
+
+impl Stop {
+    /// Coordinates of the stop, which are optional for generic nodes and boarding areas.
+    pub fn point(&self) -> Option<Point> {
+        Some(Point::new(self.latitude?, self.longitude?))
+    }
 }
 
 impl Id for Stop {
@@ -70,34 +84,54 @@ impl Id for Stop {
     }
 }
 
+impl Record for Stop {
+    fn unknown_codes(&self) -> Vec<(&'static str, u16)> {
+        unknown_codes(&[
+            ("location_type", &self.location_type),
+            ("wheelchair_boarding", &self.wheelchair_boarding),
+        ])
+    }
+
+    fn extras(&self) -> &Extras {
+        &self.extras
+    }
+
+    fn extras_mut(&mut self) -> &mut Extras {
+        &mut self.extras
+    }
+}
+
 #[non_exhaustive]
-#[derive(Debug, Copy, Clone, PartialEq, Serialize, Derivative)]
+#[derive(Debug, Copy, Clone, PartialEq, Derivative)]
 #[derivative(Default)]
 pub enum StopLocationType {
     #[derivative(Default)]
-    StopPoint = 0,
-    StopArea = 1,
-    StationEntrance = 2,
-    GenericNode = 3,
-    BoardingArea = 4,
+    StopPoint,
+    StopArea,
+    StationEntrance,
+    GenericNode,
+    BoardingArea,
+
+    /// Value not defined by the GTFS reference.
+    Other(u16),
 }
 
 #[non_exhaustive]
-#[derive(Debug, Copy, Clone, PartialEq, Serialize, Derivative)]
+#[derive(Debug, Copy, Clone, PartialEq, Derivative)]
 #[derivative(Default)]
 pub enum WheelchairBoardingAvailable {
-    #[serde(rename = "0")]
     #[derivative(Default)]
     /// No accessibility information for the trip.
     InformationNotAvailable,
 
-    #[serde(rename = "1")]
     /// Vehicle being used on this particular trip can accommodate at least one rider in a wheelchair.
     Available,
 
-    #[serde(rename = "2")]
     /// No riders in wheelchairs can be accommodated on this trip.
     NotAvailable,
+
+    /// Value not defined by the GTFS reference.
+    Other(u16),
 }
 
 impl<'de> Deserialize<'de> for WheelchairBoardingAvailable {
@@ -105,28 +139,75 @@ impl<'de> Deserialize<'de> for WheelchairBoardingAvailable {
     where
         D: Deserializer<'de>,
     {
-        let s: String = String::deserialize(deserializer)?;
-        Ok(match s.as_str() {
-            "0" => WheelchairBoardingAvailable::InformationNotAvailable,
-            "1" => WheelchairBoardingAvailable::Available,
-            "2" => WheelchairBoardingAvailable::NotAvailable,
-            _ => WheelchairBoardingAvailable::InformationNotAvailable,
+        Ok(match deserialize_code(deserializer)? {
+            None | Some(0) => WheelchairBoardingAvailable::InformationNotAvailable,
+            Some(1) => WheelchairBoardingAvailable::Available,
+            Some(2) => WheelchairBoardingAvailable::NotAvailable,
+            Some(other) => WheelchairBoardingAvailable::Other(other),
+        })
+    }
+}
+
+impl Serialize for WheelchairBoardingAvailable {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_u16(match self {
+            WheelchairBoardingAvailable::InformationNotAvailable => 0,
+            WheelchairBoardingAvailable::Available => 1,
+            WheelchairBoardingAvailable::NotAvailable => 2,
+            WheelchairBoardingAvailable::Other(i) => *i,
         })
     }
 }
 
+impl Code for WheelchairBoardingAvailable {
+    fn unknown(&self) -> Option<u16> {
+        match self {
+            WheelchairBoardingAvailable::Other(i) => Some(*i),
+            _ => None,
+        }
+    }
+}
+
 impl<'de> Deserialize<'de> for StopLocationType {
     fn deserialize<D>(deserializer: D) -> Result<StopLocationType, D::Error>
     where
         D: Deserializer<'de>,
     {
-        let s: String = String::deserialize(deserializer)?;
-        Ok(match s.as_str() {
-            "1" => StopLocationType::StopArea,
-            "2" => StopLocationType::StationEntrance,
-            "3" => StopLocationType::GenericNode,
-            "4" => StopLocationType::BoardingArea,
-            _ => StopLocationType::StopPoint,
+        Ok(match deserialize_code(deserializer)? {
+            None | Some(0) => StopLocationType::StopPoint,
+            Some(1) => StopLocationType::StopArea,
+            Some(2) => StopLocationType::StationEntrance,
+            Some(3) => StopLocationType::GenericNode,
+            Some(4) => StopLocationType::BoardingArea,
+            Some(other) => StopLocationType::Other(other),
+        })
+    }
+}
+
+impl Serialize for StopLocationType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_u16(match self {
+            StopLocationType::StopPoint => 0,
+            StopLocationType::StopArea => 1,
+            StopLocationType::StationEntrance => 2,
+            StopLocationType::GenericNode => 3,
+            StopLocationType::BoardingArea => 4,
+            StopLocationType::Other(i) => *i,
         })
     }
 }
+
+impl Code for StopLocationType {
+    fn unknown(&self) -> Option<u16> {
+        match self {
+            StopLocationType::Other(i) => Some(*i),
+            _ => None,
+        }
+    }
+}
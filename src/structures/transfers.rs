@@ -1,5 +1,8 @@
+use crate::gtfs_serde::deserialize_code;
 use derivative::Derivative;
-use serde::{Deserialize, Deserializer, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{unknown_codes, Code, Extras, Record};
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct Transfer {
@@ -11,36 +14,55 @@ pub struct Transfer {
     /// If this field refers to a station, the transfer rule applies to all child stops.
     pub to_stop_id: String,
 
+    /// Identifies a route where a connection begins.
+    /// If set, the transfer only applies to the trips of the route arriving at from_stop_id.
+    pub from_route_id: Option<String>,
+
+    /// Identifies a route where a connection ends.
+    /// If set, the transfer only applies to the trips of the route departing from to_stop_id.
+    pub to_route_id: Option<String>,
+
+    /// Identifies a trip where a connection between routes begins.
+    /// If set, the transfer only applies to this trip arriving at from_stop_id.
+    pub from_trip_id: Option<String>,
+
+    /// Identifies a trip where a connection between routes ends.
+    /// If set, the transfer only applies to this trip departing from to_stop_id.
+    pub to_trip_id: Option<String>,
+
     /// Indicates the type of connection for the specified (from_stop_id, to_stop_id) pair.
     pub transfer_type: TransferType,
 
     /// Amount of time, in seconds, that must be available to permit a transfer between routes at the specified stops.
     /// The min_transfer_time should be sufficient to permit a typical rider to move between the two stops, including buffer time to allow for schedule variance on each route.
     pub min_transfer_time: Option<u64>,
+
+    /// Columns not defined by the GTFS reference, kept when the reader is asked to.
+    #[serde(skip)]
+    pub extras: Extras,
 }
 
 #[non_exhaustive]
-#[derive(Serialize, Debug, Derivative, PartialEq, Eq, Hash, Clone, Copy)]
+#[derive(Debug, Derivative, PartialEq, Eq, Hash, Clone, Copy)]
 #[derivative(Default)]
 pub enum TransferType {
     #[derivative(Default)]
-    #[serde(rename = "0")]
     /// Recommended transfer point between routes.
     Recommended,
 
-    #[serde(rename = "1")]
     /// Timed transfer point between two routes.
     /// The departing vehicle is expected to wait for the arriving one and leave sufficient time for a rider to transfer between routes.
     Timed,
 
-    #[serde(rename = "2")]
     /// Transfer requires a minimum amount of time between arrival and departure to ensure a connection.
     /// The time required to transfer is specified by min_transfer_time.
     TimedMinimum,
 
-    #[serde(rename = "3")]
     /// Transfers are not possible between routes at the location.
     NotPossible,
+
+    /// Value not defined by the GTFS reference.
+    Other(u16),
 }
 
 impl<'de> Deserialize<'de> for TransferType {
@@ -48,13 +70,50 @@ impl<'de> Deserialize<'de> for TransferType {
     where
         D: Deserializer<'de>,
     {
-        let s: String = String::deserialize(deserializer)?;
-        Ok(match s.as_str() {
-            "0" => TransferType::Recommended,
-            "1" => TransferType::Timed,
-            "2" => TransferType::TimedMinimum,
-            "3" => TransferType::NotPossible,
-            _ => TransferType::Recommended,
+        Ok(match deserialize_code(deserializer)? {
+            None | Some(0) => TransferType::Recommended,
+            Some(1) => TransferType::Timed,
+            Some(2) => TransferType::TimedMinimum,
+            Some(3) => TransferType::NotPossible,
+            Some(other) => TransferType::Other(other),
         })
     }
 }
+
+impl Serialize for TransferType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_u16(match self {
+            TransferType::Recommended => 0,
+            TransferType::Timed => 1,
+            TransferType::TimedMinimum => 2,
+            TransferType::NotPossible => 3,
+            TransferType::Other(i) => *i,
+        })
+    }
+}
+
+impl Code for TransferType {
+    fn unknown(&self) -> Option<u16> {
+        match self {
+            TransferType::Other(i) => Some(*i),
+            _ => None,
+        }
+    }
+}
+
+impl Record for Transfer {
+    fn unknown_codes(&self) -> Vec<(&'static str, u16)> {
+        unknown_codes(&[("transfer_type", &self.transfer_type)])
+    }
+
+    fn extras(&self) -> &Extras {
+        &self.extras
+    }
+
+    fn extras_mut(&mut self) -> &mut Extras {
+        &mut self.extras
+    }
+}
@@ -1,13 +1,14 @@
 use std::{collections::HashMap, sync::Arc};
 
 use itertools::Itertools;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
 
-use crate::{error::Error, to_map, Id};
+use crate::{error::Error, gtfs_serde::deserialize_code, to_map, Id};
 
 use super::{
     stop_times::{RawStopTime, StopTime},
     stops::{Stop, WheelchairBoardingAvailable},
+    unknown_codes, Code, Extras, Record,
 };
 
 use derivative::Derivative;
@@ -49,6 +50,10 @@ pub struct RawTrip {
     /// Indicates whether bikes are allowed.
     #[serde(default)]
     pub bikes_allowed: BikesAllowed,
+
+    /// Columns not defined by the GTFS reference, kept when the reader is asked to.
+    #[serde(skip)]
+    pub extras: Extras,
 }
 
 impl Id for RawTrip {
@@ -57,6 +62,23 @@ impl Id for RawTrip {
     }
 }
 
+impl Record for RawTrip {
+    fn unknown_codes(&self) -> Vec<(&'static str, u16)> {
+        unknown_codes(&[
+            ("wheelchair_accessible", &self.wheelchair_accessible),
+            ("bikes_allowed", &self.bikes_allowed),
+        ])
+    }
+
+    fn extras(&self) -> &Extras {
+        &self.extras
+    }
+
+    fn extras_mut(&mut self) -> &mut Extras {
+        &mut self.extras
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct Trip {
     /// Identifies a route.
@@ -92,6 +114,9 @@ pub struct Trip {
 
     /// Linked stop times based off of stop_times.txt and stops.txt
     pub stop_times: Vec<StopTime>,
+
+    /// Columns of trips.txt not defined by the GTFS reference.
+    pub extras: Extras,
 }
 
 impl Id for Trip {
@@ -114,6 +139,7 @@ impl From<RawTrip> for Trip {
             wheelchair_accessible: rt.wheelchair_accessible,
             bikes_allowed: rt.bikes_allowed,
             stop_times: vec![],
+            extras: rt.extras,
         }
     }
 }
@@ -136,32 +162,56 @@ impl Trip {
             block_id: rt.block_id,
             wheelchair_accessible: rt.wheelchair_accessible,
             bikes_allowed: rt.bikes_allowed,
+            extras: rt.extras,
         }));
 
-        for raw_stop_time in raw_stop_times {
-            let trip = &mut trips
-                .get_mut(&raw_stop_time.trip_id)
-                .ok_or_else(|| Error::ReferenceError(raw_stop_time.trip_id.to_string()))?;
-
-            let stop = stops
-                .get(&raw_stop_time.stop_id)
-                .ok_or_else(|| Error::ReferenceError(raw_stop_time.stop_id.to_string()))?;
-
-            trip.stop_times
-                .push(StopTime::from(&raw_stop_time, Arc::clone(&stop)));
-        }
-
-        for trip in &mut trips.values_mut() {
-            trip.stop_times
-                .sort_by(|a, b| a.stop_sequence.cmp(&b.stop_sequence));
-        }
+        // The lines of the stop times in the file are not known
+        let stop_times = raw_stop_times
+            .into_iter()
+            .map(|stop_time| (None, stop_time));
+        link_stop_times(&mut trips, stop_times, stops)?;
 
-        let trips = trips.into_iter().map(|(_key, value)| value).collect_vec();
+        let trips = trips.into_values().collect_vec();
 
         Ok(trips)
     }
 }
 
+/// Attaches the stop times, along with their line in `stop_times.txt` when it is known, to
+/// their trips and sorts them by `stop_sequence`.
+pub(crate) fn link_stop_times(
+    trips: &mut HashMap<String, Trip>,
+    raw_stop_times: impl IntoIterator<Item = (Option<u64>, RawStopTime)>,
+    stops: &HashMap<String, Arc<Stop>>,
+) -> Result<(), Error> {
+    let reference_error =
+        |line: Option<u64>, field: &str, id: &str, parent_filename: &str| Error::ReferenceError {
+            filename: "stop_times.txt".to_owned(),
+            line,
+            field: field.to_owned(),
+            id: id.to_owned(),
+            parent_filename: parent_filename.to_owned(),
+        };
+
+    for (line, raw_stop_time) in raw_stop_times {
+        let trip = trips
+            .get_mut(&raw_stop_time.trip_id)
+            .ok_or_else(|| reference_error(line, "trip_id", &raw_stop_time.trip_id, "trips.txt"))?;
+
+        let stop = stops
+            .get(&raw_stop_time.stop_id)
+            .ok_or_else(|| reference_error(line, "stop_id", &raw_stop_time.stop_id, "stops.txt"))?;
+
+        trip.stop_times
+            .push(StopTime::from(&raw_stop_time, Arc::clone(stop)));
+    }
+
+    for trip in trips.values_mut() {
+        trip.stop_times.sort_by_key(|st| st.stop_sequence);
+    }
+
+    Ok(())
+}
 #[non_exhaustive]
 #[derive(Debug, Deserialize, Serialize, Copy, Clone, PartialEq)]
 pub enum Direction {
@@ -175,21 +225,21 @@ pub enum Direction {
 }
 
 #[non_exhaustive]
-#[derive(Debug, Derivative, Serialize, Copy, Clone, PartialEq)]
+#[derive(Debug, Derivative, Copy, Clone, PartialEq)]
 #[derivative(Default)]
 pub enum BikesAllowed {
     #[derivative(Default)]
-    #[serde(rename = "0")]
     /// No bike information for the trip.
     NoBikeInfo,
 
-    #[serde(rename = "1")]
     /// Vehicle being used on this particular trip can accommodate at least one bicycle.
     AtLeastOneBike,
 
-    #[serde(rename = "2")]
     /// No bicycles are allowed on this trip.
     NoBikesAllowed,
+
+    /// Value not defined by the GTFS reference.
+    Other(u16),
 }
 
 impl<'de> Deserialize<'de> for BikesAllowed {
@@ -197,13 +247,34 @@ impl<'de> Deserialize<'de> for BikesAllowed {
     where
         D: serde::Deserializer<'de>,
     {
-        let s: String = String::deserialize(deserializer)?;
+        Ok(match deserialize_code(deserializer)? {
+            None | Some(0) => BikesAllowed::NoBikeInfo,
+            Some(1) => BikesAllowed::AtLeastOneBike,
+            Some(2) => BikesAllowed::NoBikesAllowed,
+            Some(other) => BikesAllowed::Other(other),
+        })
+    }
+}
 
-        Ok(match s.as_str() {
-            "0" => BikesAllowed::NoBikeInfo,
-            "1" => BikesAllowed::AtLeastOneBike,
-            "2" => BikesAllowed::NoBikesAllowed,
-            _ => BikesAllowed::NoBikeInfo,
+impl Serialize for BikesAllowed {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_u16(match self {
+            BikesAllowed::NoBikeInfo => 0,
+            BikesAllowed::AtLeastOneBike => 1,
+            BikesAllowed::NoBikesAllowed => 2,
+            BikesAllowed::Other(i) => *i,
         })
     }
 }
+
+impl Code for BikesAllowed {
+    fn unknown(&self) -> Option<u16> {
+        match self {
+            BikesAllowed::Other(i) => Some(*i),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,531 @@
+//! Summary of a feed: its files, agencies, route types, geographic extent and the dates it
+//! covers.
+
+use std::collections::HashSet;
+
+use chrono::{Duration, NaiveDate};
+use serde::Serialize;
+
+use crate::{
+    error::Error,
+    geometry::Point,
+    structures::{
+        calendar::{self, Calendar},
+        calendar_dates::{CalendarDate, Exception},
+        routes::{route_agency, RouteType},
+    },
+    GtfsReader,
+};
+
+/// A file of the archive.
+#[derive(Debug, Clone, Serialize)]
+pub struct FileSummary {
+    /// Full name of the file within the archive
+    pub name: String,
+    /// Number of records, for csv files
+    pub records: Option<usize>,
+    /// Hexadecimal SHA-256 digest of the content of the file
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AgencySummary {
+    pub id: Option<String>,
+    pub name: String,
+    pub timezone: String,
+    pub routes: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RouteTypeSummary {
+    pub route_type: RouteType,
+    pub routes: usize,
+}
+
+/// The dates for which the feed is valid, according to feed_info.txt, and those on which
+/// its trips actually run.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Coverage {
+    pub feed_start_date: Option<NaiveDate>,
+    pub feed_end_date: Option<NaiveDate>,
+    /// First date on which a trip runs
+    pub service_start_date: Option<NaiveDate>,
+    /// Last date on which a trip runs
+    pub service_end_date: Option<NaiveDate>,
+    /// Dates without any trip, between the start and the end of the feed, or of its service
+    /// when feed_info.txt does not give them
+    pub days_without_service: Vec<NaiveDate>,
+}
+
+impl Coverage {
+    /// Mismatches between the validity of the feed and its service.
+    pub fn issues(&self) -> Vec<String> {
+        let mut issues = Vec::new();
+        if let (Some(feed), Some(service)) = (self.feed_start_date, self.service_start_date) {
+            if service > feed {
+                issues.push(format!(
+                    "Service starts on {}, after the feed start date {}",
+                    service, feed
+                ));
+            } else if service < feed {
+                issues.push(format!(
+                    "Service starts on {}, before the feed start date {}",
+                    service, feed
+                ));
+            }
+        }
+        if let (Some(feed), Some(service)) = (self.feed_end_date, self.service_end_date) {
+            if service < feed {
+                issues.push(format!(
+                    "Service ends on {}, before the feed end date {}",
+                    service, feed
+                ));
+            } else if service > feed {
+                issues.push(format!(
+                    "Service ends on {}, after the feed end date {}",
+                    service, feed
+                ));
+            }
+        }
+        if !self.days_without_service.is_empty() {
+            issues.push(format!(
+                "{} days without service",
+                self.days_without_service.len()
+            ));
+        }
+        issues
+    }
+}
+
+/// Description of a feed, as given by [GtfsReader::summary].
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FeedSummary {
+    pub files: Vec<FileSummary>,
+    pub agencies: Vec<AgencySummary>,
+    /// Route types by decreasing number of routes
+    pub route_types: Vec<RouteTypeSummary>,
+    /// South-west and north-east corners of the box enclosing the stops with coordinates
+    pub bounding_box: Option<(Point, Point)>,
+    /// Convex hull of the stops, counter-clockwise
+    pub convex_hull: Vec<Point>,
+    pub coverage: Coverage,
+}
+
+impl FeedSummary {
+    /// Number of records of a file, looked up by its file name.
+    pub fn records(&self, file_name: &str) -> Option<usize> {
+        self.files
+            .iter()
+            .find(|file| file.name.rsplit('/').next() == Some(file_name))
+            .and_then(|file| file.records)
+    }
+
+    pub fn to_json(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Human readable summary of the feed.
+    pub fn to_text(&self) -> String {
+        let mut text = format!("{} files\n", self.files.len());
+        for file in &self.files {
+            match file.records {
+                Some(records) => text.push_str(&format!(
+                    "    {}: {} records, sha256 {}\n",
+                    file.name, records, file.sha256
+                )),
+                None => text.push_str(&format!("    {}: sha256 {}\n", file.name, file.sha256)),
+            }
+        }
+
+        text.push_str(&format!("\n{} agencies\n", self.agencies.len()));
+        for agency in &self.agencies {
+            text.push_str(&format!(
+                "    {} ({}): {} routes\n",
+                agency.name, agency.timezone, agency.routes
+            ));
+        }
+
+        text.push_str("\nRoute types\n");
+        for route_type in &self.route_types {
+            text.push_str(&format!(
+                "    {:?}: {} routes\n",
+                route_type.route_type, route_type.routes
+            ));
+        }
+
+        if let Some((south_west, north_east)) = &self.bounding_box {
+            text.push_str(&format!(
+                "\nBounding box: {}, {} to {}, {} ({} points in the convex hull)\n",
+                south_west.latitude,
+                south_west.longitude,
+                north_east.latitude,
+                north_east.longitude,
+                self.convex_hull.len()
+            ));
+        }
+
+        let date = |date: Option<NaiveDate>| match date {
+            Some(date) => date.to_string(),
+            None => "-".to_owned(),
+        };
+        let coverage = &self.coverage;
+        text.push_str(&format!(
+            "\nFeed valid from {} to {}\nService from {} to {}\n",
+            date(coverage.feed_start_date),
+            date(coverage.feed_end_date),
+            date(coverage.service_start_date),
+            date(coverage.service_end_date)
+        ));
+        for issue in coverage.issues() {
+            text.push_str(&format!("    {}\n", issue));
+        }
+
+        text
+    }
+}
+
+pub(crate) fn summarize(gtfs: &mut GtfsReader) -> Result<FeedSummary, Error> {
+    let names: Vec<String> = gtfs.files().into_iter().map(ToOwned::to_owned).collect();
+    let mut files = Vec::new();
+    for name in names {
+        let content = gtfs.raw(&name)?;
+        let records = if name.ends_with(".txt") {
+            Some(count_records(&name, &content)?)
+        } else {
+            None
+        };
+        files.push(FileSummary {
+            records,
+            sha256: gtfs.file_hash(&name)?,
+            name,
+        });
+    }
+
+    let agencies = gtfs.optional_file("agency.txt", GtfsReader::agencies)?;
+    let routes = gtfs.optional_file("routes.txt", GtfsReader::routes)?;
+    let agency_ids = agencies.iter().filter_map(|a| a.id.as_deref()).collect();
+    let route_agencies: Vec<Option<&str>> = routes
+        .iter()
+        .map(|route| route_agency(route, &agency_ids))
+        .collect();
+    let agencies = agencies
+        .iter()
+        .map(|agency| {
+            let routes = route_agencies
+                .iter()
+                .filter(|&&route_agency| {
+                    route_agency == agency.id.as_deref()
+                        && (route_agency.is_some() || agencies.len() == 1)
+                })
+                .count();
+            AgencySummary {
+                id: agency.id.clone(),
+                name: agency.name.clone(),
+                timezone: agency.timezone.clone(),
+                routes,
+            }
+        })
+        .collect();
+
+    let mut route_types: Vec<RouteTypeSummary> = Vec::new();
+    for route in &routes {
+        match route_types
+            .iter_mut()
+            .find(|summary| summary.route_type == route.route_type)
+        {
+            Some(summary) => summary.routes += 1,
+            None => route_types.push(RouteTypeSummary {
+                route_type: route.route_type,
+                routes: 1,
+            }),
+        }
+    }
+    route_types.sort_by_key(|summary| std::cmp::Reverse(summary.routes));
+
+    let points: Vec<Point> = gtfs
+        .optional_file("stops.txt", GtfsReader::stops)?
+        .iter()
+        .filter_map(|stop| stop.point())
+        .collect();
+    let bounding_box = bounding_box(&points);
+    let convex_hull = convex_hull(points);
+
+    let feed_info = gtfs.optional_file("feed_info.txt", GtfsReader::feed_info)?;
+    let calendar = gtfs.optional_file("calendar.txt", GtfsReader::calendar)?;
+    let calendar_dates = gtfs.optional_file("calendar_dates.txt", GtfsReader::calendar_dates)?;
+    let services: Option<HashSet<String>> = if gtfs.has_file("trips.txt") {
+        Some(
+            gtfs.raw_trips()?
+                .into_iter()
+                .map(|trip| trip.service_id)
+                .collect(),
+        )
+    } else {
+        None
+    };
+    let mut coverage = service_coverage(&calendar, &calendar_dates, services.as_ref());
+    if let Some(feed_info) = feed_info.first() {
+        coverage.feed_start_date = feed_info.start_date;
+        coverage.feed_end_date = feed_info.end_date;
+    }
+    let start = coverage.feed_start_date.or(coverage.service_start_date);
+    let end = coverage.feed_end_date.or(coverage.service_end_date);
+    if let (Some(start), Some(end)) = (start, end) {
+        coverage.days_without_service = dates(start, end)
+            .filter(|date| !has_service(&calendar, &calendar_dates, services.as_ref(), *date))
+            .collect();
+    }
+
+    Ok(FeedSummary {
+        files,
+        agencies,
+        route_types,
+        bounding_box,
+        convex_hull,
+        coverage,
+    })
+}
+
+fn count_records(filename: &str, content: &[u8]) -> Result<usize, Error> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(content);
+    let mut records = 0;
+    for record in reader.byte_records() {
+        record.map_err(|source| Error::CSVError {
+            filename: filename.to_owned(),
+            source,
+            line_in_error: None,
+        })?;
+        records += 1;
+    }
+    Ok(records)
+}
+
+fn bounding_box(points: &[Point]) -> Option<(Point, Point)> {
+    let first = points.first()?;
+    let mut south_west = *first;
+    let mut north_east = *first;
+    for point in points {
+        south_west.latitude = south_west.latitude.min(point.latitude);
+        south_west.longitude = south_west.longitude.min(point.longitude);
+        north_east.latitude = north_east.latitude.max(point.latitude);
+        north_east.longitude = north_east.longitude.max(point.longitude);
+    }
+    Some((south_west, north_east))
+}
+
+/// Convex hull of points in the plane of their coordinates, with Andrew's monotone chain.
+fn convex_hull(mut points: Vec<Point>) -> Vec<Point> {
+    points.sort_by(|a, b| {
+        a.longitude
+            .total_cmp(&b.longitude)
+            .then(a.latitude.total_cmp(&b.latitude))
+    });
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+
+    let cross = |o: &Point, a: &Point, b: &Point| {
+        (a.longitude - o.longitude) * (b.latitude - o.latitude)
+            - (a.latitude - o.latitude) * (b.longitude - o.longitude)
+    };
+    let mut hull: Vec<Point> = Vec::with_capacity(points.len() * 2);
+    // Lower hull, then upper hull
+    for pass in 0..2 {
+        let start = hull.len();
+        let ordered: Box<dyn Iterator<Item = &Point>> = match pass {
+            0 => Box::new(points.iter()),
+            _ => Box::new(points.iter().rev()),
+        };
+        for point in ordered {
+            while hull.len() >= start + 2
+                && cross(&hull[hull.len() - 2], &hull[hull.len() - 1], point) <= 0.0
+            {
+                hull.pop();
+            }
+            hull.push(*point);
+        }
+        // The last point is the first of the other half
+        hull.pop();
+    }
+    hull
+}
+
+/// First and last dates on which some of the services run.
+fn service_coverage(
+    calendar: &[Calendar],
+    calendar_dates: &[CalendarDate],
+    services: Option<&HashSet<String>>,
+) -> Coverage {
+    let used = |service_id: &String| match services {
+        Some(services) => services.contains(service_id),
+        None => true,
+    };
+    let candidates = calendar
+        .iter()
+        .filter(|service| used(&service.id))
+        .flat_map(|service| [service.start_date, service.end_date])
+        .chain(
+            calendar_dates
+                .iter()
+                .filter(|date| date.exception_type == Exception::Added && used(&date.service_id))
+                .map(|date| date.date),
+        );
+    let (first, last) = match (candidates.clone().min(), candidates.max()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return Coverage::default(),
+    };
+
+    let mut running =
+        dates(first, last).filter(|date| has_service(calendar, calendar_dates, services, *date));
+    let service_start_date = running.next();
+    let service_end_date = running.last().or(service_start_date);
+    Coverage {
+        service_start_date,
+        service_end_date,
+        ..Default::default()
+    }
+}
+
+fn has_service(
+    calendar: &[Calendar],
+    calendar_dates: &[CalendarDate],
+    services: Option<&HashSet<String>>,
+    date: NaiveDate,
+) -> bool {
+    let running = calendar::services_on(calendar, calendar_dates, date);
+    match services {
+        Some(services) => running.iter().any(|service| services.contains(service)),
+        None => !running.is_empty(),
+    }
+}
+
+/// Dates from start to end, both included.
+fn dates(start: NaiveDate, end: NaiveDate) -> impl Iterator<Item = NaiveDate> {
+    let days = (end - start).num_days().max(-1) + 1;
+    (0..days).map(move |day| start + Duration::days(day))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::reader_from_files;
+
+    #[test]
+    fn summary() {
+        let mut gtfs = reader_from_files(&[
+            (
+                "agency.txt",
+                "agency_id,agency_name,agency_url,agency_timezone\n\
+                 X,Transit X,http://x.org,Europe/Paris\nY,Transit Y,http://y.org,Europe/Paris\n",
+            ),
+            (
+                "routes.txt",
+                "route_id,agency_id,route_short_name,route_long_name,route_type\n\
+                 R1,X,1,,3\nR2,X,2,,3\nR3,Y,3,,0\nR4,,4,,0\n",
+            ),
+            (
+                "stops.txt",
+                "stop_id,stop_name,stop_lat,stop_lon\n\
+                 A,A,0,0\nB,B,0,2\nC,C,2,2\nD,D,2,0\nE,E,1,1\nF,F,,\n",
+            ),
+            (
+                "trips.txt",
+                "route_id,service_id,trip_id\nR1,WD,T1\nR3,WE,T2\n",
+            ),
+            (
+                "calendar.txt",
+                "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\n\
+                 WD,1,1,1,1,1,0,0,20240304,20240315\n\
+                 WE,0,0,0,0,0,1,1,20240302,20240303\n\
+                 UNUSED,1,1,1,1,1,1,1,20240101,20241231\n",
+            ),
+            (
+                "calendar_dates.txt",
+                "service_id,date,exception_type\nWD,20240306,2\n",
+            ),
+            (
+                "feed_info.txt",
+                "feed_publisher_name,feed_publisher_url,feed_lang,feed_start_date,feed_end_date\n\
+                 X,http://x.org,fr,20240301,20240315\n",
+            ),
+            ("README", "Sample feed"),
+        ]);
+
+        let summary = gtfs.summary().unwrap();
+        assert_eq!(summary.files.len(), 8);
+        assert_eq!(summary.records("stops.txt"), Some(6));
+        assert_eq!(summary.records("calendar.txt"), Some(3));
+        assert_eq!(summary.records("README"), None);
+        assert_eq!(
+            summary.files[7].sha256,
+            "e868dabb06e3778f34794588da9efc7ff672532b82ad271a0931d32b377bdbcf"
+        );
+
+        let routes: Vec<(Option<&str>, usize)> = summary
+            .agencies
+            .iter()
+            .map(|agency| (agency.id.as_deref(), agency.routes))
+            .collect();
+        // R4 has no agency_id, which only identifies the agency of single-agency feeds
+        assert_eq!(routes, [(Some("X"), 2), (Some("Y"), 1)]);
+        assert_eq!(summary.route_types[0].route_type, RouteType::Bus);
+        assert_eq!(summary.route_types[0].routes, 2);
+        assert_eq!(summary.route_types[1].route_type, RouteType::Tramway);
+
+        assert_eq!(
+            summary.bounding_box,
+            Some((Point::new(0.0, 0.0), Point::new(2.0, 2.0)))
+        );
+        assert_eq!(
+            summary.convex_hull,
+            [
+                Point::new(0.0, 0.0),
+                Point::new(0.0, 2.0),
+                Point::new(2.0, 2.0),
+                Point::new(2.0, 0.0),
+            ]
+        );
+
+        // The unused service is not counted
+        let date = |day| NaiveDate::from_ymd_opt(2024, 3, day).unwrap();
+        let coverage = &summary.coverage;
+        assert_eq!(coverage.feed_start_date, Some(date(1)));
+        assert_eq!(coverage.feed_end_date, Some(date(15)));
+        assert_eq!(coverage.service_start_date, Some(date(2)));
+        assert_eq!(coverage.service_end_date, Some(date(15)));
+        assert_eq!(
+            coverage.days_without_service,
+            [date(1), date(6), date(9), date(10)]
+        );
+        assert_eq!(coverage.issues().len(), 2);
+
+        let text = summary.to_text();
+        assert!(text.contains("stops.txt: 6 records"));
+        assert!(text.contains("Service starts on 2024-03-02, after the feed start date 2024-03-01"));
+        let json: serde_json::Value = serde_json::from_str(&summary.to_json().unwrap()).unwrap();
+        assert_eq!(json["coverage"]["feed_start_date"], "2024-03-01");
+        assert_eq!(json["route_types"][0]["route_type"], 3);
+    }
+
+    #[test]
+    fn single_agency() {
+        let mut gtfs = reader_from_files(&[
+            (
+                "agency.txt",
+                "agency_name,agency_url,agency_timezone\n\
+                 Transit,http://transit.org,Europe/Paris\n",
+            ),
+            (
+                "routes.txt",
+                "route_id,route_short_name,route_long_name,route_type\nR1,1,,3\nR2,2,,3\n",
+            ),
+        ]);
+
+        let summary = gtfs.summary().unwrap();
+        assert_eq!(summary.agencies.len(), 1);
+        assert_eq!(summary.agencies[0].routes, 2);
+    }
+}
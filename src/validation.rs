@@ -0,0 +1,948 @@
+//! Validation of a GTFS archive.
+//!
+//! Findings are reported as [Notice]s whose codes and severities follow the ones of the
+//! canonical GTFS validator (https://github.com/MobilityData/gtfs-validator),
+//! so that a [ValidationReport] can be consumed by tools built around its JSON report.
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt,
+};
+
+use serde::{de::DeserializeOwned, Serialize, Serializer};
+use serde_json::Value;
+
+use crate::{
+    error::Error,
+    structures::{
+        agency::Agency, calendar::Calendar, calendar_dates::CalendarDate,
+        fare_attributes::FareAttribute, fare_rules::FareRule, frequencies::Frequency,
+        levels::Level, pathways::Pathway, routes::Route, stop_times::RawStopTime, stops::Stop,
+        transfers::Transfer, trips::RawTrip, Record,
+    },
+    GtfsReader, Id, Row, DATASET_FILES,
+};
+
+/// Maximum number of sample notices exported per notice code.
+const MAX_SAMPLE_NOTICES: usize = 100;
+
+const REQUIRED_FILES: [&str; 5] = [
+    "agency.txt",
+    "stops.txt",
+    "routes.txt",
+    "trips.txt",
+    "stop_times.txt",
+];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+pub enum Severity {
+    /// The dataset does not comply with the GTFS reference.
+    #[serde(rename = "ERROR")]
+    Error,
+
+    /// The dataset is valid, but likely does not match what the producer intended.
+    #[serde(rename = "WARNING")]
+    Warning,
+
+    /// Informational finding, nothing needs to be fixed.
+    #[serde(rename = "INFO")]
+    Info,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Error => "ERROR",
+            Severity::Warning => "WARNING",
+            Severity::Info => "INFO",
+        })
+    }
+}
+
+/// Stable identifier of a kind of finding.
+/// The string representation matches the notice codes of the canonical GTFS validator.
+#[non_exhaustive]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum NoticeCode {
+    /// A file required by the GTFS reference is missing from the archive.
+    MissingRequiredFile,
+
+    /// Neither calendar.txt nor calendar_dates.txt is present.
+    MissingCalendarAndCalendarDateFiles,
+
+    /// A file could not be read as CSV.
+    CsvParsingFailed,
+
+    /// A required column is missing or a required value is empty.
+    MissingRequiredField,
+
+    /// A value is not a valid integer.
+    InvalidInteger,
+
+    /// A value is not a valid float.
+    InvalidFloat,
+
+    /// A value is not a valid GTFS time (HH:MM:SS).
+    InvalidTime,
+
+    /// A value is not a valid GTFS date (YYYYMMDD).
+    InvalidDate,
+
+    /// A value is not a valid GTFS color (six digit hexadecimal).
+    InvalidColor,
+
+    /// A value could not be parsed for any other reason.
+    FieldParsingError,
+
+    /// A coded field holds a value not defined by the GTFS reference.
+    UnexpectedEnumValue,
+
+    /// A value references an id which is not defined in the referenced file.
+    ForeignKeyViolation,
+
+    /// An id which must be unique within its file is defined several times.
+    DuplicateKey,
+
+    /// A file which is not part of the GTFS reference is present in the archive.
+    UnknownFile,
+
+    /// A column which is not part of the GTFS reference is present in a file.
+    UnknownColumn,
+
+    /// The archive or one of its files could not be read.
+    IoError,
+
+    /// An unexpected error happened while loading the dataset.
+    RuntimeExceptionInLoader,
+}
+
+impl NoticeCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NoticeCode::MissingRequiredFile => "missing_required_file",
+            NoticeCode::MissingCalendarAndCalendarDateFiles => {
+                "missing_calendar_and_calendar_date_files"
+            }
+            NoticeCode::CsvParsingFailed => "csv_parsing_failed",
+            NoticeCode::MissingRequiredField => "missing_required_field",
+            NoticeCode::InvalidInteger => "invalid_integer",
+            NoticeCode::InvalidFloat => "invalid_float",
+            NoticeCode::InvalidTime => "invalid_time",
+            NoticeCode::InvalidDate => "invalid_date",
+            NoticeCode::InvalidColor => "invalid_color",
+            NoticeCode::FieldParsingError => "field_parsing_error",
+            NoticeCode::UnexpectedEnumValue => "unexpected_enum_value",
+            NoticeCode::ForeignKeyViolation => "foreign_key_violation",
+            NoticeCode::DuplicateKey => "duplicate_key",
+            NoticeCode::UnknownFile => "unknown_file",
+            NoticeCode::UnknownColumn => "unknown_column",
+            NoticeCode::IoError => "i_o_error",
+            NoticeCode::RuntimeExceptionInLoader => "runtime_exception_in_loader_error",
+        }
+    }
+
+    /// Severity the canonical validator assigns to this code.
+    pub fn severity(&self) -> Severity {
+        match self {
+            NoticeCode::UnexpectedEnumValue => Severity::Warning,
+            NoticeCode::UnknownFile | NoticeCode::UnknownColumn => Severity::Info,
+            _ => Severity::Error,
+        }
+    }
+}
+
+impl fmt::Display for NoticeCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for NoticeCode {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+/// A single validation finding.
+///
+/// When serialized, a notice produces the `sampleNotices` entries of the canonical validator report,
+/// its code and severity being carried by the enclosing group.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Notice {
+    #[serde(skip)]
+    pub code: NoticeCode,
+
+    #[serde(skip)]
+    pub severity: Severity,
+
+    /// File the finding relates to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filename: Option<String>,
+
+    /// 1-based row of the finding, the header being the first row.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub csv_row_number: Option<u64>,
+
+    /// Column the finding relates to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field_name: Option<String>,
+
+    /// Value found in the offending field.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field_value: Option<String>,
+
+    /// Code-specific context, such as the parent file of a foreign key violation.
+    #[serde(flatten)]
+    pub context: BTreeMap<String, Value>,
+}
+
+impl Notice {
+    pub fn new(code: NoticeCode) -> Self {
+        Self {
+            code,
+            severity: code.severity(),
+            filename: None,
+            csv_row_number: None,
+            field_name: None,
+            field_value: None,
+            context: BTreeMap::new(),
+        }
+    }
+
+    pub fn in_file(mut self, filename: &str) -> Self {
+        self.filename = Some(filename.to_owned());
+        self
+    }
+
+    pub fn at_row(mut self, csv_row_number: u64) -> Self {
+        self.csv_row_number = Some(csv_row_number);
+        self
+    }
+
+    pub fn with_field(mut self, name: &str, value: Option<&str>) -> Self {
+        self.field_name = Some(name.to_owned());
+        self.field_value = value.map(ToOwned::to_owned);
+        self
+    }
+
+    /// Value found in a field which is not known.
+    pub fn with_value(mut self, value: &str) -> Self {
+        self.field_value = Some(value.to_owned());
+        self
+    }
+
+    pub fn with_context<V: Into<Value>>(mut self, key: &str, value: V) -> Self {
+        self.context.insert(key.to_owned(), value.into());
+        self
+    }
+
+    /// Describes the notice in a single line, listing all its fields.
+    fn describe(&self) -> String {
+        match serde_json::to_value(self) {
+            Ok(Value::Object(fields)) => fields
+                .iter()
+                .map(|(key, value)| match value {
+                    Value::String(s) => format!("{}: {}", key, s),
+                    other => format!("{}: {}", key, other),
+                })
+                .collect::<Vec<_>>()
+                .join(", "),
+            _ => String::new(),
+        }
+    }
+}
+
+impl From<&Error> for Notice {
+    fn from(err: &Error) -> Self {
+        match err {
+            Error::FileNotFound(filename) => {
+                Notice::new(NoticeCode::MissingRequiredFile).in_file(filename)
+            }
+            Error::CSVError {
+                filename,
+                source,
+                line_in_error,
+            } => {
+                let mut notice = match source.kind() {
+                    csv::ErrorKind::Deserialize { err, .. } => {
+                        let (code, missing_field) = deserialize_error_code(err.kind());
+                        let mut notice = Notice::new(code);
+                        if let Some(field) = missing_field {
+                            notice = notice.with_field(&field, None);
+                        } else if let Some(line) = line_in_error {
+                            if let Some(column) = &line.column {
+                                notice = notice.with_field(column, line.value());
+                            }
+                        }
+                        if code == NoticeCode::FieldParsingError {
+                            notice = notice.with_context("message", err.kind().to_string());
+                        }
+                        notice
+                    }
+                    other => Notice::new(NoticeCode::CsvParsingFailed)
+                        .with_context("message", format!("{:?}", other)),
+                };
+                match (line_in_error, source.position()) {
+                    (Some(line), _) => notice = notice.at_row(line.line),
+                    (None, Some(position)) => notice = notice.at_row(position.line()),
+                    _ => {}
+                }
+                notice.in_file(filename)
+            }
+            Error::InvalidUtf8 {
+                filename,
+                line_in_error,
+            } => {
+                let mut notice = Notice::new(NoticeCode::CsvParsingFailed)
+                    .in_file(filename)
+                    .at_row(line_in_error.line)
+                    .with_context("message", "invalid UTF-8");
+                if let Some(column) = &line_in_error.column {
+                    notice = notice.with_field(column, line_in_error.value());
+                }
+                notice
+            }
+            Error::FileReadError { filename, source } => Notice::new(NoticeCode::IoError)
+                .in_file(filename)
+                .with_context("message", source.to_string()),
+            Error::Zip(_) | Error::IO(_) | Error::NotFileOrDirectory(_) => {
+                Notice::new(NoticeCode::IoError).with_context("message", err.to_string())
+            }
+            Error::InvalidColor(value) => Notice::new(NoticeCode::InvalidColor).with_value(value),
+            Error::InvalidTime(value) => Notice::new(NoticeCode::InvalidTime).with_value(value),
+            Error::InvalidDate(value) => Notice::new(NoticeCode::InvalidDate).with_value(value),
+            Error::UnknownCode {
+                filename,
+                line,
+                field,
+                value,
+            } => Notice::new(NoticeCode::UnexpectedEnumValue)
+                .in_file(filename)
+                .at_row(*line)
+                .with_field(field, Some(&value.to_string())),
+            Error::ReferenceError {
+                filename,
+                line,
+                field,
+                id,
+                parent_filename,
+            } => {
+                let notice = Notice::new(NoticeCode::ForeignKeyViolation)
+                    .with_field(field, Some(id))
+                    .with_context("childFilename", filename.as_str())
+                    .with_context("childFieldName", field.as_str())
+                    .with_context("parentFilename", parent_filename.as_str())
+                    .with_context("parentFieldName", field.as_str());
+                match line {
+                    Some(line) => notice.at_row(*line),
+                    None => notice,
+                }
+            }
+            _ => Notice::new(NoticeCode::RuntimeExceptionInLoader)
+                .with_context("message", err.to_string()),
+        }
+    }
+}
+
+/// Maps a field deserialization error to a notice code.
+/// For missing fields, the name of the field is returned as well since csv cannot locate it.
+fn deserialize_error_code(kind: &csv::DeserializeErrorKind) -> (NoticeCode, Option<String>) {
+    match kind {
+        csv::DeserializeErrorKind::ParseInt(_) => (NoticeCode::InvalidInteger, None),
+        csv::DeserializeErrorKind::ParseFloat(_) => (NoticeCode::InvalidFloat, None),
+        csv::DeserializeErrorKind::InvalidUtf8(_) => (NoticeCode::CsvParsingFailed, None),
+        csv::DeserializeErrorKind::UnexpectedEndOfRow => (NoticeCode::MissingRequiredField, None),
+        csv::DeserializeErrorKind::Message(message) => {
+            if let Some(field) = message.strip_prefix("missing field ") {
+                let field = field.trim_matches('`').to_owned();
+                (NoticeCode::MissingRequiredField, Some(field))
+            } else if message.ends_with("is not a valid time") {
+                (NoticeCode::InvalidTime, None)
+            } else if message.ends_with("is not a valid date") {
+                (NoticeCode::InvalidDate, None)
+            } else if message.ends_with("is not a valid color") {
+                (NoticeCode::InvalidColor, None)
+            } else if message.ends_with("is not a valid code") {
+                (NoticeCode::InvalidInteger, None)
+            } else {
+                (NoticeCode::FieldParsingError, None)
+            }
+        }
+        _ => (NoticeCode::FieldParsingError, None),
+    }
+}
+
+/// Findings of a validation run.
+#[derive(Debug, Clone, Default)]
+pub struct ValidationReport {
+    pub notices: Vec<Notice>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NoticeGroup<'a> {
+    code: NoticeCode,
+    severity: Severity,
+    total_notices: usize,
+    sample_notices: Vec<&'a Notice>,
+}
+
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    notices: Vec<NoticeGroup<'a>>,
+}
+
+impl ValidationReport {
+    pub fn push(&mut self, notice: Notice) {
+        self.notices.push(notice);
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.count(Severity::Error) > 0
+    }
+
+    pub fn count(&self, severity: Severity) -> usize {
+        self.notices
+            .iter()
+            .filter(|notice| notice.severity == severity)
+            .count()
+    }
+
+    /// Groups the notices by (severity, code), most severe first.
+    fn groups(&self) -> Vec<NoticeGroup<'_>> {
+        let mut groups: BTreeMap<(Severity, NoticeCode), Vec<&Notice>> = BTreeMap::new();
+        for notice in &self.notices {
+            groups
+                .entry((notice.severity, notice.code))
+                .or_default()
+                .push(notice);
+        }
+
+        groups
+            .into_iter()
+            .map(|((severity, code), notices)| NoticeGroup {
+                code,
+                severity,
+                total_notices: notices.len(),
+                sample_notices: notices.into_iter().take(MAX_SAMPLE_NOTICES).collect(),
+            })
+            .collect()
+    }
+
+    /// Exports the report in the JSON format of the canonical GTFS validator.
+    pub fn to_json(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string_pretty(&JsonReport {
+            notices: self.groups(),
+        })?)
+    }
+
+    /// Human readable summary of the report.
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "{} errors, {} warnings, {} infos\n",
+            self.count(Severity::Error),
+            self.count(Severity::Warning),
+            self.count(Severity::Info)
+        );
+
+        for group in self.groups() {
+            text.push_str(&format!(
+                "\n{} {} ({})\n",
+                group.severity, group.code, group.total_notices
+            ));
+            for notice in group.sample_notices {
+                text.push_str(&format!("    {}\n", notice.describe()));
+            }
+        }
+
+        text
+    }
+
+    /// Summary of the report as a standalone HTML page.
+    pub fn to_html(&self) -> String {
+        let mut html = String::from(
+            "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>GTFS validation report</title></head>\n<body>\n",
+        );
+        html.push_str(&format!(
+            "<h1>GTFS validation report</h1>\n<p>{} errors, {} warnings, {} infos</p>\n",
+            self.count(Severity::Error),
+            self.count(Severity::Warning),
+            self.count(Severity::Info)
+        ));
+
+        for group in self.groups() {
+            html.push_str(&format!(
+                "<h2>{} <code>{}</code> ({})</h2>\n<ul>\n",
+                group.severity, group.code, group.total_notices
+            ));
+            for notice in group.sample_notices {
+                html.push_str(&format!("<li>{}</li>\n", escape_html(&notice.describe())));
+            }
+            html.push_str("</ul>\n");
+        }
+
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+pub(crate) fn validate(reader: &mut GtfsReader) -> ValidationReport {
+    let mut report = ValidationReport::default();
+
+    for filename in REQUIRED_FILES.iter() {
+        if !reader.file_mappings.contains_key(*filename) {
+            report.push(Notice::new(NoticeCode::MissingRequiredFile).in_file(filename));
+        }
+    }
+    let has_calendar = reader.file_mappings.contains_key("calendar.txt");
+    let has_calendar_dates = reader.file_mappings.contains_key("calendar_dates.txt");
+    if !has_calendar && !has_calendar_dates {
+        report.push(Notice::new(NoticeCode::MissingCalendarAndCalendarDateFiles));
+    }
+
+    let unknown_files: Vec<String> = reader
+        .files()
+        .into_iter()
+        .filter_map(|name| std::path::Path::new(name).file_name()?.to_str())
+        .filter(|name| !DATASET_FILES.contains(name))
+        .map(ToOwned::to_owned)
+        .collect();
+    for filename in unknown_files {
+        report.push(Notice::new(NoticeCode::UnknownFile).in_file(&filename));
+    }
+
+    let agencies: Vec<(u64, Agency)> = parse(reader, "agency.txt", &mut report);
+    let stops: Vec<(u64, Stop)> = parse(reader, "stops.txt", &mut report);
+    let routes: Vec<(u64, Route)> = parse(reader, "routes.txt", &mut report);
+    let trips: Vec<(u64, RawTrip)> = parse(reader, "trips.txt", &mut report);
+    let stop_times: Vec<(u64, RawStopTime)> = parse(reader, "stop_times.txt", &mut report);
+    let calendar: Vec<(u64, Calendar)> = parse(reader, "calendar.txt", &mut report);
+    let calendar_dates: Vec<(u64, CalendarDate)> = parse(reader, "calendar_dates.txt", &mut report);
+    let fare_attributes: Vec<(u64, FareAttribute)> =
+        parse(reader, "fare_attributes.txt", &mut report);
+    let fare_rules: Vec<(u64, FareRule)> = parse(reader, "fare_rules.txt", &mut report);
+    let frequencies: Vec<(u64, Frequency)> = parse(reader, "frequencies.txt", &mut report);
+    let transfers: Vec<(u64, Transfer)> = parse(reader, "transfers.txt", &mut report);
+    let pathways: Vec<(u64, Pathway)> = parse(reader, "pathways.txt", &mut report);
+    let levels: Vec<(u64, Level)> = parse(reader, "levels.txt", &mut report);
+
+    let agency_ids = unique_ids(&mut report, "agency.txt", "agency_id", &agencies);
+    let stop_ids = unique_ids(&mut report, "stops.txt", "stop_id", &stops);
+    let route_ids = unique_ids(&mut report, "routes.txt", "route_id", &routes);
+    let trip_ids = unique_ids(&mut report, "trips.txt", "trip_id", &trips);
+    let fare_ids = unique_ids(
+        &mut report,
+        "fare_attributes.txt",
+        "fare_id",
+        &fare_attributes,
+    );
+    let level_ids = unique_ids(&mut report, "levels.txt", "level_id", &levels);
+    let service_ids: HashSet<&str> = calendar
+        .iter()
+        .map(|(_, c)| c.id.as_str())
+        .chain(calendar_dates.iter().map(|(_, c)| c.service_id.as_str()))
+        .collect();
+
+    let mut foreign_keys = ForeignKeys {
+        report: &mut report,
+        present: &reader.file_mappings,
+    };
+
+    foreign_keys.check(
+        ("routes.txt", "agency_id"),
+        ("agency.txt", "agency_id"),
+        &agency_ids,
+        routes
+            .iter()
+            .filter_map(|(line, r)| Some((*line, r.agency_id.as_deref()?))),
+    );
+    foreign_keys.check(
+        ("stops.txt", "parent_station"),
+        ("stops.txt", "stop_id"),
+        &stop_ids,
+        stops
+            .iter()
+            .filter_map(|(line, s)| Some((*line, s.parent_station.as_deref()?))),
+    );
+    foreign_keys.check(
+        ("stops.txt", "level_id"),
+        ("levels.txt", "level_id"),
+        &level_ids,
+        stops
+            .iter()
+            .filter_map(|(line, s)| Some((*line, s.level_id.as_deref()?))),
+    );
+    foreign_keys.check(
+        ("trips.txt", "route_id"),
+        ("routes.txt", "route_id"),
+        &route_ids,
+        trips.iter().map(|(line, t)| (*line, t.route_id.as_str())),
+    );
+    if has_calendar || has_calendar_dates {
+        foreign_keys.check(
+            ("trips.txt", "service_id"),
+            (
+                if has_calendar {
+                    "calendar.txt"
+                } else {
+                    "calendar_dates.txt"
+                },
+                "service_id",
+            ),
+            &service_ids,
+            trips.iter().map(|(line, t)| (*line, t.service_id.as_str())),
+        );
+    }
+    foreign_keys.check(
+        ("stop_times.txt", "trip_id"),
+        ("trips.txt", "trip_id"),
+        &trip_ids,
+        stop_times
+            .iter()
+            .map(|(line, st)| (*line, st.trip_id.as_str())),
+    );
+    foreign_keys.check(
+        ("stop_times.txt", "stop_id"),
+        ("stops.txt", "stop_id"),
+        &stop_ids,
+        stop_times
+            .iter()
+            .map(|(line, st)| (*line, st.stop_id.as_str())),
+    );
+    foreign_keys.check(
+        ("frequencies.txt", "trip_id"),
+        ("trips.txt", "trip_id"),
+        &trip_ids,
+        frequencies
+            .iter()
+            .map(|(line, f)| (*line, f.trip_id.as_str())),
+    );
+    foreign_keys.check(
+        ("fare_rules.txt", "fare_id"),
+        ("fare_attributes.txt", "fare_id"),
+        &fare_ids,
+        fare_rules.iter().map(|(line, f)| (*line, f.id.as_str())),
+    );
+    foreign_keys.check(
+        ("fare_rules.txt", "route_id"),
+        ("routes.txt", "route_id"),
+        &route_ids,
+        fare_rules
+            .iter()
+            .filter_map(|(line, f)| Some((*line, f.route_id.as_deref()?))),
+    );
+    for field in ["from_stop_id", "to_stop_id"].iter() {
+        foreign_keys.check(
+            ("transfers.txt", field),
+            ("stops.txt", "stop_id"),
+            &stop_ids,
+            transfers.iter().map(|(line, t)| {
+                let id = if *field == "from_stop_id" {
+                    &t.from_stop_id
+                } else {
+                    &t.to_stop_id
+                };
+                (*line, id.as_str())
+            }),
+        );
+        foreign_keys.check(
+            ("pathways.txt", field),
+            ("stops.txt", "stop_id"),
+            &stop_ids,
+            pathways.iter().map(|(line, p)| {
+                let id = if *field == "from_stop_id" {
+                    &p.from_stop_id
+                } else {
+                    &p.to_stop_id
+                };
+                (*line, id.as_str())
+            }),
+        );
+    }
+
+    report
+}
+
+/// Reads a file if present, turning every error and unknown code into a notice.
+fn parse<D: DeserializeOwned + Record>(
+    reader: &mut GtfsReader,
+    filename: &str,
+    report: &mut ValidationReport,
+) -> Vec<(u64, D)> {
+    let index = match reader.file_mappings.get(filename) {
+        Some(index) => *index,
+        None => return vec![],
+    };
+
+    let mut records = Vec::new();
+    let mut first_row = true;
+    let result = reader.read_records(filename, index, |row: Result<Row<D>, Error>| {
+        let row = match row {
+            Ok(row) => row,
+            Err(err) => {
+                report.push(notice_in(&err, filename));
+                return Ok(());
+            }
+        };
+
+        if first_row {
+            first_row = false;
+            for column in row.unknown_columns() {
+                report.push(
+                    Notice::new(NoticeCode::UnknownColumn)
+                        .in_file(filename)
+                        .with_field(&row.headers[column], None)
+                        .with_context("index", column),
+                );
+            }
+        }
+
+        for (field, value) in row.record.unknown_codes() {
+            report.push(Notice::from(&Error::UnknownCode {
+                filename: filename.to_owned(),
+                line: row.line,
+                field: field.to_owned(),
+                value,
+            }));
+        }
+        records.push((row.line, row.record));
+        Ok(())
+    });
+
+    if let Err(err) = result {
+        report.push(notice_in(&err, filename));
+    }
+
+    records
+}
+
+/// Notice of an error met while reading a file, which some errors do not name.
+fn notice_in(err: &Error, filename: &str) -> Notice {
+    let notice = Notice::from(err);
+    match notice.filename {
+        Some(_) => notice,
+        None => notice.in_file(filename),
+    }
+}
+
+/// Collects the ids of a file, reporting the duplicated ones.
+fn unique_ids<'a, T: Id>(
+    report: &mut ValidationReport,
+    filename: &str,
+    field_name: &str,
+    records: &'a [(u64, T)],
+) -> HashSet<&'a str> {
+    let mut first_rows: HashMap<&str, u64> = HashMap::new();
+    for (line, record) in records {
+        match first_rows.get(record.id()) {
+            Some(old_line) => report.push(
+                Notice::new(NoticeCode::DuplicateKey)
+                    .in_file(filename)
+                    .at_row(*line)
+                    .with_field(field_name, Some(record.id()))
+                    .with_context("oldCsvRowNumber", *old_line),
+            ),
+            None => {
+                first_rows.insert(record.id(), *line);
+            }
+        }
+    }
+    first_rows.into_keys().collect()
+}
+
+struct ForeignKeys<'a> {
+    report: &'a mut ValidationReport,
+    present: &'a HashMap<String, usize>,
+}
+
+impl ForeignKeys<'_> {
+    /// Reports every value that is not part of `parent_ids`.
+    /// The check is skipped when the parent file is missing, which is reported on its own.
+    fn check<'a>(
+        &mut self,
+        (child_filename, child_field): (&str, &str),
+        (parent_filename, parent_field): (&str, &str),
+        parent_ids: &HashSet<&str>,
+        values: impl Iterator<Item = (u64, &'a str)>,
+    ) {
+        if !self.present.contains_key(parent_filename) {
+            return;
+        }
+
+        for (line, value) in values {
+            if value.is_empty() || parent_ids.contains(value) {
+                continue;
+            }
+            self.report.push(
+                Notice::new(NoticeCode::ForeignKeyViolation)
+                    .at_row(line)
+                    .with_field(child_field, Some(value))
+                    .with_context("childFilename", child_filename)
+                    .with_context("childFieldName", child_field)
+                    .with_context("parentFilename", parent_filename)
+                    .with_context("parentFieldName", parent_field),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::test::reader_from_files;
+
+    #[test]
+    fn validate_zip() {
+        let mut gtfs = GtfsReader::from_path(PathBuf::from("./resources/zips/gtfs.zip")).unwrap();
+        let report = gtfs.validate();
+
+        let duplicate = report
+            .notices
+            .iter()
+            .find(|n| n.code == NoticeCode::DuplicateKey)
+            .expect("duplicate route");
+        assert_eq!(duplicate.filename, Some("routes.txt".to_string()));
+        assert_eq!(duplicate.csv_row_number, Some(3));
+        assert_eq!(duplicate.field_value, Some("A".to_string()));
+
+        let violation = report
+            .notices
+            .iter()
+            .find(|n| {
+                n.code == NoticeCode::ForeignKeyViolation
+                    && n.context.get("childFieldName") == Some(&Value::from("route_id"))
+            })
+            .expect("unknown route");
+        assert_eq!(violation.field_value, Some("route1".to_string()));
+        assert_eq!(violation.csv_row_number, Some(2));
+        // The same as when the reader finds it
+        let reference = Notice::from(&Error::ReferenceError {
+            filename: "trips.txt".to_owned(),
+            line: Some(2),
+            field: "route_id".to_owned(),
+            id: "route1".to_owned(),
+            parent_filename: "routes.txt".to_owned(),
+        });
+        assert_eq!(
+            serde_json::to_value(&reference).unwrap(),
+            serde_json::to_value(violation).unwrap()
+        );
+        assert_eq!(
+            Notice::from(&Error::InvalidColor("red".to_owned())).field_value,
+            Some("red".to_string())
+        );
+        assert!(report
+            .notices
+            .iter()
+            .any(|n| n.code == NoticeCode::UnknownColumn
+                && n.filename == Some("trips.txt".to_string())
+                && n.field_name == Some("brigade_id".to_string())));
+        assert!(report.has_errors());
+    }
+
+    #[test]
+    fn field_errors() {
+        let mut gtfs = reader_from_files(&[
+            (
+                "agency.txt",
+                "agency_name,agency_url,agency_timezone\nAgency,http://agency.com,UTC\n",
+            ),
+            (
+                "calendar_dates.txt",
+                "service_id,date,exception_type\ns1,2021-01-01,1\n",
+            ),
+            (
+                "routes.txt",
+                "route_id,route_short_name,route_long_name,route_type\nr1,1,One,three\n",
+            ),
+            (
+                "stop_times.txt",
+                "trip_id,arrival_time,departure_time,stop_id,stop_sequence\nt1,25:00,25:00:00,s1,1\n",
+            ),
+            ("stops.txt", "stop_id,stop_name,location_type\ns1,Stop,9\n"),
+            ("trips.txt", "route_id,service_id\nr1,s1\n"),
+            ("README.md", "hello"),
+        ]);
+        let report = gtfs.validate();
+
+        let find = |code| {
+            report
+                .notices
+                .iter()
+                .find(|n| n.code == code)
+                .unwrap_or_else(|| panic!("missing {}", code))
+        };
+
+        let date = find(NoticeCode::InvalidDate);
+        assert_eq!(date.filename, Some("calendar_dates.txt".to_string()));
+        assert_eq!(date.csv_row_number, Some(2));
+        assert_eq!(date.field_name, Some("date".to_string()));
+        assert_eq!(date.field_value, Some("2021-01-01".to_string()));
+
+        let time = find(NoticeCode::InvalidTime);
+        assert_eq!(time.field_name, Some("arrival_time".to_string()));
+        assert_eq!(time.field_value, Some("25:00".to_string()));
+
+        let route_type = find(NoticeCode::InvalidInteger);
+        assert_eq!(route_type.field_name, Some("route_type".to_string()));
+
+        let trip_id = find(NoticeCode::MissingRequiredField);
+        assert_eq!(trip_id.filename, Some("trips.txt".to_string()));
+        assert_eq!(trip_id.field_name, Some("trip_id".to_string()));
+
+        let location_type = find(NoticeCode::UnexpectedEnumValue);
+        assert_eq!(location_type.severity, Severity::Warning);
+        assert_eq!(location_type.field_name, Some("location_type".to_string()));
+        assert_eq!(location_type.field_value, Some("9".to_string()));
+
+        let unknown = find(NoticeCode::UnknownFile);
+        assert_eq!(unknown.severity, Severity::Info);
+        assert_eq!(unknown.filename, Some("README.md".to_string()));
+    }
+
+    #[test]
+    fn json_report() {
+        let mut report = ValidationReport::default();
+        report.push(Notice::new(NoticeCode::MissingRequiredFile).in_file("stops.txt"));
+        report.push(Notice::new(NoticeCode::MissingRequiredFile).in_file("trips.txt"));
+        report.push(Notice::new(NoticeCode::UnknownFile).in_file("notes.txt"));
+
+        let json: Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "notices": [
+                    {
+                        "code": "missing_required_file",
+                        "severity": "ERROR",
+                        "totalNotices": 2,
+                        "sampleNotices": [{"filename": "stops.txt"}, {"filename": "trips.txt"}]
+                    },
+                    {
+                        "code": "unknown_file",
+                        "severity": "INFO",
+                        "totalNotices": 1,
+                        "sampleNotices": [{"filename": "notes.txt"}]
+                    }
+                ]
+            })
+        );
+
+        let text = report.to_text();
+        assert!(text.starts_with("2 errors, 0 warnings, 1 infos"));
+        assert!(text.contains("ERROR missing_required_file (2)"));
+        assert!(report.to_html().contains("<code>unknown_file</code>"));
+    }
+}
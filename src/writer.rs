@@ -0,0 +1,154 @@
+//! Writing records back to GTFS files.
+use std::io;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{error::Error, structures::Record};
+
+/// Writes records as a GTFS csv file.
+///
+/// Columns are written in the order of the record fields, followed by the columns kept in the
+/// records' extras, so that a file read with [keep_extras](crate::GtfsReader::keep_extras)
+/// is written back with its non-standard columns.
+///
+/// ```
+/// use gtfs_parser::{writer::write_records, GtfsReader};
+///
+/// let mut gtfs = GtfsReader::from_path("./resources/zips/gtfs.zip")
+///     .unwrap()
+///     .keep_extras(true);
+/// let trips = gtfs.raw_trips().unwrap();
+///
+/// let mut output = Vec::new();
+/// write_records(&mut output, &trips).unwrap();
+///
+/// let output = String::from_utf8(output).unwrap();
+/// assert!(output.starts_with("route_id,trip_id,service_id"));
+/// assert!(output.contains("010/51"));
+/// ```
+pub fn write_records<W: io::Write, T: Serialize + Record>(
+    writer: W,
+    records: &[T],
+) -> Result<(), Error> {
+    let rows = records
+        .iter()
+        .map(|record| match serde_json::to_value(record)? {
+            Value::Object(mut fields) => {
+                for (column, value) in record.extras() {
+                    fields
+                        .entry(column.as_str())
+                        .or_insert_with(|| Value::String(value.clone()));
+                }
+                Ok(fields)
+            }
+            _ => Err(Error::NotARecord),
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    // Extras can differ between records, the header is the union of all the columns
+    let mut headers: Vec<&str> = Vec::new();
+    for row in &rows {
+        for column in row.keys() {
+            if !headers.contains(&column.as_str()) {
+                headers.push(column);
+            }
+        }
+    }
+
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record(&headers)?;
+    for row in &rows {
+        writer.write_record(
+            headers
+                .iter()
+                .map(|header| row.get(*header).map(to_cell).unwrap_or_default()),
+        )?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn to_cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::Bool(true) => "1".to_owned(),
+        Value::Bool(false) => "0".to_owned(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::{
+        structures::{stop_times::RawStopTime, trips::RawTrip},
+        test::reader_from_files,
+        GtfsReader,
+    };
+
+    #[test]
+    fn serialize_with_csv() {
+        let mut gtfs = GtfsReader::from_path(PathBuf::from("./resources/zips/gtfs.zip"))
+            .unwrap()
+            .keep_extras(true);
+        let stops = gtfs.stops().unwrap();
+        let trips = gtfs.raw_trips().unwrap();
+
+        // Records are plain structs, whose extras are left out
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        for stop in &stops {
+            writer.serialize(stop).unwrap();
+        }
+        let output = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+        assert!(output.starts_with("stop_id,"));
+        assert_eq!(output.lines().count(), stops.len() + 1);
+
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.serialize(&trips[0]).unwrap();
+        let output = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+        assert!(output.starts_with("route_id,trip_id"));
+        assert!(!output.contains("010/51"));
+    }
+
+    #[test]
+    fn round_trip_extras() {
+        let mut gtfs = GtfsReader::from_path(PathBuf::from("./resources/zips/gtfs.zip"))
+            .unwrap()
+            .keep_extras(true);
+        let trips = gtfs.raw_trips().unwrap();
+        let stop_times = gtfs.raw_stop_times().unwrap();
+
+        let mut trips_txt = Vec::new();
+        write_records(&mut trips_txt, &trips).unwrap();
+        let mut stop_times_txt = Vec::new();
+        write_records(&mut stop_times_txt, &stop_times).unwrap();
+
+        let mut written = reader_from_files(&[
+            ("trips.txt", std::str::from_utf8(&trips_txt).unwrap()),
+            (
+                "stop_times.txt",
+                std::str::from_utf8(&stop_times_txt).unwrap(),
+            ),
+        ])
+        .keep_extras(true);
+        let written_trips: Vec<RawTrip> = written.raw_trips().unwrap();
+        let written_stop_times: Vec<RawStopTime> = written.raw_stop_times().unwrap();
+
+        assert_eq!(written_trips[0].id, trips[0].id);
+        assert_eq!(written_trips[0].headsign, trips[0].headsign);
+        assert_eq!(written_trips[0].extras, trips[0].extras);
+        assert_eq!(written_trips[0].extras["brigade_id"], "010/51");
+
+        assert_eq!(
+            written_stop_times[0].arrival_time,
+            stop_times[0].arrival_time
+        );
+        assert_eq!(written_stop_times[0].pickup_type, stop_times[0].pickup_type);
+        assert_eq!(written_stop_times[0].extras, stop_times[0].extras);
+    }
+}